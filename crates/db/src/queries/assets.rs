@@ -42,6 +42,44 @@ pub async fn insert_asset(
     })
 }
 
+#[derive(Debug, sqlx::FromRow)]
+pub struct AssetDetailRow {
+    pub id: String,
+    pub project_id: String,
+    /// Folder of the project, relative to the library root.
+    pub folder_path: String,
+    pub file_path: String,
+    pub kind: String,
+    pub mime: String,
+    pub size_bytes: i64,
+    pub mtime: String,
+    pub file_hash: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub thumbnail_hash: Option<String>,
+    pub metadata_json: Option<String>,
+}
+
+pub async fn get_asset(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+    asset_id: &str,
+) -> Result<Option<AssetDetailRow>, sqlx::Error> {
+    sqlx::query_as::<_, AssetDetailRow>(
+        r#"
+        SELECT a.id, a.project_id, p.folder_path, a.file_path, a.kind, a.mime, a.size_bytes, a.mtime, a.file_hash,
+               a.created_at, a.updated_at, a.thumbnail_hash, a.metadata_json
+        FROM assets a
+        JOIN projects p ON p.id = a.project_id
+        WHERE a.id = ?1 AND a.project_id = ?2
+        "#,
+    )
+    .bind(asset_id)
+    .bind(project_id)
+    .fetch_optional(pool)
+    .await
+}
+
 pub enum DeleteAssetError {
     NotFound { project_id: String },
     Db(sqlx::Error),
@@ -117,9 +155,84 @@ pub async fn delete_asset(
 
     tx.commit().await.map_err(DeleteAssetError::Db)?;
 
+    // Folders left empty by the delete go too, stopping at the project folder. remove_dir fails
+    // on the first one that still has something in it.
+    let project_dir: PathBuf = ["data", "library", &project_path].iter().collect();
+    for dir in full_asset_path.ancestors().skip(1).take_while(|dir| *dir != project_dir.as_path()) {
+        if tokio::fs::remove_dir(dir).await.is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn set_thumbnail_hash(
+    pool: &Pool<Sqlite>,
+    asset_id: &str,
+    thumbnail_hash: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE assets SET thumbnail_hash = ?1 WHERE id = ?2"#,
+    )
+    .bind(thumbnail_hash)
+    .bind(asset_id)
+    .execute(pool)
+    .await?;
+
     Ok(())
 }
 
+/// Stores what was read from the file, `None` when it has nothing, and the version it was read from.
+pub async fn set_asset_metadata(
+    pool: &Pool<Sqlite>,
+    asset_id: &str,
+    metadata_json: Option<&str>,
+    metadata_hash: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE assets SET metadata_json = ?1, metadata_hash = ?2 WHERE id = ?3"#,
+    )
+    .bind(metadata_json)
+    .bind(metadata_hash)
+    .bind(asset_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Gives a project without main image one: its first image, or else the first model, then G-code,
+/// with a preview. Returns whether the project changed.
+pub async fn fill_missing_main_image(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+    now: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE projects
+        SET main_image_id = candidate.id,
+            updated_at = ?2
+        FROM (
+            SELECT id
+            FROM assets
+            WHERE project_id = ?1
+              AND (kind = 'image' OR (kind IN ('model', 'gcode') AND thumbnail_hash IS NOT NULL))
+            ORDER BY kind = 'image' DESC, kind = 'model' DESC, file_path
+            LIMIT 1
+        ) AS candidate
+        WHERE projects.id = ?1 AND projects.main_image_id IS NULL
+        "#,
+    )
+    .bind(project_id)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
 pub async fn set_project_main_image(
     tx: &mut Transaction<'_, Sqlite>,
     project_id: &str,
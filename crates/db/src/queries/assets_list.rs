@@ -0,0 +1,111 @@
+use lima_domain::pagination::Cursor;
+use sqlx::{FromRow, Pool, Sqlite};
+
+#[derive(Debug, FromRow)]
+pub struct AssetListRow {
+    pub id: String,
+    pub project_id: String,
+    pub file_path: String,
+    pub kind: String,
+    pub mime: String,
+    pub size_bytes: i64,
+    pub mtime: String,
+    pub thumbnail_hash: Option<String>,
+    pub metadata_json: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetSort {
+    /// By `file_path`.
+    Name,
+    Size,
+    Mtime,
+}
+
+impl AssetSort {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "name" => Some(AssetSort::Name),
+            "size" => Some(AssetSort::Size),
+            "mtime" => Some(AssetSort::Mtime),
+            _ => None,
+        }
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            AssetSort::Name => "a.file_path",
+            AssetSort::Size => "a.size_bytes",
+            AssetSort::Mtime => "a.mtime",
+        }
+    }
+
+    /// Cursor after `row`. The sort value goes in `updated_at`, or in `rank` for sizes.
+    pub fn cursor_after(&self, row: &AssetListRow) -> Cursor {
+        let (updated_at, rank) = match self {
+            AssetSort::Name => (row.file_path.clone(), None),
+            AssetSort::Size => (String::new(), Some(row.size_bytes as f64)),
+            AssetSort::Mtime => (row.mtime.clone(), None),
+        };
+
+        Cursor { updated_at, id: row.id.clone(), rank }
+    }
+}
+
+/// Every filter is optional. `name` is a case insensitive substring of the file path.
+#[derive(Debug, Default)]
+pub struct AssetFilter<'a> {
+    pub project_id: Option<&'a str>,
+    pub kind: Option<&'a str>,
+    pub mime: Option<&'a str>,
+    pub name: Option<&'a str>,
+}
+
+pub async fn list_assets(
+    pool: &Pool<Sqlite>,
+    filter: &AssetFilter<'_>,
+    sort: AssetSort,
+    descending: bool,
+    limit: i64,
+    cursor: Option<Cursor>,
+) -> Result<Vec<AssetListRow>, sqlx::Error> {
+    let (direction, after) = if descending { ("DESC", "<") } else { ("ASC", ">") };
+    let sql = format!(
+        r#"
+        SELECT a.id, a.project_id, a.file_path, a.kind, a.mime, a.size_bytes, a.mtime, a.thumbnail_hash, a.metadata_json
+        FROM assets a
+        WHERE (?1 IS NULL OR a.project_id = ?1)
+          AND (?2 IS NULL OR a.kind = ?2)
+          AND (?3 IS NULL OR a.mime = ?3)
+          AND (?4 IS NULL OR a.file_path LIKE ?4 ESCAPE '\')
+          AND (?6 IS NULL OR ({column}, a.id) {after} (?5, ?6))
+        ORDER BY {column} {direction}, a.id {direction}
+        LIMIT ?7
+        "#,
+        column = sort.column(),
+    );
+
+    let query = sqlx::query_as::<_, AssetListRow>(&sql)
+        .bind(filter.project_id)
+        .bind(filter.kind)
+        .bind(filter.mime)
+        .bind(filter.name.map(like_pattern));
+
+    let query = match (sort, &cursor) {
+        (AssetSort::Size, Some(cursor)) => query.bind(cursor.rank.map(|size| size as i64)),
+        (_, Some(cursor)) => query.bind(Some(cursor.updated_at.clone())),
+        (_, None) => query.bind(None::<String>),
+    };
+
+    query
+        .bind(cursor.map(|cursor| cursor.id))
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+// `%` and `_` typed by the user are literal.
+fn like_pattern(substring: &str) -> String {
+    let escaped = substring.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
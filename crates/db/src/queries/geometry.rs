@@ -0,0 +1,138 @@
+use lima_domain::models::geometry::{AssetGeometry, BoundingBox};
+use sqlx::{Pool, Sqlite, FromRow};
+
+#[derive(FromRow)]
+struct GeometryRow {
+    asset_id: String,
+    unit: Option<String>,
+    min_x: f64,
+    min_y: f64,
+    min_z: f64,
+    max_x: f64,
+    max_y: f64,
+    max_z: f64,
+    triangle_count: i64,
+    vertex_count: i64,
+    surface_area: f64,
+    volume: f64,
+    watertight: bool,
+}
+
+impl From<GeometryRow> for (String, AssetGeometry) {
+    fn from(row: GeometryRow) -> Self {
+        (row.asset_id, AssetGeometry {
+            unit: row.unit,
+            bounding_box: BoundingBox::new([row.min_x, row.min_y, row.min_z], [row.max_x, row.max_y, row.max_z]),
+            triangle_count: row.triangle_count,
+            vertex_count: row.vertex_count,
+            surface_area: row.surface_area,
+            volume: row.volume,
+            watertight: row.watertight,
+        })
+    }
+}
+
+pub async fn upsert_asset_geometry(
+    pool: &Pool<Sqlite>,
+    asset_id: &str,
+    file_hash: &str,
+    geometry: &AssetGeometry,
+    now: &str,
+) -> Result<(), sqlx::Error> {
+    let [min_x, min_y, min_z] = geometry.bounding_box.min;
+    let [max_x, max_y, max_z] = geometry.bounding_box.max;
+
+    sqlx::query(
+        r#"
+        INSERT INTO asset_geometry (
+            asset_id, file_hash, unit,
+            min_x, min_y, min_z, max_x, max_y, max_z,
+            triangle_count, vertex_count, surface_area, volume, watertight, computed_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+        ON CONFLICT(asset_id) DO UPDATE SET
+            file_hash = excluded.file_hash,
+            unit = excluded.unit,
+            min_x = excluded.min_x,
+            min_y = excluded.min_y,
+            min_z = excluded.min_z,
+            max_x = excluded.max_x,
+            max_y = excluded.max_y,
+            max_z = excluded.max_z,
+            triangle_count = excluded.triangle_count,
+            vertex_count = excluded.vertex_count,
+            surface_area = excluded.surface_area,
+            volume = excluded.volume,
+            watertight = excluded.watertight,
+            computed_at = excluded.computed_at
+        "#,
+    )
+    .bind(asset_id)
+    .bind(file_hash)
+    .bind(&geometry.unit)
+    .bind(min_x)
+    .bind(min_y)
+    .bind(min_z)
+    .bind(max_x)
+    .bind(max_y)
+    .bind(max_z)
+    .bind(geometry.triangle_count)
+    .bind(geometry.vertex_count)
+    .bind(geometry.surface_area)
+    .bind(geometry.volume)
+    .bind(geometry.watertight)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// (asset id, geometry) of the assets of a project, leaving out values computed from an older
+/// version of the file.
+pub async fn get_project_geometry(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+) -> Result<Vec<(String, AssetGeometry)>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, GeometryRow>(
+        r#"
+        SELECT
+            g.asset_id, g.unit,
+            g.min_x, g.min_y, g.min_z, g.max_x, g.max_y, g.max_z,
+            g.triangle_count, g.vertex_count, g.surface_area, g.volume, g.watertight
+        FROM asset_geometry g
+        JOIN assets a ON a.id = g.asset_id
+        WHERE a.project_id = ?1 AND g.file_hash = a.file_hash
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
+
+/// Same as [`get_project_geometry`] for a set of assets, from any project.
+pub async fn get_assets_geometry(
+    pool: &Pool<Sqlite>,
+    asset_ids: &[String],
+) -> Result<Vec<(String, AssetGeometry)>, sqlx::Error> {
+    let ids = serde_json::to_string(asset_ids).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    let rows = sqlx::query_as::<_, GeometryRow>(
+        r#"
+        SELECT
+            g.asset_id, g.unit,
+            g.min_x, g.min_y, g.min_z, g.max_x, g.max_y, g.max_z,
+            g.triangle_count, g.vertex_count, g.surface_area, g.volume, g.watertight
+        FROM asset_geometry g
+        JOIN assets a ON a.id = g.asset_id
+        WHERE a.id IN (SELECT value FROM json_each(?1)) AND g.file_hash = a.file_hash
+        "#,
+    )
+    .bind(ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(Into::into).collect())
+}
@@ -8,6 +8,7 @@ pub async fn ping(pool: &Pool<Sqlite>) -> Result<i64, sqlx::Error> {
     Ok(v)
 }
 
+pub mod printers;
 pub mod projects;
 pub mod projects_search;
 pub mod projects_create;
@@ -16,4 +17,8 @@ pub mod projects_detail;
 pub mod projects_update;
 pub mod projects_import;
 pub mod tags;
-pub mod assets;
\ No newline at end of file
+pub mod assets;
+pub mod assets_list;
+pub mod geometry;
+pub mod scan;
+pub mod sync_runs;
\ No newline at end of file
@@ -0,0 +1,125 @@
+use lima_domain::models::printer::BuildVolume;
+use sqlx::{FromRow, Pool, Sqlite};
+use uuid::Uuid;
+
+#[derive(Debug, FromRow)]
+pub struct PrinterRow {
+    pub id: String,
+    pub name: String,
+    pub build_x: f64,
+    pub build_y: f64,
+    pub build_z: f64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl PrinterRow {
+    pub fn build_volume(&self) -> BuildVolume {
+        BuildVolume { x: self.build_x, y: self.build_y, z: self.build_z }
+    }
+}
+
+// A library has a handful of printers, no pagination.
+pub async fn list_printers(
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<PrinterRow>, sqlx::Error> {
+    sqlx::query_as::<_, PrinterRow>(
+        r#"
+        SELECT id, name, build_x, build_y, build_z, created_at, updated_at
+        FROM printers
+        ORDER BY name, id
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_printer(
+    pool: &Pool<Sqlite>,
+    printer_id: &str,
+) -> Result<Option<PrinterRow>, sqlx::Error> {
+    sqlx::query_as::<_, PrinterRow>(
+        r#"
+        SELECT id, name, build_x, build_y, build_z, created_at, updated_at
+        FROM printers
+        WHERE id = ?1
+        "#,
+    )
+    .bind(printer_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create_printer(
+    pool: &Pool<Sqlite>,
+    name: &str,
+    volume: &BuildVolume,
+    now: &str,
+) -> Result<PrinterRow, sqlx::Error> {
+    let id = Uuid::new_v4().to_string();
+
+    sqlx::query(
+        r#"INSERT INTO printers (id, name, build_x, build_y, build_z, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)"#,
+    )
+    .bind(&id)
+    .bind(name)
+    .bind(volume.x)
+    .bind(volume.y)
+    .bind(volume.z)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(PrinterRow {
+        id,
+        name: name.to_string(),
+        build_x: volume.x,
+        build_y: volume.y,
+        build_z: volume.z,
+        created_at: now.to_string(),
+        updated_at: now.to_string(),
+    })
+}
+
+/// Returns the updated printer, None when it doesn't exist.
+pub async fn update_printer(
+    pool: &Pool<Sqlite>,
+    printer_id: &str,
+    name: Option<&str>,
+    volume: Option<&BuildVolume>,
+    now: &str,
+) -> Result<Option<PrinterRow>, sqlx::Error> {
+    sqlx::query_as::<_, PrinterRow>(
+        r#"
+        UPDATE printers
+        SET name = COALESCE(?2, name),
+            build_x = COALESCE(?3, build_x),
+            build_y = COALESCE(?4, build_y),
+            build_z = COALESCE(?5, build_z),
+            updated_at = ?6
+        WHERE id = ?1
+        RETURNING id, name, build_x, build_y, build_z, created_at, updated_at
+        "#,
+    )
+    .bind(printer_id)
+    .bind(name)
+    .bind(volume.map(|v| v.x))
+    .bind(volume.map(|v| v.y))
+    .bind(volume.map(|v| v.z))
+    .bind(now)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Returns whether the printer existed.
+pub async fn delete_printer(
+    pool: &Pool<Sqlite>,
+    printer_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(r#"DELETE FROM printers WHERE id = ?1"#)
+        .bind(printer_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
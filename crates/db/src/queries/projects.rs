@@ -1,33 +1,78 @@
 use sqlx::{Sqlite, Pool};
+use lima_domain::models::printer::BuildVolume;
 use lima_domain::models::project::ProjectRow;
 use lima_domain::pagination::Cursor;
 
+// Exports round, a 250 mm model may measure 250.00001.
+const FIT_TOLERANCE_MM: f64 = 0.01;
+
+/// `fits` only keeps projects with at least one measured model that fits in the volume.
 pub async fn list_projects(
     pool: &Pool<Sqlite>,
     limit: i64,
     cursor: Option<Cursor>,
+    fits: Option<BuildVolume>,
 ) -> Result<Vec<ProjectRow>, sqlx::Error> {
     match cursor {
-        None => { list_projects_from_start(pool, limit).await },
-        Some(cursor) => { list_projects_from_cursor(pool, limit, &cursor.updated_at, &cursor.id).await },
+        None => { list_projects_from_start(pool, limit, fits).await },
+        Some(cursor) => { list_projects_from_cursor(pool, limit, &cursor.updated_at, &cursor.id, fits).await },
+    }
+}
+
+/// SQL condition on `p.id`, true when a model of the project fits in the volume bound at `?first`,
+/// `?first + 1` and `?first + 2` (x, y, z). The footprint may be turned by 90°. No filter when they
+/// are NULL, see [`bind_fits`].
+pub(crate) fn fits_condition(first: usize) -> String {
+    let (x, y, z) = (first, first + 1, first + 2);
+    format!(
+        r#"(?{x} IS NULL OR EXISTS (
+            SELECT 1
+            FROM asset_geometry g
+            JOIN assets a ON a.id = g.asset_id
+            WHERE a.project_id = p.id
+              AND g.file_hash = a.file_hash
+              AND g.max_z - g.min_z <= ?{z}
+              AND ((g.max_x - g.min_x <= ?{x} AND g.max_y - g.min_y <= ?{y})
+                OR (g.max_y - g.min_y <= ?{x} AND g.max_x - g.min_x <= ?{y}))
+        ))"#
+    )
+}
+
+/// The x, y and z values for [`fits_condition`].
+pub(crate) fn bind_fits(fits: Option<BuildVolume>) -> [Option<f64>; 3] {
+    match fits {
+        Some(v) => [v.x, v.y, v.z].map(|size| Some(size + FIT_TOLERANCE_MM)),
+        None => [None; 3],
     }
 }
 
 async fn list_projects_from_start(
     pool: &Pool<Sqlite>,
-    limit: i64
+    limit: i64,
+    fits: Option<BuildVolume>,
 ) -> Result<Vec<ProjectRow>, sqlx::Error> {
-    sqlx::query_as::<_, ProjectRow>(
+    let [x, y, z] = bind_fits(fits);
+    let sql = format!(
         r#"
-        SELECT id, folder_path, name, description, main_image_id, created_at, updated_at, last_scanned_at
-        FROM projects
-        ORDER BY updated_at DESC, id DESC
+        SELECT p.id, p.folder_path, p.name, p.description, p.main_image_id, p.created_at, p.updated_at, p.last_scanned_at,
+               m.thumbnail_hash AS main_image_thumbnail_hash
+        FROM projects p
+        LEFT JOIN assets m ON m.id = p.main_image_id
+        WHERE {}
+        ORDER BY p.updated_at DESC, p.id DESC
         LIMIT ?1
         "#,
-    )
+        fits_condition(2),
+    );
+
+    sqlx::query_as::<_, ProjectRow>(&sql)
     .bind(limit)
+    .bind(x)
+    .bind(y)
+    .bind(z)
     .fetch_all(pool)
     .await
+    .map(|rows| rows.into_iter().map(ProjectRow::with_thumbnails).collect())
 }
 
 async fn list_projects_from_cursor(
@@ -35,19 +80,31 @@ async fn list_projects_from_cursor(
     limit: i64,
     updated_at: &str,
     id: &str,
+    fits: Option<BuildVolume>,
 ) -> Result<Vec<ProjectRow>, sqlx::Error> {
-    sqlx::query_as::<_, ProjectRow>(
+    let [x, y, z] = bind_fits(fits);
+    let sql = format!(
         r#"
-        SELECT id, folder_path, name, description, main_image_id, created_at, updated_at, last_scanned_at
-        FROM projects
-        WHERE (updated_at, id) < (?1, ?2)
-        ORDER BY updated_at DESC, id DESC
+        SELECT p.id, p.folder_path, p.name, p.description, p.main_image_id, p.created_at, p.updated_at, p.last_scanned_at,
+               m.thumbnail_hash AS main_image_thumbnail_hash
+        FROM projects p
+        LEFT JOIN assets m ON m.id = p.main_image_id
+        WHERE (p.updated_at, p.id) < (?1, ?2)
+          AND {}
+        ORDER BY p.updated_at DESC, p.id DESC
         LIMIT ?3
         "#,
-    )
+        fits_condition(4),
+    );
+
+    sqlx::query_as::<_, ProjectRow>(&sql)
     .bind(updated_at)
     .bind(id)
     .bind(limit)
+    .bind(x)
+    .bind(y)
+    .bind(z)
     .fetch_all(pool)
     .await
+    .map(|rows| rows.into_iter().map(ProjectRow::with_thumbnails).collect())
 }
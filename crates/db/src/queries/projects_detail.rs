@@ -18,6 +18,8 @@ pub struct ProjectAssetRow {
     pub file_path: String,
     pub kind: String,
     pub size_bytes: i64,
+    pub thumbnail_hash: Option<String>,
+    pub metadata_json: Option<String>,
 }
 
 #[derive(Debug, FromRow)]
@@ -104,9 +106,12 @@ pub async fn get_project_assets(
             id,
             file_path,
             kind,
-            size_bytes
+            size_bytes,
+            thumbnail_hash,
+            metadata_json
         FROM assets
         WHERE project_id = ?1
+        ORDER BY file_path
         "#,
     )
     .bind(project_id)
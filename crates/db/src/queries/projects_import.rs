@@ -1,7 +1,8 @@
 use sqlx::Pool;
 use tokio::fs;
-use std::path::PathBuf;
-use lima_domain::models::bundle::BundleMeta;
+use std::path::{Path, PathBuf};
+use lima_domain::models::bundle::{BUNDLE_META_FILE, BundleMeta, ImportAction, ImportConflict};
+use lima_domain::models::asset::sanitize_asset_path;
 use serde_json;
 
 use crate::queries::projects_detail::ProjectAssetRow;
@@ -12,6 +13,7 @@ pub enum ImportFromBundleError {
     MetaNotFound,
     ProjectNotFound,
     MissingFile { name: String },
+    InvalidPath { name: String },
     Conflict { name: String },
     FileSystemError(String),
     DatabaseError(sqlx::Error),
@@ -25,6 +27,23 @@ pub enum ImportFromBundleError {
     }
 }*/
 
+/// One file of the bundle. `file_path` is where its content is in the project, and `asset_id` the
+/// asset there, the existing one when the file was skipped.
+#[derive(Debug)]
+pub struct ImportedFile {
+    pub name: String,
+    pub action: ImportAction,
+    pub file_path: String,
+    pub asset_id: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ImportOutcome {
+    /// The assets created, skipped files aren't in here.
+    pub assets: Vec<ProjectAssetRow>,
+    pub files: Vec<ImportedFile>,
+}
+
 impl ToString for ImportFromBundleError {
     fn to_string(&self) -> String {
         match self {
@@ -32,6 +51,7 @@ impl ToString for ImportFromBundleError {
             ImportFromBundleError::MetaNotFound => "Missing meta file in bundle".to_string(),
             ImportFromBundleError::ProjectNotFound => "Project not found".to_string(),
             ImportFromBundleError::MissingFile { name } => format!("Missing file in bundle: {}", name),
+            ImportFromBundleError::InvalidPath { name } => format!("Invalid file path in bundle: {}", name),
             ImportFromBundleError::Conflict { name } => format!("Conflict with existing file: {}", name),
             ImportFromBundleError::DatabaseError(e) => format!("Database error: {}", e),
             ImportFromBundleError::FileSystemError(e) => format!("File system error: {}", e),
@@ -43,7 +63,8 @@ pub async fn import_assets_from_bundle(
     pool: &Pool<sqlx::Sqlite>,
     project_id: &str,
     bundle_id: &str,
-) -> Result<Vec<ProjectAssetRow>, ImportFromBundleError> {
+    conflict: ImportConflict,
+) -> Result<ImportOutcome, ImportFromBundleError> {
     let bundle_folder: PathBuf = ["data", "state", "bundles", &bundle_id].iter().collect();
 
     if !fs::metadata(&bundle_folder).await.is_ok() {
@@ -57,7 +78,7 @@ pub async fn import_assets_from_bundle(
 
     if meta.files.is_empty() {
         fs::remove_dir_all(&bundle_folder).await.ok();
-        return Ok(vec![]);
+        return Ok(ImportOutcome { assets: vec![], files: vec![] });
     }
 
     let project  = match crate::queries::projects_detail::get_project(pool, project_id).await {
@@ -68,39 +89,104 @@ pub async fn import_assets_from_bundle(
     
     // We assume if project exists folder structure is valid.
     let project_dir: PathBuf = ["data", "library", &project.folder_path].iter().collect();
-    let mut moved_files: Vec<PathBuf> = Vec::with_capacity(meta.files.len());
+    let mut moved_files: Vec<(PathBuf, PathBuf)> = Vec::with_capacity(meta.files.len());
+    let mut created_dirs: Vec<PathBuf> = Vec::new();
     let mut built_assets: Vec<ProjectAssetRow> = Vec::with_capacity(meta.files.len());
+    let mut imported_files: Vec<ImportedFile> = Vec::with_capacity(meta.files.len());
 
     let mut transaction = pool.begin().await.map_err(ImportFromBundleError::DatabaseError)?;
 
     for file_info in &meta.files {
+        // The meta file is ours, but it's on disk. Never follow a path out of the project folder.
+        if sanitize_asset_path(&file_info.name).is_none() {
+            rollback_imported_files(&moved_files, &created_dirs).await;
+            let _ = transaction.rollback().await;
+            return Err(ImportFromBundleError::InvalidPath { name: file_info.name.clone() });
+        }
+
         // check if file exists in bundle
         let src = bundle_folder.join(&file_info.name);
         if fs::metadata(&src).await.is_err() {
-            rollback_imported_files(&moved_files).await;
+            rollback_imported_files(&moved_files, &created_dirs).await;
             let _ = transaction.rollback().await;
             return Err(ImportFromBundleError::MissingFile { name: file_info.name.clone() });
         }
 
         // Ensure we don't replace existing files. We want to respect what is in the folders already.
-        let dst = project_dir.join(&file_info.name);
-        if fs::metadata(&dst).await.is_ok() {
-            rollback_imported_files(&moved_files).await;
+        let mut file_path = file_info.name.clone();
+        let mut action = ImportAction::Imported;
+        if fs::metadata(project_dir.join(&file_path)).await.is_ok() {
+            let existing: Option<(String, Option<String>)> = match sqlx::query_as(
+                "SELECT id, file_hash FROM assets WHERE project_id = ?1 AND file_path = ?2",
+            )
+            .bind(&project.id)
+            .bind(&file_info.name)
+            .fetch_optional(&mut *transaction)
+            .await {
+                Ok(existing) => existing,
+                Err(e) => {
+                    rollback_imported_files(&moved_files, &created_dirs).await;
+                    let _ = transaction.rollback().await;
+                    return Err(ImportFromBundleError::DatabaseError(e));
+                }
+            };
+
+            let identical = file_info.checksum.is_some()
+                && existing.as_ref().is_some_and(|(_, hash)| *hash == file_info.checksum);
+            let resolution = match conflict {
+                ImportConflict::Fail => None,
+                ImportConflict::Skip => Some(ImportAction::Skipped),
+                ImportConflict::SkipIdentical if identical => Some(ImportAction::SkippedIdentical),
+                ImportConflict::SkipIdentical => None,
+                ImportConflict::Rename => {
+                    file_path = match free_file_path(&mut transaction, &project.id, &project_dir, &file_info.name).await {
+                        Ok(file_path) => file_path,
+                        Err(e) => {
+                            rollback_imported_files(&moved_files, &created_dirs).await;
+                            let _ = transaction.rollback().await;
+                            return Err(ImportFromBundleError::DatabaseError(e));
+                        }
+                    };
+                    Some(ImportAction::Renamed)
+                }
+            };
+
+            match resolution {
+                None => {
+                    rollback_imported_files(&moved_files, &created_dirs).await;
+                    let _ = transaction.rollback().await;
+                    return Err(ImportFromBundleError::Conflict { name: file_info.name.clone() } );
+                }
+                Some(ImportAction::Renamed) => action = ImportAction::Renamed,
+                Some(skipped) => {
+                    imported_files.push(ImportedFile {
+                        name: file_info.name.clone(),
+                        action: skipped,
+                        file_path,
+                        asset_id: existing.map(|(id, _)| id),
+                    });
+                    continue;
+                }
+            }
+        }
+        let dst = project_dir.join(&file_path);
+
+        if let Err(e) = create_parent_dirs(&project_dir, &dst, &mut created_dirs).await {
+            rollback_imported_files(&moved_files, &created_dirs).await;
             let _ = transaction.rollback().await;
-            return Err(ImportFromBundleError::Conflict { name: file_info.name.clone() } );
+            return Err(ImportFromBundleError::FileSystemError(e.to_string()));
         }
 
         if let Err(e) = move_file(&src, &dst).await {
-            rollback_imported_files(&moved_files).await;
+            rollback_imported_files(&moved_files, &created_dirs).await;
             let _ = transaction.rollback().await;
             return Err(ImportFromBundleError::FileSystemError(e.to_string()));
         }
 
-        moved_files.push(dst);
+        moved_files.push((src, dst));
 
-        let asset_id = uuid::Uuid::new_v4().to_string();
-
-        sqlx::query(
+        // A row left by a file that is gone from disk keeps its id.
+        let inserted: Result<String, sqlx::Error> = sqlx::query_scalar(
         r#"
             INSERT INTO assets (
               id, project_id, file_path, kind, size_bytes, mtime, mime, file_hash, created_at, updated_at
@@ -113,11 +199,12 @@ pub async fn import_assets_from_bundle(
               mime = excluded.mime,
               file_hash = excluded.file_hash,
               updated_at = excluded.updated_at
+            RETURNING id
             "#,
         )
-        .bind(&asset_id)
+        .bind(uuid::Uuid::new_v4().to_string())
         .bind(&project.id)
-        .bind(&file_info.name)
+        .bind(&file_path)
         .bind(&file_info.kind)
         .bind(file_info.size)
         .bind(file_info.mtime.as_deref().unwrap_or(""))
@@ -125,38 +212,94 @@ pub async fn import_assets_from_bundle(
         .bind(&file_info.checksum.as_deref())
         .bind(&meta.uploaded_at)
         .bind(&meta.uploaded_at)
-        .execute(&mut *transaction)
-        .await
-        .map_err(ImportFromBundleError::DatabaseError)?;
+        .fetch_one(&mut *transaction)
+        .await;
+
+        let asset_id = match inserted {
+            Ok(asset_id) => asset_id,
+            Err(e) => {
+                rollback_imported_files(&moved_files, &created_dirs).await;
+                let _ = transaction.rollback().await;
+                return Err(ImportFromBundleError::DatabaseError(e));
+            }
+        };
+
+        imported_files.push(ImportedFile {
+            name: file_info.name.clone(),
+            action,
+            file_path: file_path.clone(),
+            asset_id: Some(asset_id.clone()),
+        });
 
         built_assets.push(ProjectAssetRow {
             id: asset_id,
-            file_path: file_info.name.clone(),
+            file_path,
             kind: file_info.kind.clone(),
             size_bytes: file_info.size,
+            thumbnail_hash: None,
+            metadata_json: None,
         });
     };
 
     if let Err(e) = transaction.commit().await {
-        rollback_imported_files(&moved_files).await;
+        rollback_imported_files(&moved_files, &created_dirs).await;
         return Err(ImportFromBundleError::DatabaseError(e));
     }
 
-    // Ignore the error. Files have been imported correctly and the cron job will take care of cleaning up if needed.
+    // Ignore the error. Files have been imported correctly and the scheduled bundle_gc run will take care of cleaning up if needed.
     let _ = fs::remove_dir_all(&bundle_folder)
         .await
         .ok();
 
-    return Ok(built_assets);
+    return Ok(ImportOutcome { assets: built_assets, files: imported_files });
 }
 
 async fn get_bundle_meta_file(bundle_folder: &PathBuf) -> Option<BundleMeta> {
-    let meta_path = bundle_folder.join("meta.json");
+    let meta_path = bundle_folder.join(BUNDLE_META_FILE);
     let meta_data = fs::read_to_string(meta_path).await.ok()?;
     let bundle_meta: BundleMeta = serde_json::from_str(&meta_data).ok()?;
     Some(bundle_meta)
 }
 
+// `base.stl` becomes `base (2).stl`, or `base (3).stl` if that one is taken too, on disk or in the
+// index. Stays in the same folder.
+async fn free_file_path(
+    transaction: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    project_id: &str,
+    project_dir: &Path,
+    name: &str,
+) -> Result<String, sqlx::Error> {
+    let (dir, file_name) = match name.rsplit_once('/') {
+        Some((dir, file_name)) => (Some(dir), file_name),
+        None => (None, name),
+    };
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (file_name, None),
+    };
+
+    let mut copy = 2;
+    loop {
+        let candidate = match extension {
+            Some(extension) => format!("{} ({}).{}", stem, copy, extension),
+            None => format!("{} ({})", stem, copy),
+        };
+        let candidate = match dir {
+            Some(dir) => format!("{}/{}", dir, candidate),
+            None => candidate,
+        };
+        let indexed: Option<i64> = sqlx::query_scalar("SELECT 1 FROM assets WHERE project_id = ?1 AND file_path = ?2")
+            .bind(project_id)
+            .bind(&candidate)
+            .fetch_optional(&mut **transaction)
+            .await?;
+        if indexed.is_none() && fs::metadata(project_dir.join(&candidate)).await.is_err() {
+            return Ok(candidate);
+        }
+        copy += 1;
+    }
+}
+
 async fn move_file(src: &PathBuf, dst: &PathBuf) -> Result<(), std::io::Error> {
     match fs::rename(src, dst).await {
         Ok(_) => Ok(()),
@@ -169,8 +312,35 @@ async fn move_file(src: &PathBuf, dst: &PathBuf) -> Result<(), std::io::Error> {
     }
 }
 
-async fn rollback_imported_files(moved_files: &Vec<PathBuf>) {
-    for file_path in moved_files.iter() {
-        let _ = fs::remove_file(file_path).await;
+// Remembers the folders it creates so a rollback can remove them again.
+async fn create_parent_dirs(project_dir: &Path, dst: &Path, created_dirs: &mut Vec<PathBuf>) -> Result<(), std::io::Error> {
+    let Some(parent) = dst.parent() else {
+        return Ok(());
+    };
+
+    let mut missing: Vec<PathBuf> = Vec::new();
+    for dir in parent.ancestors().take_while(|dir| *dir != project_dir) {
+        if fs::metadata(dir).await.is_ok() {
+            break;
+        }
+        missing.push(dir.to_path_buf());
+    }
+
+    fs::create_dir_all(parent).await?;
+    // Kept in creation order, the rollback removes them backwards.
+    created_dirs.extend(missing.into_iter().rev());
+    Ok(())
+}
+
+// Files go back into the bundle, so the import can be retried, with another conflict strategy for one.
+async fn rollback_imported_files(moved_files: &[(PathBuf, PathBuf)], created_dirs: &[PathBuf]) {
+    for (src, dst) in moved_files.iter().rev() {
+        if move_file(dst, src).await.is_err() {
+            let _ = fs::remove_file(dst).await;
+        }
+    }
+
+    for dir in created_dirs.iter().rev() {
+        let _ = fs::remove_dir(dir).await;
     }
 }
@@ -1,8 +1,11 @@
 
+use lima_domain::models::printer::BuildVolume;
 use lima_domain::models::project::ProjectRow;
 use lima_domain::pagination::Cursor;
 use sqlx::{Pool, Sqlite, FromRow};
 
+use crate::queries::projects::{bind_fits, fits_condition};
+
 #[derive(Debug)]
 pub struct SearchProjectRow {
     pub rank: f64,
@@ -20,6 +23,7 @@ struct SearchRow {
     created_at: String,
     updated_at: String,
     last_scanned_at: Option<String>,
+    main_image_thumbnail_hash: Option<String>,
 }
 
 impl From<SearchRow> for SearchProjectRow {
@@ -35,7 +39,9 @@ impl From<SearchRow> for SearchProjectRow {
                 created_at: row.created_at,
                 updated_at: row.updated_at,
                 last_scanned_at: row.last_scanned_at,
-            }
+                main_image_thumbnail_hash: row.main_image_thumbnail_hash,
+                thumbnails: None,
+            }.with_thumbnails()
         }
     }
 }
@@ -45,19 +51,22 @@ pub async fn search_projects(
     query: &str,
     limit: i64,
     cursor: Option<Cursor>,
+    fits: Option<BuildVolume>,
 ) -> Result<Vec<SearchProjectRow>, sqlx::Error> {
     match cursor {
-        None => { search_projects_from_start(pool, query, limit).await }
-        Some(cursor) => { search_projects_from_cursor(pool, query, limit, &cursor).await }
+        None => { search_projects_from_start(pool, query, limit, fits).await }
+        Some(cursor) => { search_projects_from_cursor(pool, query, limit, &cursor, fits).await }
     }
 }
 
 async fn search_projects_from_start(
     pool: &Pool<Sqlite>,
     query: &str,
-    limit: i64
+    limit: i64,
+    fits: Option<BuildVolume>,
 ) -> Result<Vec<SearchProjectRow>, sqlx::Error> {
-    let rows = sqlx::query_as::<_, SearchRow>(
+    let [x, y, z] = bind_fits(fits);
+    let sql = format!(
         r#"
         SELECT
             COALESCE(bm25(projects_fts), 0.0) AS rank,
@@ -68,16 +77,25 @@ async fn search_projects_from_start(
             p.main_image_id,
             p.created_at,
             p.updated_at,
-            p.last_scanned_at
+            p.last_scanned_at,
+            m.thumbnail_hash AS main_image_thumbnail_hash
         FROM projects_fts
         JOIN projects p ON projects_fts.project_id = p.id
+        LEFT JOIN assets m ON m.id = p.main_image_id
         WHERE projects_fts MATCH ?1
+          AND {}
         ORDER BY rank ASC, p.updated_at DESC, p.id DESC
         LIMIT ?2
         "#,
-    )
+        fits_condition(3),
+    );
+
+    let rows = sqlx::query_as::<_, SearchRow>(&sql)
     .bind(query)
     .bind(limit)
+    .bind(x)
+    .bind(y)
+    .bind(z)
     .fetch_all(pool)
     .await?;
 
@@ -88,9 +106,11 @@ async fn search_projects_from_cursor(
     pool: &Pool<Sqlite>,
     query: &str,
     limit: i64,
-    cursor: &Cursor
+    cursor: &Cursor,
+    fits: Option<BuildVolume>,
 ) -> Result<Vec<SearchProjectRow>, sqlx::Error> {
-    let rows = sqlx::query_as::<_, SearchRow>(
+    let [x, y, z] = bind_fits(fits);
+    let sql = format!(
         r#"
         WITH ranked AS(
             SELECT
@@ -102,10 +122,13 @@ async fn search_projects_from_cursor(
                 p.main_image_id,
                 p.created_at,
                 p.updated_at,
-                p.last_scanned_at
+                p.last_scanned_at,
+                m.thumbnail_hash AS main_image_thumbnail_hash
             FROM projects_fts
             JOIN projects p ON projects_fts.project_id = p.id
+            LEFT JOIN assets m ON m.id = p.main_image_id
             WHERE projects_fts MATCH ?1
+              AND {}
         )
         SELECT *
         FROM ranked
@@ -115,12 +138,18 @@ async fn search_projects_from_cursor(
         ORDER BY rank ASC, updated_at DESC, id DESC
         LIMIT ?5
         "#,
-    )
+        fits_condition(6),
+    );
+
+    let rows = sqlx::query_as::<_, SearchRow>(&sql)
      .bind(query)
      .bind(cursor.rank)
      .bind(cursor.updated_at.clone())
     .bind(cursor.id.clone())
     .bind(limit)
+    .bind(x)
+    .bind(y)
+    .bind(z)
     .fetch_all(pool)
     .await?;
 
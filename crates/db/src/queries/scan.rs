@@ -0,0 +1,348 @@
+use sqlx::{FromRow, Pool, Sqlite, Transaction};
+use uuid::Uuid;
+
+pub struct ScannedAsset<'a> {
+    pub file_path: &'a str,
+    pub kind: &'a str,
+    pub size_bytes: i64,
+    pub mtime: &'a str,
+    pub mime: &'a str,
+    pub file_hash: Option<&'a str>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct IndexedProjectRow {
+    pub id: String,
+    pub folder_path: String,
+}
+
+#[derive(Debug, FromRow)]
+pub struct IndexedAssetRow {
+    pub id: String,
+    pub file_path: String,
+    pub kind: String,
+    pub size_bytes: i64,
+    pub mtime: String,
+    pub mime: String,
+    pub file_hash: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct HashedAssetRow {
+    pub id: String,
+    pub project_id: String,
+    pub folder_path: String,
+    pub file_path: String,
+    pub size_bytes: i64,
+    pub mtime: String,
+    pub file_hash: String,
+}
+
+// An asset file something is derived from (thumbnails, metadata), at the version `file_hash`.
+#[derive(Debug, FromRow)]
+pub struct AssetSourceRow {
+    pub id: String,
+    pub file_path: String,
+    pub kind: String,
+    pub file_hash: String,
+}
+
+pub async fn list_indexed_projects(
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<IndexedProjectRow>, sqlx::Error> {
+    sqlx::query_as::<_, IndexedProjectRow>(
+        r#"SELECT id, folder_path FROM projects ORDER BY folder_path"#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn list_indexed_assets(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+) -> Result<Vec<IndexedAssetRow>, sqlx::Error> {
+    sqlx::query_as::<_, IndexedAssetRow>(
+        r#"
+        SELECT id, file_path, kind, size_bytes, mtime, mime, file_hash
+        FROM assets
+        WHERE project_id = ?1
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await
+}
+
+// Everything that has a hash to verify, grouped by project so files of a folder are read together.
+pub async fn list_hashed_assets(
+    pool: &Pool<Sqlite>,
+) -> Result<Vec<HashedAssetRow>, sqlx::Error> {
+    sqlx::query_as::<_, HashedAssetRow>(
+        r#"
+        SELECT a.id, a.project_id, p.folder_path, a.file_path, a.size_bytes, a.mtime, a.file_hash
+        FROM assets a
+        JOIN projects p ON p.id = a.project_id
+        WHERE a.file_hash IS NOT NULL
+        ORDER BY p.folder_path, a.file_path
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+// Hashed images, models and G-code whose thumbnails are missing or were made from an older version of the
+// file. Not every model can be rendered, the indexer filters by format.
+pub async fn list_stale_thumbnails(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+) -> Result<Vec<AssetSourceRow>, sqlx::Error> {
+    sqlx::query_as::<_, AssetSourceRow>(
+        r#"
+        SELECT id, file_path, kind, file_hash
+        FROM assets
+        WHERE project_id = ?1
+          AND kind IN ('image', 'model', 'gcode')
+          AND file_hash IS NOT NULL
+          AND (thumbnail_hash IS NULL OR thumbnail_hash <> file_hash)
+        ORDER BY file_path
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await
+}
+
+// Hashed 3MF and G-code files whose metadata was never read or was read from an older version of the file.
+pub async fn list_stale_metadata(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+) -> Result<Vec<AssetSourceRow>, sqlx::Error> {
+    sqlx::query_as::<_, AssetSourceRow>(
+        r#"
+        SELECT id, file_path, kind, file_hash
+        FROM assets
+        WHERE project_id = ?1
+          AND (lower(file_path) LIKE '%.3mf' OR lower(file_path) LIKE '%.gcode' OR lower(file_path) LIKE '%.bgcode')
+          AND file_hash IS NOT NULL
+          AND (metadata_hash IS NULL OR metadata_hash <> file_hash)
+        ORDER BY file_path
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await
+}
+
+// Hashed models the indexer can measure that have no geometry for the current version of the file.
+pub async fn list_stale_geometry(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+) -> Result<Vec<AssetSourceRow>, sqlx::Error> {
+    sqlx::query_as::<_, AssetSourceRow>(
+        r#"
+        SELECT a.id, a.file_path, a.kind, a.file_hash
+        FROM assets a
+        LEFT JOIN asset_geometry g ON g.asset_id = a.id
+        WHERE a.project_id = ?1
+          AND a.kind = 'model'
+          AND a.file_hash IS NOT NULL
+          AND (g.asset_id IS NULL OR g.file_hash <> a.file_hash)
+          AND (lower(a.file_path) LIKE '%.stl' OR lower(a.file_path) LIKE '%.obj' OR lower(a.file_path) LIKE '%.3mf'
+            OR lower(a.file_path) LIKE '%.gltf' OR lower(a.file_path) LIKE '%.glb')
+        ORDER BY a.file_path
+        "#,
+    )
+    .bind(project_id)
+    .fetch_all(pool)
+    .await
+}
+
+// Projects found on disk are keyed by folder_path so re-runs reuse the existing row (and its metadata).
+// Returns the project id and whether it was created.
+pub async fn upsert_project_for_folder(
+    tx: &mut Transaction<'_, Sqlite>,
+    folder_path: &str,
+    now: &str,
+) -> Result<(String, bool), sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO projects (id, folder_path, name, description, main_image_id, created_at, updated_at, last_scanned_at)
+        VALUES (?1, ?2, ?2, '', NULL, ?3, ?3, NULL)
+        ON CONFLICT(folder_path) DO NOTHING
+        "#,
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(folder_path)
+    .bind(now)
+    .execute(&mut **tx)
+    .await?;
+
+    let id = sqlx::query_scalar::<_, String>(
+        r#"SELECT id FROM projects WHERE folder_path = ?1"#,
+    )
+    .bind(folder_path)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok((id, result.rows_affected() > 0))
+}
+
+pub async fn insert_scanned_asset(
+    tx: &mut Transaction<'_, Sqlite>,
+    project_id: &str,
+    asset: &ScannedAsset<'_>,
+    now: &str,
+) -> Result<String, sqlx::Error> {
+    let id = Uuid::new_v4().to_string();
+
+    sqlx::query(
+        r#"
+        INSERT INTO assets (
+          id, project_id, file_path, kind, size_bytes, mtime, mime, file_hash, created_at, updated_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+        "#,
+    )
+    .bind(&id)
+    .bind(project_id)
+    .bind(asset.file_path)
+    .bind(asset.kind)
+    .bind(asset.size_bytes)
+    .bind(asset.mtime)
+    .bind(asset.mime)
+    .bind(asset.file_hash)
+    .bind(now)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn update_scanned_asset(
+    tx: &mut Transaction<'_, Sqlite>,
+    asset_id: &str,
+    asset: &ScannedAsset<'_>,
+    now: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE assets
+        SET kind = ?1,
+            size_bytes = ?2,
+            mtime = ?3,
+            mime = ?4,
+            file_hash = ?5,
+            updated_at = ?6
+        WHERE id = ?7
+        "#,
+    )
+    .bind(asset.kind)
+    .bind(asset.size_bytes)
+    .bind(asset.mtime)
+    .bind(asset.mime)
+    .bind(asset.file_hash)
+    .bind(now)
+    .bind(asset_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+// Only removes the index rows. The files are already gone from disk when we get here.
+pub async fn delete_missing_asset(
+    tx: &mut Transaction<'_, Sqlite>,
+    project_id: &str,
+    asset_id: &str,
+    now: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"DELETE FROM assets WHERE id = ?1 AND project_id = ?2"#,
+    )
+    .bind(asset_id)
+    .bind(project_id)
+    .execute(&mut **tx)
+    .await?;
+
+    // main_image_id is not a real FK, so clear it ourselves.
+    sqlx::query(
+        r#"UPDATE projects SET main_image_id = NULL, updated_at = ?1 WHERE id = ?2 AND main_image_id = ?3"#,
+    )
+    .bind(now)
+    .bind(project_id)
+    .bind(asset_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+// The name follows the folder unless someone gave the project a name of its own.
+pub async fn move_project(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+    from_folder_path: &str,
+    to_folder_path: &str,
+    now: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE projects
+        SET folder_path = ?1,
+            name = CASE WHEN name = ?2 THEN ?1 ELSE name END,
+            updated_at = ?3
+        WHERE id = ?4
+        "#,
+    )
+    .bind(to_folder_path)
+    .bind(from_folder_path)
+    .bind(now)
+    .bind(project_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+// The project itself stays, with its tags, collections and description. Only deleting it through the
+// API drops those.
+pub async fn delete_missing_project_assets(
+    pool: &Pool<Sqlite>,
+    project_id: &str,
+    now: &str,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"DELETE FROM assets WHERE project_id = ?1"#,
+    )
+    .bind(project_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"UPDATE projects SET main_image_id = NULL, updated_at = ?1 WHERE id = ?2 AND main_image_id IS NOT NULL"#,
+    )
+    .bind(now)
+    .bind(project_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+pub async fn mark_project_scanned(
+    tx: &mut Transaction<'_, Sqlite>,
+    project_id: &str,
+    now: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE projects SET last_scanned_at = ?1 WHERE id = ?2"#,
+    )
+    .bind(now)
+    .bind(project_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
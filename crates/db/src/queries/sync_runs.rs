@@ -0,0 +1,338 @@
+use lima_domain::pagination::Cursor;
+use sqlx::{FromRow, Pool, Sqlite};
+use uuid::Uuid;
+
+#[derive(Debug, FromRow)]
+pub struct SyncRunRow {
+    pub id: String,
+    pub mode: String,
+    pub reason: String,
+    pub status: String,
+    pub options_json: String,
+    pub stats_json: String,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, FromRow)]
+pub struct SyncEventRow {
+    pub id: i64,
+    pub run_id: String,
+    pub created_at: String,
+    pub level: String,
+    pub kind: String,
+    pub data_json: String,
+}
+
+pub struct QueuedRun {
+    pub id: String,
+    pub created: bool,
+}
+
+// Identical requests (same mode and options) are coalesced into the run that is already waiting,
+// so triggering a sync twice before the worker picks it up gives back the same run.
+pub async fn enqueue_sync_run(
+    pool: &Pool<Sqlite>,
+    mode: &str,
+    reason: &str,
+    options_json: &str,
+    now: &str,
+) -> Result<QueuedRun, sqlx::Error> {
+    loop {
+        let id = Uuid::new_v4().to_string();
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO sync_runs (id, mode, reason, status, options_json, stats_json, created_at, started_at, finished_at, error)
+            SELECT ?1, ?2, ?3, 'queued', ?4, '{}', ?5, NULL, NULL, ''
+            WHERE NOT EXISTS (
+                SELECT 1 FROM sync_runs WHERE status = 'queued' AND mode = ?2 AND options_json = ?4
+            )
+            "#,
+        )
+        .bind(&id)
+        .bind(mode)
+        .bind(reason)
+        .bind(options_json)
+        .bind(now)
+        .execute(pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            return Ok(QueuedRun { id, created: true });
+        }
+
+        let existing = sqlx::query_scalar::<_, String>(
+            r#"SELECT id FROM sync_runs WHERE status = 'queued' AND mode = ?1 AND options_json = ?2 ORDER BY created_at LIMIT 1"#,
+        )
+        .bind(mode)
+        .bind(options_json)
+        .fetch_optional(pool)
+        .await?;
+
+        // The worker may have claimed it in between, in that case queue a new one.
+        if let Some(id) = existing {
+            return Ok(QueuedRun { id, created: false });
+        }
+    }
+}
+
+// Oldest queued run first. The status check in the UPDATE makes the claim safe if called concurrently.
+pub async fn claim_next_sync_run(
+    pool: &Pool<Sqlite>,
+    now: &str,
+) -> Result<Option<SyncRunRow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRunRow>(
+        r#"
+        UPDATE sync_runs
+        SET status = 'running', started_at = ?1
+        WHERE id = (
+            SELECT id FROM sync_runs WHERE status = 'queued' ORDER BY created_at, id LIMIT 1
+        ) AND status = 'queued'
+        RETURNING id, mode, reason, status, options_json, stats_json, created_at, started_at, finished_at, error
+        "#,
+    )
+    .bind(now)
+    .fetch_optional(pool)
+    .await
+}
+
+// Runs left as running by a previous process will never finish, flag them so they don't look stuck.
+pub async fn fail_interrupted_sync_runs(
+    pool: &Pool<Sqlite>,
+    now: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"UPDATE sync_runs SET status = 'failed', error = 'Interrupted by server restart', finished_at = ?1 WHERE status = 'running'"#,
+    )
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Only queued runs, a running one has to be stopped by the worker so its stats end up in the row.
+pub async fn cancel_queued_sync_run(
+    pool: &Pool<Sqlite>,
+    run_id: &str,
+    now: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"UPDATE sync_runs SET status = 'cancelled', finished_at = ?1 WHERE id = ?2 AND status = 'queued'"#,
+    )
+    .bind(now)
+    .bind(run_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn finish_sync_run(
+    pool: &Pool<Sqlite>,
+    run_id: &str,
+    status: &str,
+    stats_json: &str,
+    error: &str,
+    now: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"UPDATE sync_runs SET status = ?1, stats_json = ?2, error = ?3, finished_at = ?4 WHERE id = ?5"#,
+    )
+    .bind(status)
+    .bind(stats_json)
+    .bind(error)
+    .bind(now)
+    .bind(run_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_sync_run(
+    pool: &Pool<Sqlite>,
+    run_id: &str,
+) -> Result<Option<SyncRunRow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRunRow>(
+        r#"
+        SELECT id, mode, reason, status, options_json, stats_json, created_at, started_at, finished_at, error
+        FROM sync_runs
+        WHERE id = ?1
+        "#,
+    )
+    .bind(run_id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn get_running_sync_run(
+    pool: &Pool<Sqlite>,
+) -> Result<Option<SyncRunRow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRunRow>(
+        r#"
+        SELECT id, mode, reason, status, options_json, stats_json, created_at, started_at, finished_at, error
+        FROM sync_runs
+        WHERE status = 'running'
+        ORDER BY started_at DESC
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn get_last_finished_sync_run(
+    pool: &Pool<Sqlite>,
+) -> Result<Option<SyncRunRow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRunRow>(
+        r#"
+        SELECT id, mode, reason, status, options_json, stats_json, created_at, started_at, finished_at, error
+        FROM sync_runs
+        WHERE finished_at IS NOT NULL
+        ORDER BY finished_at DESC
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn count_queued_sync_runs(
+    pool: &Pool<Sqlite>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>(
+        r#"SELECT COUNT(*) FROM sync_runs WHERE status = 'queued'"#,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list_sync_runs(
+    pool: &Pool<Sqlite>,
+    limit: i64,
+    cursor: Option<Cursor>,
+) -> Result<Vec<SyncRunRow>, sqlx::Error> {
+    match cursor {
+        None => { list_sync_runs_from_start(pool, limit).await },
+        Some(cursor) => { list_sync_runs_from_cursor(pool, limit, &cursor.updated_at, &cursor.id).await },
+    }
+}
+
+async fn list_sync_runs_from_start(
+    pool: &Pool<Sqlite>,
+    limit: i64,
+) -> Result<Vec<SyncRunRow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRunRow>(
+        r#"
+        SELECT id, mode, reason, status, options_json, stats_json, created_at, started_at, finished_at, error
+        FROM sync_runs
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?1
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+async fn list_sync_runs_from_cursor(
+    pool: &Pool<Sqlite>,
+    limit: i64,
+    created_at: &str,
+    id: &str,
+) -> Result<Vec<SyncRunRow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncRunRow>(
+        r#"
+        SELECT id, mode, reason, status, options_json, stats_json, created_at, started_at, finished_at, error
+        FROM sync_runs
+        WHERE (created_at, id) < (?1, ?2)
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?3
+        "#,
+    )
+    .bind(created_at)
+    .bind(id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn insert_sync_event(
+    pool: &Pool<Sqlite>,
+    run_id: &str,
+    level: &str,
+    kind: &str,
+    data_json: &str,
+    now: &str,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"INSERT INTO sync_events (run_id, created_at, level, kind, data_json) VALUES (?1, ?2, ?3, ?4, ?5)"#,
+    )
+    .bind(run_id)
+    .bind(now)
+    .bind(level)
+    .bind(kind)
+    .bind(data_json)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+// Events are a log, so they are read oldest first. The cursor is the last seen event id.
+pub async fn list_sync_events(
+    pool: &Pool<Sqlite>,
+    run_id: &str,
+    limit: i64,
+    after_id: Option<i64>,
+) -> Result<Vec<SyncEventRow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncEventRow>(
+        r#"
+        SELECT id, run_id, created_at, level, kind, data_json
+        FROM sync_events
+        WHERE run_id = ?1 AND id > ?2
+        ORDER BY id ASC
+        LIMIT ?3
+        "#,
+    )
+    .bind(run_id)
+    .bind(after_id.unwrap_or(0))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+// Events of every run, for the live stream. Same cursor as list_sync_events.
+pub async fn list_all_sync_events(
+    pool: &Pool<Sqlite>,
+    limit: i64,
+    after_id: i64,
+) -> Result<Vec<SyncEventRow>, sqlx::Error> {
+    sqlx::query_as::<_, SyncEventRow>(
+        r#"
+        SELECT id, run_id, created_at, level, kind, data_json
+        FROM sync_events
+        WHERE id > ?1
+        ORDER BY id ASC
+        LIMIT ?2
+        "#,
+    )
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_last_sync_event_id(
+    pool: &Pool<Sqlite>,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar::<_, i64>(
+        r#"SELECT COALESCE(MAX(id), 0) FROM sync_events"#,
+    )
+    .fetch_one(pool)
+    .await
+}
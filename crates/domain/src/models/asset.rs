@@ -0,0 +1,136 @@
+use mime_guess::MimeGuess;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What an asset is, from its extension. Stored as `assets.kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    /// Printable meshes and CAD exchange formats.
+    Model,
+    /// Native files of a modelling tool, the models are exported from them.
+    CadSource,
+    Gcode,
+    Image,
+    Document,
+    Video,
+    Archive,
+    Other,
+}
+
+impl AssetKind {
+    pub const ALL: [AssetKind; 8] = [
+        AssetKind::Model,
+        AssetKind::CadSource,
+        AssetKind::Gcode,
+        AssetKind::Image,
+        AssetKind::Document,
+        AssetKind::Video,
+        AssetKind::Archive,
+        AssetKind::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssetKind::Model => "model",
+            AssetKind::CadSource => "cad_source",
+            AssetKind::Gcode => "gcode",
+            AssetKind::Image => "image",
+            AssetKind::Document => "document",
+            AssetKind::Video => "video",
+            AssetKind::Archive => "archive",
+            AssetKind::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+
+    /// Lowercase extensions of the kind, without the dot. Empty for `Other`, which is everything else.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            AssetKind::Model => &["stl", "obj", "3mf", "step", "stp", "ply", "amf", "glb", "gltf", "fbx"],
+            AssetKind::CadSource => &["f3d", "scad", "fcstd", "blend", "skp"],
+            AssetKind::Gcode => &["gcode", "bgcode"],
+            AssetKind::Image => &["png", "jpg", "jpeg", "webp", "gif", "bmp"],
+            AssetKind::Document => &["pdf", "md", "txt"],
+            AssetKind::Video => &["mp4", "mov", "webm", "mkv", "avi"],
+            AssetKind::Archive => &["zip", "7z", "rar", "tar", "gz", "tgz"],
+            AssetKind::Other => &[],
+        }
+    }
+
+    /// Extensions are matched case insensitively, `Part.FCStd` is a `cad_source`.
+    pub fn from_path(path: &str) -> Self {
+        let Some((_, extension)) = path.rsplit_once('.') else {
+            return AssetKind::Other;
+        };
+        let extension = extension.to_ascii_lowercase();
+
+        Self::ALL.into_iter()
+            .find(|kind| kind.extensions().contains(&extension.as_str()))
+            .unwrap_or(AssetKind::Other)
+    }
+}
+
+pub fn guess_mime(path: &str) -> String {
+    MimeGuess::from_path(path)
+    .first_or_octet_stream()
+    .to_string()
+}
+
+
+/// Checks an asset path relative to its project folder, like `stls/base.stl`. Separators are
+/// always '/'. Returns None for anything that could leave the folder: absolute paths, `.` or
+/// `..` segments, empty segments, backslashes and NUL bytes.
+pub fn sanitize_asset_path(path: &str) -> Option<String> {
+    if path.is_empty() || path.contains('\\') || path.contains('\0') {
+        return None;
+    }
+
+    let valid = path.split('/').all(|segment| !segment.is_empty() && segment != "." && segment != "..");
+    if !valid {
+        return None;
+    }
+
+    Some(path.to_string())
+}
+
+/// Folder of an asset path, empty for files at the root of the project.
+pub fn asset_directory(file_path: &str) -> &str {
+    file_path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_paths_are_kept() {
+        assert_eq!(sanitize_asset_path("base.stl").as_deref(), Some("base.stl"));
+        assert_eq!(sanitize_asset_path("stls/v2/base.stl").as_deref(), Some("stls/v2/base.stl"));
+        assert_eq!(sanitize_asset_path("..base.stl").as_deref(), Some("..base.stl"));
+    }
+
+    #[test]
+    fn paths_leaving_the_folder_are_rejected() {
+        for path in ["../up.stl", "stls/../../up.stl", "stls/..", "/etc/passwd", "./base.stl", "stls/./base.stl"] {
+            assert_eq!(sanitize_asset_path(path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn empty_segments_are_rejected() {
+        for path in ["", "stls//base.stl", "stls/", "/"] {
+            assert_eq!(sanitize_asset_path(path), None, "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn backslashes_and_nul_are_rejected() {
+        for path in ["..\\up.stl", "stls\\base.stl", "C:\\base.stl", "base.stl\0.txt", "\0"] {
+            assert_eq!(sanitize_asset_path(path), None, "{:?}", path);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What LiMa could read from inside an asset file. Stored as JSON in `assets.metadata_json`, every
+/// field is optional because every slicer writes a different subset. The first four come from 3MF
+/// files, the others from G-code.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct AssetMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub designer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// e.g. "PrusaSlicer 2.7.1".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slicer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub printer_model: Option<String>,
+    /// Slicer estimate, in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub print_time_seconds: Option<u64>,
+    /// Summed over every extruder.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filament_used_g: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filament_used_mm: Option<f64>,
+}
+
+impl AssetMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Assets LiMa reads metadata from. The SQL side filters on the same extensions.
+pub fn has_metadata_source(file_path: &str) -> bool {
+    let lower = file_path.to_ascii_lowercase();
+    lower.ends_with(".3mf") || lower.ends_with(".gcode") || lower.ends_with(".bgcode")
+}
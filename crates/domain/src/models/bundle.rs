@@ -1,4 +1,8 @@
 use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+/// Lists the files of a bundle, at its root. No uploaded file may take its name.
+pub const BUNDLE_META_FILE: &str = "meta.json";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BundleMeta {
@@ -6,7 +10,7 @@ pub struct BundleMeta {
     pub files: Vec<FileMeta>
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct FileMeta {
     pub name: String,
     pub size: i64,
@@ -15,3 +19,30 @@ pub struct FileMeta {
     pub kind: String,
     pub checksum: Option<String>,
 }
+
+/// What an import does with a bundle file whose name is already taken in the project.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportConflict {
+    /// Nothing is imported.
+    #[default]
+    Fail,
+    /// The file in the project stays, the bundle one is dropped.
+    Skip,
+    /// Imported as `name (2).stl`, `name (3).stl`...
+    Rename,
+    /// Skipped when its checksum is the `file_hash` of the existing asset, fails otherwise.
+    SkipIdentical,
+}
+
+/// What happened to one file of an imported bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportAction {
+    Imported,
+    /// Imported under another name.
+    Renamed,
+    Skipped,
+    /// Skipped, the project already has the same content under that name.
+    SkippedIdentical,
+}
@@ -0,0 +1,42 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Measurements of a model asset, computed by the indexer. Lengths are in millimetres and Z is up.
+/// STL and OBJ declare neither, their coordinates are taken as written.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct AssetGeometry {
+    /// Unit declared by the file ("millimeter", "inch", "meter"...), values are converted from it.
+    /// None for STL and OBJ, which have no unit, their numbers are taken as millimetres.
+    pub unit: Option<String>,
+    pub bounding_box: BoundingBox,
+    pub triangle_count: i64,
+    /// Distinct positions, vertices repeated by the file are counted once.
+    pub vertex_count: i64,
+    /// mm²
+    pub surface_area: f64,
+    /// mm³, only meaningful when the mesh is watertight.
+    pub volume: f64,
+    /// Every edge is shared by exactly two triangles.
+    pub watertight: bool,
+}
+
+/// Axis aligned, Z up.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct BoundingBox {
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    /// max - min
+    pub size: [f64; 3],
+}
+
+impl BoundingBox {
+    pub fn new(min: [f64; 3], max: [f64; 3]) -> Self {
+        Self { min, max, size: [max[0] - min[0], max[1] - min[1], max[2] - min[2]] }
+    }
+}
+
+/// Assets the indexer measures. The SQL side filters on the same extensions.
+pub fn has_geometry_source(file_path: &str) -> bool {
+    let lower = file_path.to_ascii_lowercase();
+    [".stl", ".obj", ".3mf", ".gltf", ".glb"].iter().any(|ext| lower.ends_with(ext))
+}
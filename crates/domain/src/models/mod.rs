@@ -1,2 +1,8 @@
+pub mod printer;
 pub mod project;
-pub mod bundle;
\ No newline at end of file
+pub mod bundle;
+pub mod asset;
+pub mod asset_metadata;
+pub mod geometry;
+pub mod sync;
+pub mod thumbnail;
\ No newline at end of file
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Build volume of a printer, in millimetres. Z is the height.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BuildVolume {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl BuildVolume {
+    pub fn is_valid(&self) -> bool {
+        [self.x, self.y, self.z].iter().all(|v| v.is_finite() && *v > 0.0)
+    }
+}
@@ -2,6 +2,8 @@ use serde::Serialize;
 use sqlx::FromRow;
 use utoipa::ToSchema;
 
+use crate::models::thumbnail::ThumbnailUrls;
+
 #[derive(Debug, Serialize, FromRow, ToSchema)]
 pub struct ProjectRow {
     pub id: String,
@@ -12,4 +14,20 @@ pub struct ProjectRow {
     pub created_at: String,
     pub updated_at: String,
     pub last_scanned_at: Option<String>,
+    /// `thumbnail_hash` of the main image, selected along with the project.
+    #[serde(skip)]
+    pub main_image_thumbnail_hash: Option<String>,
+    /// Thumbnails of the main image, once they are generated.
+    #[sqlx(skip)]
+    pub thumbnails: Option<ThumbnailUrls>,
+}
+
+impl ProjectRow {
+    pub fn with_thumbnails(mut self) -> Self {
+        self.thumbnails = match (&self.main_image_id, &self.main_image_thumbnail_hash) {
+            (Some(asset_id), Some(hash)) => Some(ThumbnailUrls::new(asset_id, hash)),
+            _ => None,
+        };
+        self
+    }
 }
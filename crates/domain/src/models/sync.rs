@@ -0,0 +1,100 @@
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    Full,
+    Incremental,
+    /// Deletes upload bundles older than the TTL. Doesn't touch the index.
+    BundleGc,
+    /// Rehashes indexed files and reports the ones that no longer match. Doesn't touch the index.
+    Verify,
+}
+
+impl SyncMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncMode::Full => "full",
+            SyncMode::Incremental => "incremental",
+            SyncMode::BundleGc => "bundle_gc",
+            SyncMode::Verify => "verify",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "full" => Some(SyncMode::Full),
+            "incremental" => Some(SyncMode::Incremental),
+            "bundle_gc" => Some(SyncMode::BundleGc),
+            "verify" => Some(SyncMode::Verify),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncReason {
+    Manual,
+    Scheduled,
+    Watcher,
+}
+
+impl SyncReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncReason::Manual => "manual",
+            SyncReason::Scheduled => "scheduled",
+            SyncReason::Watcher => "watcher",
+        }
+    }
+}
+
+/// Stored as `sync_runs.options_json`. Identical options are what makes two queued runs the same run.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct SyncOptions {
+    /// Only sync these project folders (relative to the library root). Every folder when missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folders: Option<Vec<String>>,
+    /// Compute what the sync would change without writing to `projects`/`assets`. Full and incremental only.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// `bundle_gc` only: bundles untouched for longer than this are deleted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bundle_ttl_secs: Option<u64>,
+}
+
+impl SyncOptions {
+    pub fn for_folders(mut folders: Vec<String>) -> Self {
+        folders.sort();
+        folders.dedup();
+        Self { folders: Some(folders), ..Self::default() }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(default)]
+pub struct SyncStats {
+    pub projects_scanned: u64,
+    pub projects_added: u64,
+    /// Projects whose folder is gone. Their assets are removed, the project stays.
+    pub projects_missing: u64,
+    pub projects_moved: u64,
+    pub assets_added: u64,
+    pub assets_updated: u64,
+    pub assets_removed: u64,
+    pub assets_unchanged: u64,
+    pub hash_failed: u64,
+    pub assets_verified: u64,
+    pub assets_missing: u64,
+    pub hash_mismatch: u64,
+    pub bundles_removed: u64,
+    pub thumbnails_generated: u64,
+    pub thumbnails_failed: u64,
+    pub metadata_extracted: u64,
+    pub metadata_failed: u64,
+    pub geometry_analyzed: u64,
+    pub geometry_failed: u64,
+}
@@ -0,0 +1,37 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Longest side of the generated thumbnails, in pixels. Smaller images are never upscaled.
+pub const THUMBNAIL_SIZES: [u32; 2] = [SMALL_THUMBNAIL, LARGE_THUMBNAIL];
+pub const SMALL_THUMBNAIL: u32 = 256;
+pub const LARGE_THUMBNAIL: u32 = 1024;
+
+/// Where lima-server serves `data/state/thumbnails` from.
+pub const THUMBNAILS_URL_PREFIX: &str = "/media/thumbs";
+
+/// File name of one thumbnail inside the asset folder. It carries the hash of the source file, so a
+/// changed file gets new names and clients never see a stale cached thumbnail.
+pub fn thumbnail_file_name(source_hash: &str, size: u32) -> String {
+    let key = &source_hash[..source_hash.len().min(16)];
+    format!("{}-{}.jpg", key, size)
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ThumbnailUrls {
+    /// 256 px
+    pub small: String,
+    /// 1024 px
+    pub large: String,
+}
+
+impl ThumbnailUrls {
+    /// `source_hash` is the hash the thumbnails were generated from, as stored in `assets.thumbnail_hash`.
+    pub fn new(asset_id: &str, source_hash: &str) -> Self {
+        let url = |size| format!("{}/{}/{}", THUMBNAILS_URL_PREFIX, asset_id, thumbnail_file_name(source_hash, size));
+
+        Self {
+            small: url(SMALL_THUMBNAIL),
+            large: url(LARGE_THUMBNAIL),
+        }
+    }
+}
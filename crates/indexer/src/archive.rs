@@ -0,0 +1,344 @@
+use std::io::{self, Read, Write};
+use std::time::SystemTime;
+
+use flate2::Crc;
+use flate2::write::DeflateEncoder;
+use time::OffsetDateTime;
+
+// The `zip` crate seeks back to patch every local header, which a response body can't do. This
+// writer only appends: sizes and CRC go in a data descriptor after each file, as streaming zip
+// tools do, and Zip64 records are used where the 32-bit fields are too small.
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR: u32 = 0x0807_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP64_END: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+const END: u32 = 0x0605_4b50;
+
+// Bit 3: sizes in a data descriptor. Bit 11: UTF-8 names.
+const FLAGS: u16 = 0x0008 | 0x0800;
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+const VERSION: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+// Unix, so readers apply the permissions in the external attributes.
+const MADE_BY_UNIX: u16 = 3 << 8;
+const FILE_MODE: u32 = 0o100644;
+
+// Above this a file gets 64-bit sizes. Leaves room for deflate growing incompressible data.
+const ZIP64_THRESHOLD: u64 = 0xF000_0000;
+const MAX_32: u64 = 0xFFFF_FFFF;
+
+struct CentralEntry {
+    name: String,
+    method: u16,
+    dos_time: u16,
+    dos_date: u16,
+    crc: u32,
+    compressed_size: u64,
+    size: u64,
+    offset: u64,
+    zip64: bool,
+}
+
+/// Writes a zip archive to a stream that can't seek, like an HTTP response.
+pub struct ZipStream<W: Write> {
+    out: CountingWriter<W>,
+    entries: Vec<CentralEntry>,
+}
+
+impl<W: Write> ZipStream<W> {
+    pub fn new(out: W) -> Self {
+        Self { out: CountingWriter { inner: out, written: 0 }, entries: Vec::new() }
+    }
+
+    /// Adds the content of `reader` as `name`, a `/` separated path. `size` is what the reader is
+    /// expected to give, it only decides whether the entry needs Zip64.
+    pub fn add_file(&mut self, name: &str, reader: impl Read, size: u64, modified: SystemTime, compress: bool) -> io::Result<()> {
+        let (dos_time, dos_date) = dos_date_time(modified);
+        let method = if compress { DEFLATED } else { STORED };
+        let zip64 = size >= ZIP64_THRESHOLD;
+        let offset = self.out.written;
+
+        // Sizes and CRC are unknown until the data is written, they are zero here.
+        let extra = if zip64 { zip64_extra(&[0, 0]) } else { Vec::new() };
+        let header_size = if zip64 { MAX_32 as u32 } else { 0 };
+        let mut header = Vec::with_capacity(30 + name.len() + extra.len());
+        put_u32(&mut header, LOCAL_HEADER);
+        put_u16(&mut header, if zip64 { VERSION_ZIP64 } else { VERSION });
+        put_u16(&mut header, FLAGS);
+        put_u16(&mut header, method);
+        put_u16(&mut header, dos_time);
+        put_u16(&mut header, dos_date);
+        put_u32(&mut header, 0);
+        put_u32(&mut header, header_size);
+        put_u32(&mut header, header_size);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, extra.len() as u16);
+        header.extend_from_slice(name.as_bytes());
+        header.extend_from_slice(&extra);
+        self.out.write_all(&header)?;
+
+        let data_start = self.out.written;
+        let mut source = CrcReader { inner: reader, crc: Crc::new(), read: 0 };
+        if compress {
+            let mut encoder = DeflateEncoder::new(&mut self.out, flate2::Compression::default());
+            io::copy(&mut source, &mut encoder)?;
+            encoder.finish()?;
+        } else {
+            io::copy(&mut source, &mut self.out)?;
+        }
+        let compressed_size = self.out.written - data_start;
+        let size = source.read;
+        let crc = source.crc.sum();
+
+        if !zip64 && (size > MAX_32 || compressed_size > MAX_32) {
+            return Err(io::Error::other(format!("{} grew past 4 GiB while being archived", name)));
+        }
+
+        let mut descriptor = Vec::with_capacity(24);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR);
+        put_u32(&mut descriptor, crc);
+        if zip64 {
+            put_u64(&mut descriptor, compressed_size);
+            put_u64(&mut descriptor, size);
+        } else {
+            put_u32(&mut descriptor, compressed_size as u32);
+            put_u32(&mut descriptor, size as u32);
+        }
+        self.out.write_all(&descriptor)?;
+
+        self.entries.push(CentralEntry {
+            name: name.to_string(),
+            method,
+            dos_time,
+            dos_date,
+            crc,
+            compressed_size,
+            size,
+            offset,
+            zip64,
+        });
+
+        Ok(())
+    }
+
+    /// Writes the central directory and gives the stream back.
+    pub fn finish(mut self) -> io::Result<W> {
+        let directory_start = self.out.written;
+
+        for entry in &self.entries {
+            let big_offset = entry.offset >= MAX_32;
+            let mut fields = Vec::new();
+            if entry.zip64 {
+                fields.extend([entry.size, entry.compressed_size]);
+            }
+            if big_offset {
+                fields.push(entry.offset);
+            }
+            let extra = if fields.is_empty() { Vec::new() } else { zip64_extra(&fields) };
+            let version = if fields.is_empty() { VERSION } else { VERSION_ZIP64 };
+
+            let mut header = Vec::with_capacity(46 + entry.name.len() + extra.len());
+            put_u32(&mut header, CENTRAL_HEADER);
+            put_u16(&mut header, MADE_BY_UNIX | version);
+            put_u16(&mut header, version);
+            put_u16(&mut header, FLAGS);
+            put_u16(&mut header, entry.method);
+            put_u16(&mut header, entry.dos_time);
+            put_u16(&mut header, entry.dos_date);
+            put_u32(&mut header, entry.crc);
+            put_u32(&mut header, if entry.zip64 { MAX_32 as u32 } else { entry.compressed_size as u32 });
+            put_u32(&mut header, if entry.zip64 { MAX_32 as u32 } else { entry.size as u32 });
+            put_u16(&mut header, entry.name.len() as u16);
+            put_u16(&mut header, extra.len() as u16);
+            put_u16(&mut header, 0);
+            put_u16(&mut header, 0);
+            put_u16(&mut header, 0);
+            put_u32(&mut header, FILE_MODE << 16);
+            put_u32(&mut header, if big_offset { MAX_32 as u32 } else { entry.offset as u32 });
+            header.extend_from_slice(entry.name.as_bytes());
+            header.extend_from_slice(&extra);
+            self.out.write_all(&header)?;
+        }
+
+        let directory_end = self.out.written;
+        let directory_size = directory_end - directory_start;
+        let count = self.entries.len() as u64;
+        let needs_zip64 = count >= 0xFFFF || directory_start >= MAX_32 || directory_size >= MAX_32;
+
+        let mut end = Vec::with_capacity(98);
+        if needs_zip64 {
+            put_u32(&mut end, ZIP64_END);
+            put_u64(&mut end, 44);
+            put_u16(&mut end, MADE_BY_UNIX | VERSION_ZIP64);
+            put_u16(&mut end, VERSION_ZIP64);
+            put_u32(&mut end, 0);
+            put_u32(&mut end, 0);
+            put_u64(&mut end, count);
+            put_u64(&mut end, count);
+            put_u64(&mut end, directory_size);
+            put_u64(&mut end, directory_start);
+
+            put_u32(&mut end, ZIP64_LOCATOR);
+            put_u32(&mut end, 0);
+            put_u64(&mut end, directory_end);
+            put_u32(&mut end, 1);
+        }
+        put_u32(&mut end, END);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, count.min(0xFFFF) as u16);
+        put_u16(&mut end, count.min(0xFFFF) as u16);
+        put_u32(&mut end, directory_size.min(MAX_32) as u32);
+        put_u32(&mut end, directory_start.min(MAX_32) as u32);
+        put_u16(&mut end, 0);
+        self.out.write_all(&end)?;
+
+        self.out.flush()?;
+        Ok(self.out.inner)
+    }
+}
+
+/// Formats that are already compressed only cost CPU to deflate again.
+pub fn worth_compressing(kind: &str, file_path: &str) -> bool {
+    let lower = file_path.to_ascii_lowercase();
+    !matches!(kind, "image" | "video" | "archive") && !lower.ends_with(".3mf") && !lower.ends_with(".glb")
+}
+
+struct CountingWriter<W> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// flate2's own CrcReader counts in 32 bits.
+struct CrcReader<R> {
+    inner: R,
+    crc: Crc,
+    read: u64,
+}
+
+impl<R: Read> Read for CrcReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        self.read += n as u64;
+        Ok(n)
+    }
+}
+
+fn zip64_extra(fields: &[u64]) -> Vec<u8> {
+    let mut extra = Vec::with_capacity(4 + fields.len() * 8);
+    put_u16(&mut extra, 0x0001);
+    put_u16(&mut extra, (fields.len() * 8) as u16);
+    for field in fields {
+        put_u64(&mut extra, *field);
+    }
+    extra
+}
+
+// MS-DOS time has a 2 second resolution and starts in 1980.
+fn dos_date_time(modified: SystemTime) -> (u16, u16) {
+    let time = OffsetDateTime::from(modified);
+    if time.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+
+    let year = time.year().min(2107) as u16;
+    let dos_time = ((time.hour() as u16) << 11) | ((time.minute() as u16) << 5) | (time.second() as u16 / 2);
+    let dos_date = ((year - 1980) << 9) | ((time.month() as u16) << 5) | time.day() as u16;
+    (dos_time, dos_date)
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use zip::{CompressionMethod, ZipArchive};
+
+    use super::*;
+
+    fn crc(data: &[u8]) -> u32 {
+        let mut crc = Crc::new();
+        crc.update(data);
+        crc.sum()
+    }
+
+    #[test]
+    fn entries_read_back_with_the_zip_crate() {
+        let text = "solid cube\n".repeat(1000);
+        let binary: Vec<u8> = (0..50_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+        // The size only decides on Zip64, a small file claimed past the threshold gets the records.
+        let files: [(&str, &[u8], u64, bool); 4] = [
+            ("models/cube.stl", text.as_bytes(), text.len() as u64, true),
+            ("images/photo.jpg", &binary, binary.len() as u64, false),
+            ("big/stored.bin", b"zip64 stored", ZIP64_THRESHOLD, false),
+            ("big/deflated.txt", text.as_bytes(), ZIP64_THRESHOLD + 1, true),
+        ];
+
+        let mut zip = ZipStream::new(Vec::new());
+        for (name, data, size, compress) in files {
+            zip.add_file(name, data, size, SystemTime::now(), compress).unwrap();
+        }
+        let bytes = zip.finish().unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), files.len());
+        for (index, (name, data, _, compress)) in files.into_iter().enumerate() {
+            let mut entry = archive.by_index(index).unwrap();
+            assert_eq!(entry.name(), name);
+            assert_eq!(entry.compression(), if compress { CompressionMethod::Deflated } else { CompressionMethod::Stored });
+            assert_eq!(entry.crc32(), crc(data));
+            assert_eq!(entry.size(), data.len() as u64);
+            assert_eq!(entry.unix_mode(), Some(FILE_MODE));
+            if compress {
+                assert!(entry.compressed_size() < entry.size());
+            } else {
+                assert_eq!(entry.compressed_size(), entry.size());
+            }
+
+            // The zip crate checks the CRC once everything is read.
+            let mut content = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            assert_eq!(content, data);
+        }
+    }
+
+    #[test]
+    fn zip64_end_record_past_0xffff_entries() {
+        let mut zip = ZipStream::new(Vec::new());
+        for i in 0..0x1_0000 {
+            zip.add_file(&format!("{}.txt", i), &b""[..], 0, SystemTime::now(), false).unwrap();
+        }
+        let bytes = zip.finish().unwrap();
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.len(), 0x1_0000);
+        assert_eq!(archive.by_index(0xFFFF).unwrap().name(), "65535.txt");
+    }
+}
@@ -0,0 +1,476 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::MultiGzDecoder;
+use lima_domain::models::asset::sanitize_asset_path;
+use lima_domain::models::bundle::BUNDLE_META_FILE;
+use sevenz_rust::{Password, SevenZReader};
+
+// Guards against decompression bombs. Counted on what is actually decompressed, sizes in headers
+// are whatever the archive claims.
+const MAX_ENTRIES: usize = 10_000;
+const MAX_EXTRACTED_BYTES: u64 = 16 * 1024 * 1024 * 1024;
+// Text STL and G-code compress well, but not this well.
+const MAX_RATIO: u64 = 100;
+const MIN_ALLOWANCE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    SevenZ,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// By extension, case insensitive. 3MF files are ZIPs too, but they are models.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if lower.ends_with(".7z") {
+            Some(ArchiveFormat::SevenZ)
+        } else if lower.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExtractError {
+    Io(io::Error),
+    Archive(String),
+    TooManyEntries,
+    TooLarge { limit: u64 },
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Io(e) => write!(f, "Filesystem error: {}", e),
+            ExtractError::Archive(e) => write!(f, "Invalid archive: {}", e),
+            ExtractError::TooManyEntries => write!(f, "Archive has more than {} entries", MAX_ENTRIES),
+            ExtractError::TooLarge { limit } => write!(f, "Archive expands to more than {} bytes", limit),
+        }
+    }
+}
+
+impl From<io::Error> for ExtractError {
+    fn from(e: io::Error) -> Self {
+        ExtractError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Rejected by the `skip` check, like `__MACOSX/` or dotfiles.
+    Ignored,
+    /// Absolute, with `..` segments, or the bundle meta file: it would land outside the destination
+    /// or replace a file that isn't the archive's.
+    UnsafePath,
+    /// A file of that name is already there.
+    Duplicate,
+}
+
+/// Paths are `prefix` joined with the path inside the archive.
+#[derive(Debug, Default)]
+pub struct Extraction {
+    pub files: Vec<String>,
+    pub skipped: Vec<(String, SkipReason)>,
+}
+
+/// Expands the regular files of `archive` under `root/prefix`, keeping the folders of the archive.
+/// Links and special files are left out and existing files are never replaced. On error, the files
+/// written so far are removed again. Blocking.
+pub fn extract_archive(
+    archive: &Path,
+    format: ArchiveFormat,
+    root: &Path,
+    prefix: &str,
+    skip: &dyn Fn(&str) -> bool,
+) -> Result<Extraction, ExtractError> {
+    let archive_size = std::fs::metadata(archive)?.len();
+    let limit = archive_size.saturating_mul(MAX_RATIO).clamp(MIN_ALLOWANCE, MAX_EXTRACTED_BYTES);
+
+    let mut writer = EntryWriter {
+        root,
+        prefix,
+        skip,
+        entries: 0,
+        remaining: limit,
+        limit,
+        written: Vec::new(),
+        extraction: Extraction::default(),
+    };
+
+    let result = match format {
+        ArchiveFormat::Zip => extract_zip(archive, &mut writer),
+        ArchiveFormat::SevenZ => extract_7z(archive, &mut writer),
+        ArchiveFormat::Tar => extract_tar(File::open(archive)?, &mut writer),
+        ArchiveFormat::TarGz => extract_tar(MultiGzDecoder::new(File::open(archive)?), &mut writer),
+    };
+
+    if let Err(e) = result {
+        for path in &writer.written {
+            let _ = std::fs::remove_file(path);
+        }
+        return Err(e);
+    }
+
+    Ok(writer.extraction)
+}
+
+fn extract_zip(archive: &Path, writer: &mut EntryWriter) -> Result<(), ExtractError> {
+    let mut zip = zip::ZipArchive::new(File::open(archive)?).map_err(|e| ExtractError::Archive(e.to_string()))?;
+    if zip.len() > MAX_ENTRIES {
+        return Err(ExtractError::TooManyEntries);
+    }
+
+    for index in 0..zip.len() {
+        let mut entry = zip.by_index(index).map_err(|e| ExtractError::Archive(e.to_string()))?;
+        if !entry.is_file() || entry.is_symlink() {
+            writer.count_entry()?;
+            continue;
+        }
+        let name = entry.name().to_string();
+        writer.write(&name, &mut entry)?;
+    }
+
+    Ok(())
+}
+
+fn extract_7z(archive: &Path, writer: &mut EntryWriter) -> Result<(), ExtractError> {
+    let mut reader = SevenZReader::open(archive, Password::empty()).map_err(|e| ExtractError::Archive(e.to_string()))?;
+    if reader.archive().files.len() > MAX_ENTRIES {
+        return Err(ExtractError::TooManyEntries);
+    }
+
+    // The callback can only return the library's errors, ours wait here.
+    let mut failure = None;
+    reader.for_each_entries(|entry, data| {
+        let result = if entry.is_directory() || entry.is_anti_item() {
+            writer.count_entry()
+        } else {
+            writer.write(entry.name(), data)
+        };
+
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                failure = Some(e);
+                Ok(false)
+            }
+        }
+    }).map_err(|e| ExtractError::Archive(e.to_string()))?;
+
+    failure.map_or(Ok(()), Err)
+}
+
+fn extract_tar(reader: impl Read, writer: &mut EntryWriter) -> Result<(), ExtractError> {
+    let mut tar = tar::Archive::new(reader);
+
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        if !entry_type.is_file() && entry_type != tar::EntryType::Continuous {
+            writer.count_entry()?;
+            continue;
+        }
+        let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        writer.write(&name, &mut entry)?;
+    }
+
+    Ok(())
+}
+
+struct EntryWriter<'a> {
+    root: &'a Path,
+    prefix: &'a str,
+    skip: &'a dyn Fn(&str) -> bool,
+    entries: usize,
+    /// Bytes that may still be decompressed.
+    remaining: u64,
+    limit: u64,
+    written: Vec<PathBuf>,
+    extraction: Extraction,
+}
+
+impl EntryWriter<'_> {
+    fn count_entry(&mut self) -> Result<(), ExtractError> {
+        self.entries += 1;
+        if self.entries > MAX_ENTRIES {
+            return Err(ExtractError::TooManyEntries);
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, entry_name: &str, data: &mut dyn Read) -> Result<(), ExtractError> {
+        self.count_entry()?;
+
+        let name = sanitize_asset_path(entry_name)
+            .map(|path| if self.prefix.is_empty() { path } else { format!("{}/{}", self.prefix, path) })
+            .filter(|name| name != BUNDLE_META_FILE);
+        let Some(name) = name else {
+            self.extraction.skipped.push((entry_name.to_string(), SkipReason::UnsafePath));
+            return self.discard(data);
+        };
+        if (self.skip)(&name) {
+            self.extraction.skipped.push((name, SkipReason::Ignored));
+            return self.discard(data);
+        }
+
+        let destination = self.root.join(&name);
+        if let Some(parent) = destination.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&destination) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                self.extraction.skipped.push((name, SkipReason::Duplicate));
+                return self.discard(data);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        self.written.push(destination);
+
+        self.copy(data, &mut file)?;
+        self.extraction.files.push(name);
+        Ok(())
+    }
+
+    // Skipped entries still have to be read through in solid 7z and compressed tar archives.
+    fn discard(&mut self, data: &mut dyn Read) -> Result<(), ExtractError> {
+        self.copy(data, &mut io::sink())
+    }
+
+    fn copy(&mut self, data: &mut dyn Read, out: &mut dyn io::Write) -> Result<(), ExtractError> {
+        // One byte over the allowance tells a full allowance from a bomb.
+        let copied = io::copy(&mut data.take(self.remaining + 1), out)?;
+        if copied > self.remaining {
+            return Err(ExtractError::TooLarge { limit: self.limit });
+        }
+        self.remaining -= copied;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("lima-extract-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn no_skip(_: &str) -> bool {
+        false
+    }
+
+    fn entry_writer<'a>(root: &'a Path, prefix: &'a str, skip: &'a dyn Fn(&str) -> bool, limit: u64) -> EntryWriter<'a> {
+        EntryWriter {
+            root,
+            prefix,
+            skip,
+            entries: 0,
+            remaining: limit,
+            limit,
+            written: Vec::new(),
+            extraction: Extraction::default(),
+        }
+    }
+
+    fn zip_file(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    // The header is written by hand, `tar::Builder` refuses the names we want to test.
+    fn tar_file(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut tar = tar::Builder::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            let mut header = tar::Header::new_old();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_cksum();
+            tar.append(&header, *data).unwrap();
+        }
+        tar.finish().unwrap();
+    }
+
+    fn skipped(extraction: &Extraction, reason: SkipReason) -> Vec<&str> {
+        extraction.skipped.iter().filter(|(_, r)| *r == reason).map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn unsafe_entries_are_skipped() {
+        let dir = TempDir::new("unsafe");
+        let mut writer = entry_writer(&dir.0, "", &no_skip, MIN_ALLOWANCE);
+        for name in ["../up.stl", "stls/../../up.stl", "/etc/evil.stl", "..\\up.stl", "nul\0.stl", "ok.stl"] {
+            writer.write(name, &mut &b"solid"[..]).unwrap();
+        }
+
+        assert_eq!(writer.extraction.files, ["ok.stl"]);
+        assert_eq!(skipped(&writer.extraction, SkipReason::UnsafePath).len(), 5);
+        assert!(!dir.0.parent().unwrap().join("up.stl").exists());
+    }
+
+    #[test]
+    fn bundle_meta_file_is_only_refused_at_the_root() {
+        let dir = TempDir::new("meta");
+        let mut root = entry_writer(&dir.0, "", &no_skip, MIN_ALLOWANCE);
+        root.write(BUNDLE_META_FILE, &mut &b"{}"[..]).unwrap();
+        root.write("stls/meta.json", &mut &b"{}"[..]).unwrap();
+        assert_eq!(skipped(&root.extraction, SkipReason::UnsafePath), [BUNDLE_META_FILE]);
+        assert_eq!(root.extraction.files, ["stls/meta.json"]);
+        assert!(!dir.0.join(BUNDLE_META_FILE).exists());
+
+        // Extracted next to `pack/archive.zip`, it isn't the bundle's.
+        let mut nested = entry_writer(&dir.0, "pack", &no_skip, MIN_ALLOWANCE);
+        nested.write(BUNDLE_META_FILE, &mut &b"{}"[..]).unwrap();
+        assert_eq!(nested.extraction.files, ["pack/meta.json"]);
+    }
+
+    #[test]
+    fn skip_check_sees_the_prefixed_name() {
+        let dir = TempDir::new("skip");
+        let skip = |name: &str| name.starts_with("pack/__MACOSX/") || name.ends_with(".DS_Store");
+        let mut writer = entry_writer(&dir.0, "pack", &skip, MIN_ALLOWANCE);
+        for name in ["__MACOSX/._base.stl", "stls/.DS_Store", "base.stl"] {
+            writer.write(name, &mut &b"solid"[..]).unwrap();
+        }
+
+        assert_eq!(writer.extraction.files, ["pack/base.stl"]);
+        assert_eq!(skipped(&writer.extraction, SkipReason::Ignored), ["pack/__MACOSX/._base.stl", "pack/stls/.DS_Store"]);
+        assert!(!dir.0.join("pack/__MACOSX").exists());
+    }
+
+    #[test]
+    fn existing_files_are_not_replaced() {
+        let dir = TempDir::new("duplicate");
+        std::fs::write(dir.0.join("base.stl"), "mine").unwrap();
+        let mut writer = entry_writer(&dir.0, "", &no_skip, MIN_ALLOWANCE);
+        writer.write("base.stl", &mut &b"theirs"[..]).unwrap();
+
+        assert_eq!(skipped(&writer.extraction, SkipReason::Duplicate), ["base.stl"]);
+        assert_eq!(std::fs::read_to_string(dir.0.join("base.stl")).unwrap(), "mine");
+    }
+
+    #[test]
+    fn entries_past_the_limit_fail() {
+        let dir = TempDir::new("entries");
+        let mut writer = entry_writer(&dir.0, "", &no_skip, MIN_ALLOWANCE);
+        for _ in 0..MAX_ENTRIES {
+            writer.count_entry().unwrap();
+        }
+
+        assert!(matches!(writer.write("one-more.stl", &mut &b""[..]), Err(ExtractError::TooManyEntries)));
+    }
+
+    #[test]
+    fn zip_with_too_many_entries_is_refused() {
+        let dir = TempDir::new("zip-entries");
+        let names: Vec<String> = (0..=MAX_ENTRIES).map(|i| format!("{}.txt", i)).collect();
+        let entries: Vec<(&str, &[u8])> = names.iter().map(|name| (name.as_str(), &b""[..])).collect();
+        zip_file(&dir.0.join("many.zip"), &entries);
+
+        let result = extract_archive(&dir.0.join("many.zip"), ArchiveFormat::Zip, &dir.0, "", &no_skip);
+        assert!(matches!(result, Err(ExtractError::TooManyEntries)));
+        assert!(!dir.0.join("0.txt").exists());
+    }
+
+    #[test]
+    fn expanding_past_the_allowance_fails() {
+        let dir = TempDir::new("bomb");
+        let mut writer = entry_writer(&dir.0, "", &no_skip, 1024);
+        writer.write("fits.stl", &mut &[0u8; 1024][..]).unwrap();
+
+        let result = writer.write("bomb.stl", &mut io::repeat(0));
+        assert!(matches!(result, Err(ExtractError::TooLarge { limit: 1024 })));
+        // Skipped entries are read through, they count too.
+        let mut skipping = entry_writer(&dir.0, "", &|_| true, 1024);
+        assert!(matches!(skipping.write("bomb.stl", &mut io::repeat(0)), Err(ExtractError::TooLarge { .. })));
+    }
+
+    #[test]
+    fn zip_is_extracted_under_the_prefix() {
+        let dir = TempDir::new("zip");
+        zip_file(&dir.0.join("pack.zip"), &[
+            ("stls/base.stl", b"solid base"),
+            ("../up.stl", b"solid up"),
+            ("meta.json", b"{}"),
+            ("__MACOSX/stls/._base.stl", b""),
+        ]);
+        let skip = |name: &str| name.split('/').any(|segment| segment == "__MACOSX");
+
+        let extraction = extract_archive(&dir.0.join("pack.zip"), ArchiveFormat::Zip, &dir.0, "", &skip).unwrap();
+        assert_eq!(extraction.files, ["stls/base.stl"]);
+        assert_eq!(skipped(&extraction, SkipReason::UnsafePath), ["../up.stl", "meta.json"]);
+        assert_eq!(skipped(&extraction, SkipReason::Ignored), ["__MACOSX/stls/._base.stl"]);
+        assert_eq!(std::fs::read_to_string(dir.0.join("stls/base.stl")).unwrap(), "solid base");
+    }
+
+    #[test]
+    fn tar_unsafe_entries_are_skipped() {
+        let dir = TempDir::new("tar");
+        tar_file(&dir.0.join("pack.tar"), &[
+            ("../up.stl", b"solid up"),
+            ("/abs.stl", b"solid abs"),
+            ("win\\up.stl", b"solid win"),
+            ("base.stl", b"solid base"),
+        ]);
+
+        let extraction = extract_archive(&dir.0.join("pack.tar"), ArchiveFormat::Tar, &dir.0, "pack", &no_skip).unwrap();
+        assert_eq!(extraction.files, ["pack/base.stl"]);
+        assert_eq!(skipped(&extraction, SkipReason::UnsafePath), ["../up.stl", "/abs.stl", "win\\up.stl"]);
+    }
+
+    #[test]
+    fn written_files_are_removed_on_error() {
+        let dir = TempDir::new("rollback");
+        let stored = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let mut zip = zip::ZipWriter::new(File::create(dir.0.join("broken.zip")).unwrap());
+        zip.start_file("first.stl", stored).unwrap();
+        zip.write_all(b"solid first").unwrap();
+        zip.start_file("second.stl", stored).unwrap();
+        zip.write_all(b"solid second").unwrap();
+        zip.finish().unwrap();
+        // The CRC check of the second entry fails once its data is read.
+        let mut bytes = std::fs::read(dir.0.join("broken.zip")).unwrap();
+        let data = bytes.windows(12).position(|w| w == b"solid second").unwrap();
+        bytes[data] = b'X';
+        std::fs::write(dir.0.join("broken.zip"), bytes).unwrap();
+
+        let result = extract_archive(&dir.0.join("broken.zip"), ArchiveFormat::Zip, &dir.0, "", &no_skip);
+        assert!(result.is_err());
+        assert!(!dir.0.join("first.stl").exists());
+    }
+}
@@ -0,0 +1,355 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use flate2::read::ZlibDecoder;
+use lima_domain::models::asset_metadata::AssetMetadata;
+
+// Slicers write their thumbnails and summary at the top of the file and, for PrusaSlicer and Orca,
+// the totals and config at the bottom. The moves in between are never read.
+const HEAD_BYTES: u64 = 4 * 1024 * 1024;
+const TAIL_BYTES: u64 = 512 * 1024;
+
+const BGCODE_MAGIC: &[u8; 4] = b"GCDE";
+// Bigger blocks than this in the header of a binary G-code mean a corrupt file.
+const MAX_BGCODE_BLOCK: u32 = 32 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum GcodeError {
+    Io(std::io::Error),
+    InvalidBgcode(&'static str),
+}
+
+impl fmt::Display for GcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GcodeError::Io(e) => write!(f, "Failed reading G-code: {}", e),
+            GcodeError::InvalidBgcode(reason) => write!(f, "Invalid binary G-code: {}", reason),
+        }
+    }
+}
+
+impl From<std::io::Error> for GcodeError {
+    fn from(e: std::io::Error) -> Self {
+        GcodeError::Io(e)
+    }
+}
+
+/// What the slicer left in the header of a G-code file.
+#[derive(Debug, Default)]
+pub struct GcodeInfo {
+    pub metadata: AssetMetadata,
+    /// The biggest embedded thumbnail, as stored (PNG, JPEG or QOI).
+    pub thumbnail: Option<Vec<u8>>,
+}
+
+/// Reads a `.gcode` or Prusa binary `.bgcode` file. Blocking, the file is only read.
+pub fn read_gcode(path: &Path) -> Result<GcodeInfo, GcodeError> {
+    let mut file = File::open(path)?;
+
+    let mut magic = [0u8; 4];
+    let is_binary = file.read_exact(&mut magic).is_ok() && &magic == BGCODE_MAGIC;
+    file.seek(SeekFrom::Start(0))?;
+
+    let mut info = GcodeInfo::default();
+    let mut thumbnails = Thumbnails::default();
+    if is_binary {
+        read_binary(BufReader::new(file), &mut info.metadata, &mut thumbnails)?;
+    } else {
+        read_text(file, &mut info.metadata, &mut thumbnails)?;
+    }
+
+    info.thumbnail = thumbnails.best;
+    Ok(info)
+}
+
+// Keeps the biggest thumbnail, slicers embed several sizes.
+#[derive(Default)]
+struct Thumbnails {
+    best: Option<Vec<u8>>,
+    best_pixels: u64,
+}
+
+impl Thumbnails {
+    fn offer(&mut self, width: u64, height: u64, bytes: Vec<u8>) {
+        // Sizes come from the text header, nothing keeps them sensible.
+        let pixels = width.saturating_mul(height);
+        if self.best.is_none() || pixels > self.best_pixels {
+            self.best = Some(bytes);
+            self.best_pixels = pixels;
+        }
+    }
+}
+
+fn read_text(mut file: File, metadata: &mut AssetMetadata, thumbnails: &mut Thumbnails) -> Result<(), GcodeError> {
+    let len = file.metadata()?.len();
+
+    let mut head = Vec::new();
+    (&mut file).take(HEAD_BYTES).read_to_end(&mut head)?;
+    read_text_part(&head, metadata, thumbnails);
+
+    if len > HEAD_BYTES {
+        let start = (len - TAIL_BYTES).max(HEAD_BYTES);
+        file.seek(SeekFrom::Start(start))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+
+        // Starts in the middle of a line.
+        let first_line = tail.iter().position(|&b| b == b'\n').map_or(tail.len(), |i| i + 1);
+        read_text_part(&tail[first_line..], metadata, thumbnails);
+    }
+
+    Ok(())
+}
+
+fn read_text_part(bytes: &[u8], metadata: &mut AssetMetadata, thumbnails: &mut Thumbnails) {
+    // (width, height, base64 so far) of the thumbnail being read.
+    let mut current: Option<(u64, u64, String)> = None;
+
+    for line in bytes.split(|&b| b == b'\n') {
+        let Ok(line) = std::str::from_utf8(line) else {
+            continue;
+        };
+        let Some(comment) = line.trim().strip_prefix(';') else {
+            continue;
+        };
+        let comment = comment.trim();
+
+        // "thumbnail begin 300x300 12345", "thumbnail_JPG begin ..." or "thumbnail_QOI begin ...".
+        if let Some(rest) = comment.strip_prefix("thumbnail") {
+            let mut words = rest.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(marker), size) if marker.ends_with("begin") => {
+                    let (width, height) = size.and_then(parse_size).unwrap_or((0, 0));
+                    current = Some((width, height, String::new()));
+                    continue;
+                }
+                (Some(marker), _) if marker.ends_with("end") => {
+                    if let Some((width, height, data)) = current.take()
+                        && let Ok(bytes) = BASE64.decode(data)
+                    {
+                        thumbnails.offer(width, height, bytes);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((_, _, data)) = current.as_mut() {
+            data.push_str(comment);
+            continue;
+        }
+
+        // Orca puts several values on one line: "model printing time: 1h; total estimated time: 2h".
+        for part in comment.split(';') {
+            read_header_value(metadata, part);
+        }
+    }
+}
+
+fn parse_size(size: &str) -> Option<(u64, u64)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+// "key = value", "key: value" or "generated by ..." lines. The first value found wins.
+fn read_header_value(metadata: &mut AssetMetadata, part: &str) {
+    let part = part.trim();
+    let lower = part.to_ascii_lowercase();
+
+    for prefix in ["generated by ", "generated with "] {
+        if lower.starts_with(prefix) {
+            let producer = &part[prefix.len()..];
+            // PrusaSlicer and Orca append " on <date> at <time>".
+            let producer = producer.split(" on ").next().unwrap_or(producer);
+            set_once(&mut metadata.slicer, producer.trim().replace('_', " "));
+            return;
+        }
+    }
+
+    let Some(split) = part.find(['=', ':']) else {
+        return;
+    };
+    let key = part[..split].trim().to_ascii_lowercase();
+    let value = part[split + 1..].trim();
+    if value.is_empty() {
+        return;
+    }
+
+    match key.as_str() {
+        "producer" => set_once(&mut metadata.slicer, value.to_string()),
+        "printer_model" | "target_machine.name" => set_once(&mut metadata.printer_model, value.to_string()),
+        "estimated printing time (normal mode)" | "total estimated time" | "time" => {
+            metadata.print_time_seconds = metadata.print_time_seconds.or_else(|| parse_duration(value));
+        }
+        "filament used [g]" | "total filament used [g]" => {
+            metadata.filament_used_g = metadata.filament_used_g.or_else(|| sum_values(value, ""));
+        }
+        "filament used [mm]" => {
+            metadata.filament_used_mm = metadata.filament_used_mm.or_else(|| sum_values(value, ""));
+        }
+        // Cura writes metres, "Filament used: 1.23456m".
+        "filament used" => {
+            metadata.filament_used_mm = metadata.filament_used_mm.or_else(|| sum_values(value, "m").map(|metres| metres * 1000.0));
+        }
+        _ => {}
+    }
+}
+
+fn set_once(field: &mut Option<String>, value: String) {
+    if field.is_none() && !value.is_empty() {
+        *field = Some(value);
+    }
+}
+
+// One value per extruder, comma separated.
+fn sum_values(value: &str, unit: &str) -> Option<f64> {
+    let mut total = 0.0;
+    for part in value.split(',') {
+        let part = part.trim();
+        let number: f64 = part.strip_suffix(unit).unwrap_or(part).trim().parse().ok()?;
+        total += number;
+    }
+
+    total.is_finite().then_some(total)
+}
+
+// "1d 2h 3m 4s" from PrusaSlicer and Orca, plain seconds from Cura. `None` past `u64::MAX` seconds.
+fn parse_duration(value: &str) -> Option<u64> {
+    if let Ok(seconds) = value.parse::<f64>() {
+        return (seconds.is_finite() && seconds >= 0.0).then_some(seconds.round() as u64);
+    }
+
+    let mut total = 0;
+    for token in value.split_whitespace() {
+        let split = token.find(|c: char| !c.is_ascii_digit())?;
+        let amount: u64 = token[..split].parse().ok()?;
+        let unit = match &token[split..] {
+            "d" => 86_400,
+            "h" => 3_600,
+            "m" => 60,
+            "s" => 1,
+            _ => return None,
+        };
+        total = amount.checked_mul(unit).and_then(|seconds| seconds.checked_add(total))?;
+    }
+
+    Some(total)
+}
+
+// Block layout of libbgcode: a file header, then blocks of (header, parameters, data, checksum).
+// Metadata and thumbnails come before the G-code blocks, reading stops at the first of those.
+fn read_binary(mut reader: BufReader<File>, metadata: &mut AssetMetadata, thumbnails: &mut Thumbnails) -> Result<(), GcodeError> {
+    let mut header = [0u8; 10];
+    reader.read_exact(&mut header)?;
+    let checksum_size = match u16::from_le_bytes([header[8], header[9]]) {
+        0 => 0,
+        1 => 4,
+        _ => return Err(GcodeError::InvalidBgcode("unknown checksum type")),
+    };
+
+    loop {
+        let mut block = [0u8; 8];
+        match reader.read_exact(&mut block) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+
+        let block_type = u16::from_le_bytes([block[0], block[1]]);
+        let compression = u16::from_le_bytes([block[2], block[3]]);
+        let uncompressed_size = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+        let data_size = if compression == 0 { uncompressed_size } else { read_u32(&mut reader)? };
+
+        const GCODE: u16 = 1;
+        const THUMBNAIL: u16 = 5;
+        if block_type == GCODE {
+            break;
+        }
+        if data_size > MAX_BGCODE_BLOCK || uncompressed_size > MAX_BGCODE_BLOCK {
+            return Err(GcodeError::InvalidBgcode("block too big"));
+        }
+
+        let mut params = [0u8; 6];
+        let params = &mut params[..if block_type == THUMBNAIL { 6 } else { 2 }];
+        reader.read_exact(params)?;
+
+        let mut data = vec![0u8; data_size as usize];
+        reader.read_exact(&mut data)?;
+        reader.seek_relative(checksum_size)?;
+
+        if block_type == THUMBNAIL {
+            let width = u16::from_le_bytes([params[2], params[3]]);
+            let height = u16::from_le_bytes([params[4], params[5]]);
+            thumbnails.offer(width as u64, height as u64, data);
+            continue;
+        }
+
+        // File, printer, print and slicer metadata: "key=value" lines. Heatshrink is only used for
+        // G-code blocks in practice, anything other than none or deflate is skipped.
+        let text = match compression {
+            0 => data,
+            1 => {
+                let mut inflated = Vec::with_capacity(uncompressed_size as usize);
+                ZlibDecoder::new(data.as_slice()).take(uncompressed_size as u64).read_to_end(&mut inflated)?;
+                inflated
+            }
+            _ => continue,
+        };
+
+        for line in String::from_utf8_lossy(&text).lines() {
+            read_header_value(metadata, line);
+        }
+    }
+
+    Ok(())
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, std::io::Error> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(lines: &str) -> (AssetMetadata, Option<Vec<u8>>) {
+        let mut metadata = AssetMetadata::default();
+        let mut thumbnails = Thumbnails::default();
+        read_text_part(lines.as_bytes(), &mut metadata, &mut thumbnails);
+        (metadata, thumbnails.best)
+    }
+
+    #[test]
+    fn durations_are_parsed() {
+        assert_eq!(parse_duration("1d 2h 3m 4s"), Some(93_784));
+        assert_eq!(parse_duration("12m 5s"), Some(725));
+        assert_eq!(parse_duration("4521.6"), Some(4522));
+        assert_eq!(parse_duration("2 weeks"), None);
+    }
+
+    #[test]
+    fn overflowing_durations_are_dropped() {
+        assert_eq!(parse_duration("9999999999999999999d"), None);
+        assert_eq!(parse_duration("18446744073709551615s 1s"), None);
+
+        let (metadata, _) = header("; estimated printing time (normal mode) = 9999999999999999999d\n; total estimated time: 1h\n");
+        assert_eq!(metadata.print_time_seconds, Some(3_600));
+    }
+
+    #[test]
+    fn oversized_thumbnails_are_compared() {
+        let (_, thumbnail) = header(concat!(
+            "; thumbnail begin 16x16 4\n; AQI=\n; thumbnail end\n",
+            "; thumbnail begin 99999999999x99999999999 4\n; AwQ=\n; thumbnail end\n",
+            "; thumbnail begin 300x300 4\n; BQY=\n; thumbnail end\n",
+        ));
+        assert_eq!(thumbnail, Some(vec![3, 4]));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use lima_db::queries::geometry::upsert_asset_geometry;
+use lima_domain::models::geometry::{AssetGeometry, BoundingBox};
+use sqlx::{Pool, Sqlite};
+
+use crate::mesh::{Mesh, MeshError, load_mesh};
+
+#[derive(Debug)]
+pub enum GeometryError {
+    Io(std::io::Error),
+    Mesh(MeshError),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::Io(e) => write!(f, "Filesystem error: {}", e),
+            GeometryError::Mesh(e) => write!(f, "Mesh error: {}", e),
+            GeometryError::Db(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for GeometryError {
+    fn from(e: std::io::Error) -> Self {
+        GeometryError::Io(e)
+    }
+}
+
+impl From<MeshError> for GeometryError {
+    fn from(e: MeshError) -> Self {
+        GeometryError::Mesh(e)
+    }
+}
+
+impl From<sqlx::Error> for GeometryError {
+    fn from(e: sqlx::Error) -> Self {
+        GeometryError::Db(e)
+    }
+}
+
+/// Measures the model at `source` and stores the result with the version of the file it comes from.
+/// Check `has_geometry_source` first.
+pub async fn refresh_geometry(
+    pool: &Pool<Sqlite>,
+    asset_id: &str,
+    source: &Path,
+    source_hash: &str,
+    now: &str,
+) -> Result<AssetGeometry, GeometryError> {
+    let source = source.to_path_buf();
+    let geometry = tokio::task::spawn_blocking(move || load_mesh(&source).map(|mesh| analyze(&mesh)))
+        .await
+        .map_err(std::io::Error::other)??;
+
+    upsert_asset_geometry(pool, asset_id, source_hash, &geometry, now).await?;
+    Ok(geometry)
+}
+
+/// Sums are done in f64, a big mesh in f32 loses whole cubic millimetres.
+pub fn analyze(mesh: &Mesh) -> AssetGeometry {
+    // Files repeat vertices (STL per triangle, glTF per normal), weld equal positions first or no
+    // edge would ever be shared.
+    let mut welded: HashMap<[u32; 3], usize> = HashMap::with_capacity(mesh.vertices.len());
+    let remap: Vec<usize> = mesh.vertices.iter()
+        .map(|v| {
+            let next = welded.len();
+            // + 0.0 turns -0.0 into 0.0, same point.
+            *welded.entry(v.map(|c| (c + 0.0).to_bits())).or_insert(next)
+        })
+        .collect();
+
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    let mut surface_area = 0.0;
+    let mut volume = 0.0;
+    let mut edges: HashMap<(usize, usize), u32> = HashMap::with_capacity(mesh.triangles.len() * 3 / 2);
+
+    for triangle in &mesh.triangles {
+        let [a, b, c] = triangle.map(|i| mesh.vertices[i].map(f64::from));
+        for v in [a, b, c] {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(v[axis]);
+                max[axis] = max[axis].max(v[axis]);
+            }
+        }
+
+        let normal = cross(sub(b, a), sub(c, a));
+        surface_area += dot(normal, normal).sqrt() / 2.0;
+        // Signed volume of the tetrahedron to the origin, they add up to the enclosed volume.
+        volume += dot(a, cross(b, c)) / 6.0;
+
+        let [ia, ib, ic] = triangle.map(|i| remap[i]);
+        if ia == ib || ib == ic || ia == ic {
+            continue;
+        }
+        for (from, to) in [(ia, ib), (ib, ic), (ic, ia)] {
+            *edges.entry((from.min(to), from.max(to))).or_default() += 1;
+        }
+    }
+
+    if mesh.triangles.is_empty() {
+        min = [0.0; 3];
+        max = [0.0; 3];
+    }
+
+    AssetGeometry {
+        unit: mesh.unit.clone(),
+        bounding_box: BoundingBox::new(min, max),
+        triangle_count: mesh.triangles.len() as i64,
+        vertex_count: welded.len() as i64,
+        surface_area,
+        volume: volume.abs(),
+        watertight: !edges.is_empty() && edges.values().all(|&count| count == 2),
+    }
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
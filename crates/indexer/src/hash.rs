@@ -0,0 +1,50 @@
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// blake3 hex digest of a file. This is what ends up in `assets.file_hash`, every writer must use it.
+pub async fn hash_file(path: &Path) -> Result<String, std::io::Error> {
+    let path = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update_reader(std::fs::File::open(&path)?)?;
+        Ok(hasher.finalize().to_hex().to_string())
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// Same as [`hash_file`] but gives up with `ErrorKind::Interrupted` once `cancel` is set, so a single
+/// huge file doesn't hold up a cancelled sync.
+pub async fn hash_file_cancellable(path: &Path, cancel: Arc<AtomicBool>) -> Result<String, std::io::Error> {
+    let path = path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path)?;
+        let mut hasher = blake3::Hasher::new();
+        let mut buf = vec![0u8; CHUNK_SIZE];
+
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "hashing cancelled"));
+            }
+
+            match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    hasher.update(&buf[..n]);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(hasher.finalize().to_hex().to_string())
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
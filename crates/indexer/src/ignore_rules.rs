@@ -0,0 +1,131 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Per folder ignore file, same syntax as `.gitignore`. LiMa only reads it.
+pub const IGNORE_FILE_NAME: &str = ".limaignore";
+
+/// Junk that never is an asset. Dotfiles are always skipped so they don't need to be listed.
+pub const DEFAULT_IGNORE_PATTERNS: &[&str] = &[
+    "Thumbs.db",
+    "desktop.ini",
+    "__MACOSX/",
+    "@eaDir/",
+    "#recycle/",
+    "*.blend[0-9]*",
+    "*.tmp",
+    "*.part",
+];
+
+/// The global patterns: the defaults plus the configured ones. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    global: Arc<Gitignore>,
+}
+
+impl IgnoreRules {
+    /// Invalid patterns are logged and skipped, a typo must not stop the server.
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let lines = DEFAULT_IGNORE_PATTERNS.iter().copied().chain(patterns.iter().map(|p| p.as_ref()));
+        Self { global: Arc::new(build("config", lines)) }
+    }
+
+    /// For files that are not in the library yet (uploads), only the global patterns apply.
+    pub fn is_ignored_file_name(&self, name: &str) -> bool {
+        self.global.matched_path_or_any_parents(name, false).is_ignore()
+    }
+
+    /// Reads the `.limaignore` at the root of the library.
+    pub async fn load_library(&self, library_root: &Path) -> LibraryIgnore {
+        LibraryIgnore {
+            global: self.global.clone(),
+            library: Arc::new(load_file(&library_root.join(IGNORE_FILE_NAME)).await),
+        }
+    }
+}
+
+/// Global patterns plus the library `.limaignore`. Paths are relative to the library root.
+#[derive(Debug, Clone)]
+pub struct LibraryIgnore {
+    global: Arc<Gitignore>,
+    library: Arc<Gitignore>,
+}
+
+impl LibraryIgnore {
+    pub fn is_ignored_folder(&self, folder_path: &str) -> bool {
+        decide(&[&self.library, &self.global], Path::new(folder_path), true)
+    }
+
+    /// Adds the `.limaignore` of the project folder.
+    pub async fn load_project(&self, library_root: &Path, folder_path: &str) -> ProjectIgnore {
+        ProjectIgnore {
+            folder_path: folder_path.to_string(),
+            library: self.clone(),
+            project: load_file(&library_root.join(folder_path).join(IGNORE_FILE_NAME)).await,
+        }
+    }
+}
+
+/// Every rule that applies inside one project folder. Paths are relative to the project folder.
+#[derive(Debug)]
+pub struct ProjectIgnore {
+    folder_path: String,
+    library: LibraryIgnore,
+    project: Gitignore,
+}
+
+impl ProjectIgnore {
+    pub fn is_ignored(&self, file_path: &str, is_dir: bool) -> bool {
+        // The closest file wins, like git. A `!pattern` in the project can bring back a file the
+        // global list ignores.
+        match self.project.matched_path_or_any_parents(file_path, is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+
+        let in_library = Path::new(&self.folder_path).join(file_path);
+        decide(&[&self.library.library, &self.library.global], &in_library, is_dir)
+    }
+}
+
+fn decide(layers: &[&Gitignore], path: &Path, is_dir: bool) -> bool {
+    for layer in layers {
+        match layer.matched_path_or_any_parents(path, is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+
+    false
+}
+
+async fn load_file(path: &Path) -> Gitignore {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => build(&path.display().to_string(), content.lines()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Gitignore::empty(),
+        Err(e) => {
+            tracing::warn!("Failed reading {}: {}", path.display(), e);
+            Gitignore::empty()
+        }
+    }
+}
+
+// Patterns are matched against relative paths, "." keeps the matcher from stripping anything.
+fn build<'a>(source: &str, lines: impl Iterator<Item = &'a str>) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(".");
+
+    for line in lines {
+        if let Err(e) = builder.add_line(None, line) {
+            tracing::warn!("Skipping invalid ignore pattern {:?} from {}: {}", line, source, e);
+        }
+    }
+
+    builder.build().unwrap_or_else(|e| {
+        tracing::warn!("Failed building ignore rules from {}: {}", source, e);
+        Gitignore::empty()
+    })
+}
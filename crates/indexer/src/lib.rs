@@ -1,14 +1,13 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
-    }
-}
+pub mod archive;
+pub mod extract;
+pub mod gcode;
+pub mod geometry;
+pub mod hash;
+pub mod ignore_rules;
+pub mod mesh;
+pub mod metadata;
+pub mod render;
+pub mod sync;
+pub mod threemf;
+pub mod thumbnails;
+pub mod watcher;
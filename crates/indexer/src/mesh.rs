@@ -0,0 +1,240 @@
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::threemf::{ThreeMf, ThreeMfError};
+
+/// Mesh formats LiMa can read on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshFormat {
+    Stl,
+    Obj,
+    ThreeMf,
+    /// `.gltf` with its buffers, or `.glb`.
+    Gltf,
+}
+
+impl MeshFormat {
+    pub fn from_path(file_path: &str) -> Option<Self> {
+        let lower = file_path.to_ascii_lowercase();
+        if lower.ends_with(".stl") {
+            Some(MeshFormat::Stl)
+        } else if lower.ends_with(".obj") {
+            Some(MeshFormat::Obj)
+        } else if lower.ends_with(".3mf") {
+            Some(MeshFormat::ThreeMf)
+        } else if lower.ends_with(".gltf") || lower.ends_with(".glb") {
+            Some(MeshFormat::Gltf)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MeshError {
+    Io(std::io::Error),
+    Obj(tobj::LoadError),
+    ThreeMf(ThreeMfError),
+    Gltf(gltf::Error),
+    UnsupportedFormat,
+    Empty,
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::Io(e) => write!(f, "Failed reading mesh: {}", e),
+            MeshError::Obj(e) => write!(f, "Invalid OBJ file: {}", e),
+            MeshError::ThreeMf(e) => write!(f, "{}", e),
+            MeshError::Gltf(e) => write!(f, "Invalid glTF file: {}", e),
+            MeshError::UnsupportedFormat => write!(f, "Unsupported mesh format"),
+            MeshError::Empty => write!(f, "Mesh has no triangles"),
+        }
+    }
+}
+
+impl From<std::io::Error> for MeshError {
+    fn from(e: std::io::Error) -> Self {
+        MeshError::Io(e)
+    }
+}
+
+impl From<ThreeMfError> for MeshError {
+    fn from(e: ThreeMfError) -> Self {
+        MeshError::ThreeMf(e)
+    }
+}
+
+impl From<gltf::Error> for MeshError {
+    fn from(e: gltf::Error) -> Self {
+        MeshError::Gltf(e)
+    }
+}
+
+/// Indexed triangle soup, in millimetres when the file declares its unit and Z up when it declares
+/// its axes. STL and OBJ do neither, their numbers are kept as is, which is millimetres and Z up
+/// for almost every file out there.
+#[derive(Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<[f32; 3]>,
+    pub triangles: Vec<[usize; 3]>,
+    /// Unit declared by the file, e.g. "millimeter" or "meter". None for STL and OBJ.
+    pub unit: Option<String>,
+}
+
+impl Mesh {
+    /// (min, max) corners. None for a mesh without vertices.
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = *self.vertices.first()?;
+        let mut min = first;
+        let mut max = first;
+
+        for v in &self.vertices {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(v[axis]);
+                max[axis] = max[axis].max(v[axis]);
+            }
+        }
+
+        Some((min, max))
+    }
+}
+
+/// Blocking, call it from `spawn_blocking`. Binary and ASCII STL are both detected, 3MF and glTF
+/// scenes are flattened with their transforms applied.
+pub fn load_mesh(path: &Path) -> Result<Mesh, MeshError> {
+    let format = path.to_str().and_then(MeshFormat::from_path).ok_or(MeshError::UnsupportedFormat)?;
+
+    let mesh = match format {
+        MeshFormat::Stl => load_stl(path)?,
+        MeshFormat::Obj => load_obj(path)?,
+        MeshFormat::ThreeMf => ThreeMf::open(path)?.mesh()?,
+        MeshFormat::Gltf => load_gltf(path)?,
+    };
+
+    if mesh.triangles.is_empty() {
+        return Err(MeshError::Empty);
+    }
+
+    Ok(mesh)
+}
+
+fn load_stl(path: &Path) -> Result<Mesh, MeshError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let indexed = stl_io::read_stl(&mut reader)?;
+
+    Ok(Mesh {
+        vertices: indexed.vertices.into_iter().map(|v| v.0).collect(),
+        triangles: indexed.faces.into_iter().map(|f| f.vertices).collect(),
+        unit: None,
+    })
+}
+
+// Kept as authored like STL, printable OBJs are mostly Z up and nothing in the file tells the
+// Y up ones apart. Materials are not needed.
+fn load_obj(path: &Path) -> Result<Mesh, MeshError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        ..Default::default()
+    };
+    let (models, _) = tobj::load_obj_buf(&mut reader, &options, |_| Err(tobj::LoadError::OpenFileFailed))
+        .map_err(MeshError::Obj)?;
+
+    let mut mesh = Mesh::default();
+    for model in models {
+        let offset = mesh.vertices.len();
+        let positions = &model.mesh.positions;
+        mesh.vertices.extend(positions.chunks_exact(3).map(|p| [p[0], p[1], p[2]]));
+
+        let count = positions.len() / 3;
+        for t in model.mesh.indices.chunks_exact(3) {
+            let triangle = [t[0] as usize, t[1] as usize, t[2] as usize];
+            if triangle.iter().all(|&i| i < count) {
+                mesh.triangles.push(triangle.map(|i| i + offset));
+            }
+        }
+    }
+
+    Ok(mesh)
+}
+
+// glTF is Y up and in metres. Every mesh of the default scene is placed with its node transforms,
+// only triangle primitives count. Textures are never decoded.
+fn load_gltf(path: &Path) -> Result<Mesh, MeshError> {
+    let gltf::Gltf { document, blob } = gltf::Gltf::open(path)?;
+    let buffers = gltf::import_buffers(&document, path.parent(), blob)?;
+
+    let mut mesh = Mesh {
+        unit: Some("meter".to_string()),
+        ..Default::default()
+    };
+
+    let Some(scene) = document.default_scene().or_else(|| document.scenes().next()) else {
+        return Ok(mesh);
+    };
+
+    // (node, parent transform), depth first.
+    let mut stack: Vec<(gltf::Node, [[f32; 4]; 4])> = scene.nodes().map(|n| (n, IDENTITY)).collect();
+    while let Some((node, parent)) = stack.pop() {
+        let transform = mul(parent, node.transform().matrix());
+
+        if let Some(node_mesh) = node.mesh() {
+            for primitive in node_mesh.primitives() {
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    continue;
+                }
+
+                let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+                let Some(positions) = reader.read_positions() else {
+                    continue;
+                };
+
+                let offset = mesh.vertices.len();
+                mesh.vertices.extend(positions.map(|p| {
+                    let [x, y, z] = apply(&transform, p);
+                    [x * 1000.0, -z * 1000.0, y * 1000.0]
+                }));
+                let count = mesh.vertices.len() - offset;
+
+                let indices: Vec<usize> = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+                    None => (0..count).collect(),
+                };
+                for t in indices.chunks_exact(3) {
+                    let triangle = [t[0], t[1], t[2]];
+                    if triangle.iter().all(|&i| i < count) {
+                        mesh.triangles.push(triangle.map(|i| i + offset));
+                    }
+                }
+            }
+        }
+
+        stack.extend(node.children().map(|child| (child, transform)));
+    }
+
+    Ok(mesh)
+}
+
+const IDENTITY: [[f32; 4]; 4] = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+// glTF matrices are column major, `m[column][row]`.
+fn mul(a: [[f32; 4]; 4], b: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut out = [[0.0; 4]; 4];
+    for (column, out_column) in out.iter_mut().enumerate() {
+        for (row, value) in out_column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    out
+}
+
+fn apply(m: &[[f32; 4]; 4], [x, y, z]: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * x + m[1][0] * y + m[2][0] * z + m[3][0],
+        m[0][1] * x + m[1][1] * y + m[2][1] * z + m[3][1],
+        m[0][2] * x + m[1][2] * y + m[2][2] * z + m[3][2],
+    ]
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::path::Path;
+
+use lima_db::queries::assets::set_asset_metadata;
+use lima_domain::models::asset_metadata::AssetMetadata;
+use sqlx::{Pool, Sqlite};
+
+use crate::gcode::{GcodeError, read_gcode};
+use crate::threemf::{ThreeMf, ThreeMfError};
+
+#[derive(Debug)]
+pub enum MetadataError {
+    Io(std::io::Error),
+    ThreeMf(ThreeMfError),
+    Gcode(GcodeError),
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for MetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataError::Io(e) => write!(f, "Filesystem error: {}", e),
+            MetadataError::ThreeMf(e) => write!(f, "{}", e),
+            MetadataError::Gcode(e) => write!(f, "{}", e),
+            MetadataError::Db(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for MetadataError {
+    fn from(e: std::io::Error) -> Self {
+        MetadataError::Io(e)
+    }
+}
+
+impl From<ThreeMfError> for MetadataError {
+    fn from(e: ThreeMfError) -> Self {
+        MetadataError::ThreeMf(e)
+    }
+}
+
+impl From<GcodeError> for MetadataError {
+    fn from(e: GcodeError) -> Self {
+        MetadataError::Gcode(e)
+    }
+}
+
+impl From<sqlx::Error> for MetadataError {
+    fn from(e: sqlx::Error) -> Self {
+        MetadataError::Db(e)
+    }
+}
+
+/// Reads the metadata of an asset and records which version of the file it comes from, so the file
+/// is only read again when it changes. Check `has_metadata_source` first.
+pub async fn refresh_metadata(
+    pool: &Pool<Sqlite>,
+    asset_id: &str,
+    source: &Path,
+    source_hash: &str,
+) -> Result<AssetMetadata, MetadataError> {
+    let path = source.to_path_buf();
+    let metadata = tokio::task::spawn_blocking(move || read_metadata(&path))
+        .await
+        .map_err(std::io::Error::other)??;
+
+    // Files without any field still get their hash, so they aren't opened at every sync.
+    let json = if metadata.is_empty() {
+        None
+    } else {
+        Some(serde_json::to_string(&metadata).map_err(std::io::Error::other)?)
+    };
+
+    set_asset_metadata(pool, asset_id, json.as_deref(), source_hash).await?;
+    Ok(metadata)
+}
+
+fn read_metadata(path: &Path) -> Result<AssetMetadata, MetadataError> {
+    let is_3mf = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("3mf"));
+    if is_3mf {
+        Ok(ThreeMf::open(path)?.metadata()?)
+    } else {
+        Ok(read_gcode(path)?.metadata)
+    }
+}
@@ -0,0 +1,170 @@
+use image::{Rgb, RgbImage};
+
+use crate::mesh::Mesh;
+
+const BACKGROUND: [u8; 3] = [240, 241, 243];
+const MODEL_COLOR: [f32; 3] = [0.56, 0.64, 0.74];
+const AMBIENT: f32 = 0.3;
+// Part of the image left around the model on each side.
+const MARGIN: f32 = 0.06;
+
+type Vec3 = [f32; 3];
+
+/// Renders a shaded preview of `mesh` on the CPU, no GPU or display needed. The camera looks from
+/// the front right and a bit above, the model is fitted to the image whatever its size. Blocking.
+pub fn render_preview(mesh: &Mesh, size: u32) -> Option<RgbImage> {
+    let camera = Camera::new();
+
+    // Camera space is (right, up, depth). Orthographic, so fitting is a matter of scale and offset.
+    let projected: Vec<Vec3> = mesh.vertices.iter()
+        .map(|v| [dot(*v, camera.right), dot(*v, camera.up), dot(*v, camera.forward)])
+        .collect();
+
+    let (min, max) = bounds_2d(&projected)?;
+    let extent = (max[0] - min[0]).max(max[1] - min[1]);
+    if !extent.is_finite() || extent <= 0.0 {
+        return None;
+    }
+
+    let scale = size as f32 * (1.0 - 2.0 * MARGIN) / extent;
+    let center = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+    let half = size as f32 / 2.0;
+
+    // Screen y grows downwards.
+    let screen: Vec<Vec3> = projected.iter()
+        .map(|p| [half + (p[0] - center[0]) * scale, half - (p[1] - center[1]) * scale, p[2]])
+        .collect();
+
+    let mut image = RgbImage::from_pixel(size, size, Rgb(BACKGROUND));
+    let mut depth = vec![f32::INFINITY; (size * size) as usize];
+
+    for triangle in &mesh.triangles {
+        let [a, b, c] = triangle.map(|i| mesh.vertices[i]);
+        let Some(normal) = normalize(cross(sub(b, a), sub(c, a))) else {
+            continue;
+        };
+
+        // Winding is unreliable in the wild, light both sides the same.
+        let facing = if dot(normal, camera.forward) > 0.0 { scale_vec(normal, -1.0) } else { normal };
+        let light = AMBIENT + (1.0 - AMBIENT) * dot(facing, camera.light).max(0.0);
+        let color = Rgb(MODEL_COLOR.map(|c| (c * light * 255.0).clamp(0.0, 255.0) as u8));
+
+        fill_triangle(&mut image, &mut depth, triangle.map(|i| screen[i]), color);
+    }
+
+    Some(image)
+}
+
+struct Camera {
+    right: Vec3,
+    up: Vec3,
+    /// From the camera into the scene.
+    forward: Vec3,
+    /// Towards the light.
+    light: Vec3,
+}
+
+impl Camera {
+    fn new() -> Self {
+        let azimuth = (-60f32).to_radians();
+        let elevation = 25f32.to_radians();
+
+        let eye = [
+            elevation.cos() * azimuth.cos(),
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+        ];
+        let forward = scale_vec(eye, -1.0);
+        let right = normalize(cross(forward, [0.0, 0.0, 1.0])).unwrap_or([1.0, 0.0, 0.0]);
+        let up = cross(right, forward);
+
+        // Over the left shoulder of the camera, so the front faces aren't flat.
+        let light = normalize(add(add(eye, scale_vec(up, 0.8)), scale_vec(right, -0.5))).unwrap_or(eye);
+
+        Self { right, up, forward, light }
+    }
+}
+
+fn fill_triangle(image: &mut RgbImage, depth: &mut [f32], [a, b, c]: [Vec3; 3], color: Rgb<u8>) {
+    let area = edge(a, b, c);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let min_x = a[0].min(b[0]).min(c[0]).floor().max(0.0) as u32;
+    let min_y = a[1].min(b[1]).min(c[1]).floor().max(0.0) as u32;
+    let max_x = a[0].max(b[0]).max(c[0]).ceil().min(width - 1.0);
+    let max_y = a[1].max(b[1]).max(c[1]).ceil().min(height - 1.0);
+    if max_x < 0.0 || max_y < 0.0 {
+        return;
+    }
+
+    for y in min_y..=max_y as u32 {
+        for x in min_x..=max_x as u32 {
+            let p = [x as f32 + 0.5, y as f32 + 0.5, 0.0];
+            let (w0, w1, w2) = (edge(b, c, p) / area, edge(c, a, p) / area, edge(a, b, p) / area);
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                continue;
+            }
+
+            let z = w0 * a[2] + w1 * b[2] + w2 * c[2];
+            let index = (y * image.width() + x) as usize;
+            if z < depth[index] {
+                depth[index] = z;
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+fn bounds_2d(points: &[Vec3]) -> Option<([f32; 2], [f32; 2])> {
+    let first = points.first()?;
+    let mut min = [first[0], first[1]];
+    let mut max = min;
+
+    for p in points {
+        min = [min[0].min(p[0]), min[1].min(p[1])];
+        max = [max[0].max(p[0]), max[1].max(p[1])];
+    }
+
+    Some((min, max))
+}
+
+// Twice the signed area of (a, b, p), only x and y are used.
+fn edge(a: Vec3, b: Vec3, p: Vec3) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale_vec(a: Vec3, s: f32) -> Vec3 {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: Vec3) -> Option<Vec3> {
+    let length = dot(a, a).sqrt();
+    if length <= f32::EPSILON || !length.is_finite() {
+        return None;
+    }
+
+    Some(scale_vec(a, 1.0 / length))
+}
@@ -0,0 +1,144 @@
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use lima_db::queries::scan;
+use lima_domain::models::sync::SyncStats;
+use serde_json::json;
+
+use super::{RunContext, SyncError, ensure_library};
+
+pub const DEFAULT_BUNDLE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Uploads that were never imported (or whose cleanup failed after the import) pile up in the bundles
+// folder. A bundle is as old as the newest thing in it, so one that is still being filled is kept.
+pub(super) async fn collect_bundles(
+    ctx: &RunContext<'_>,
+    bundles_root: &Path,
+    ttl: Duration,
+    stats: &mut SyncStats,
+) -> Result<(), SyncError> {
+    let mut entries = match tokio::fs::read_dir(bundles_root).await {
+        Ok(entries) => entries,
+        // Nothing was ever uploaded.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let now = SystemTime::now();
+
+    while let Some(entry) = entries.next_entry().await? {
+        ctx.check_cancelled()?;
+
+        if !entry.file_type().await?.is_dir() {
+            continue;
+        }
+
+        let bundle_dir = entry.path();
+        let bundle_id = entry.file_name().to_string_lossy().into_owned();
+        let last_modified = bundle_last_modified(&bundle_dir).await?;
+        let age = now.duration_since(last_modified).unwrap_or_default();
+
+        if age < ttl {
+            continue;
+        }
+
+        if let Err(e) = tokio::fs::remove_dir_all(&bundle_dir).await {
+            tracing::warn!("Failed removing expired bundle {}: {}", bundle_id, e);
+            ctx.event("warn", "bundle_gc_failed", json!({ "bundle_id": bundle_id, "error": e.to_string() })).await;
+            continue;
+        }
+
+        stats.bundles_removed += 1;
+        ctx.event("info", "bundle_removed", json!({ "bundle_id": bundle_id, "age_secs": age.as_secs() })).await;
+        tracing::info!("Removed expired bundle {} ({}s old)", bundle_id, age.as_secs());
+    }
+
+    Ok(())
+}
+
+/// When something in the bundle was last written, what the TTL counts from.
+pub async fn bundle_last_modified(dir: &Path) -> Result<SystemTime, std::io::Error> {
+    let mut newest = tokio::fs::metadata(dir).await?.modified()?;
+    let mut entries = tokio::fs::read_dir(dir).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        if let Ok(modified) = entry.metadata().await.and_then(|m| m.modified()) {
+            newest = newest.max(modified);
+        }
+    }
+
+    Ok(newest)
+}
+
+// Catches files that changed behind our back (bit rot, a tool rewriting files and restoring the mtime...).
+// Only reports: the next sync decides what the index should look like.
+pub(super) async fn verify_hashes(
+    ctx: &RunContext<'_>,
+    library_root: &Path,
+    stats: &mut SyncStats,
+) -> Result<(), SyncError> {
+    // Otherwise an unmounted share reports every file as missing.
+    ensure_library(library_root).await?;
+
+    let assets = scan::list_hashed_assets(ctx.pool).await?;
+
+    for asset in &assets {
+        ctx.check_cancelled()?;
+
+        let path = library_root.join(&asset.folder_path).join(&asset.file_path);
+
+        let hash = match ctx.hash(&path).await {
+            Ok(hash) => hash,
+            Err(_) if ctx.is_cancelled() => return Err(SyncError::Cancelled),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                stats.assets_missing += 1;
+                ctx.event("warn", "asset_missing", json!({
+                    "asset_id": asset.id,
+                    "project_id": asset.project_id,
+                    "folder_path": asset.folder_path,
+                    "file_path": asset.file_path,
+                })).await;
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("Failed hashing {}/{}: {}", asset.folder_path, asset.file_path, e);
+                stats.hash_failed += 1;
+                ctx.event("warn", "hash_failed", json!({
+                    "folder_path": asset.folder_path,
+                    "file_path": asset.file_path,
+                    "error": e.to_string(),
+                })).await;
+                continue;
+            }
+        };
+
+        stats.assets_verified += 1;
+
+        if hash == asset.file_hash {
+            continue;
+        }
+
+        // A file edited since the last sync also ends up here, flag it so it can be told apart from corruption.
+        let metadata_changed = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => {
+                metadata.len() as i64 != asset.size_bytes
+                    || metadata.modified().map(super::walk::format_time).ok().as_deref() != Some(asset.mtime.as_str())
+            }
+            Err(_) => true,
+        };
+
+        stats.hash_mismatch += 1;
+        ctx.event("warn", "hash_mismatch", json!({
+            "asset_id": asset.id,
+            "project_id": asset.project_id,
+            "folder_path": asset.folder_path,
+            "file_path": asset.file_path,
+            "expected": asset.file_hash,
+            "actual": hash,
+            "metadata_changed": metadata_changed,
+        })).await;
+        tracing::warn!("Hash mismatch for {}/{}", asset.folder_path, asset.file_path);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,621 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+use lima_db::queries::scan::{self, IndexedAssetRow, ScannedAsset};
+use lima_db::queries::sync_runs::{self, SyncRunRow};
+use lima_domain::models::asset::{AssetKind, guess_mime};
+use lima_domain::models::asset_metadata::has_metadata_source;
+use lima_domain::models::geometry::has_geometry_source;
+use lima_domain::models::sync::{SyncMode, SyncOptions, SyncStats};
+use serde_json::json;
+use sqlx::{Pool, Sqlite};
+use tokio::sync::watch;
+
+use crate::hash::hash_file_cancellable;
+use crate::geometry::refresh_geometry;
+use crate::ignore_rules::IgnoreRules;
+use crate::metadata::refresh_metadata;
+use crate::thumbnails::{ThumbnailError, ThumbnailStore, has_thumbnail_source, refresh_thumbnails};
+
+mod maintenance;
+mod moves;
+mod walk;
+mod worker;
+
+pub use maintenance::{DEFAULT_BUNDLE_TTL, bundle_last_modified};
+pub use worker::{CancelOutcome, SyncQueue};
+
+/// What the worker works on.
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    pub library_root: PathBuf,
+    /// Upload bundles, cleaned up by `bundle_gc` runs.
+    pub bundles_root: PathBuf,
+    pub ignore: IgnoreRules,
+    pub thumbnails: ThumbnailStore,
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+    LibraryNotFound(PathBuf),
+    Io(std::io::Error),
+    Db(sqlx::Error),
+    Cancelled,
+}
+
+impl fmt::Display for SyncError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyncError::LibraryNotFound(path) => write!(f, "Library root not found: {}", path.display()),
+            SyncError::Io(e) => write!(f, "Filesystem error: {}", e),
+            SyncError::Db(e) => write!(f, "Database error: {}", e),
+            SyncError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl From<std::io::Error> for SyncError {
+    fn from(e: std::io::Error) -> Self {
+        SyncError::Io(e)
+    }
+}
+
+impl From<sqlx::Error> for SyncError {
+    fn from(e: sqlx::Error) -> Self {
+        SyncError::Db(e)
+    }
+}
+
+/// Executes an already claimed run and finishes the `sync_runs` row.
+///
+/// `full` rehashes every file. `incremental` trusts size + mtime and only rehashes files that changed
+/// (or were never hashed). Both modes add new files and remove rows for missing files, also when the
+/// whole project folder is gone. The project itself stays until it is deleted. `bundle_gc` and
+/// `verify` are maintenance jobs that go through the same queue so they never compete with a sync for
+/// the disk.
+///
+/// After a project is written its images, models and G-code get thumbnails when they have none for
+/// the current version of the file, which also backfills libraries indexed before thumbnails existed.
+/// A project without main image then gets its first image, or a model or G-code preview when it has
+/// no image. 3MF and G-code files also get their embedded metadata read, and models their geometry
+/// measured (bounding box, volume...), the same way.
+///
+/// Setting `cancel` stops the run at the next file, whatever was committed until then stays.
+/// With `dry_run` in the options nothing is written to `projects`/`assets`, the events describe what
+/// would have been done instead.
+pub(crate) async fn execute_run(
+    pool: &Pool<Sqlite>,
+    config: &SyncConfig,
+    events: &watch::Sender<i64>,
+    cancel: Arc<AtomicBool>,
+    run: &SyncRunRow,
+) -> Result<SyncStats, SyncError> {
+    let mode = SyncMode::parse(&run.mode).unwrap_or(SyncMode::Incremental);
+    let options: SyncOptions = serde_json::from_str(&run.options_json).unwrap_or_default();
+    let ctx = RunContext { pool, events, cancel, dry_run: options.dry_run, ignore: &config.ignore, thumbnails: &config.thumbnails, run_id: &run.id };
+    ctx.event("info", "run_started", json!({ "mode": mode.as_str(), "reason": run.reason, "options": options })).await;
+
+    let mut stats = SyncStats::default();
+    let result = match mode {
+        SyncMode::Full | SyncMode::Incremental => {
+            scan_library(&ctx, &config.library_root, mode, &options, &mut stats).await
+        }
+        SyncMode::BundleGc => {
+            let ttl = options.bundle_ttl_secs.map(Duration::from_secs).unwrap_or(DEFAULT_BUNDLE_TTL);
+            maintenance::collect_bundles(&ctx, &config.bundles_root, ttl, &mut stats).await
+        }
+        SyncMode::Verify => maintenance::verify_hashes(&ctx, &config.library_root, &mut stats).await,
+    };
+
+    // Partial stats are stored on failure and cancel too, whatever was committed is still valid.
+    let stats_json = serde_json::to_string(&stats).unwrap_or_else(|_| "{}".to_string());
+    let (status, error) = match &result {
+        Ok(_) => ("complete", String::new()),
+        Err(SyncError::Cancelled) => ("cancelled", String::new()),
+        Err(e) => ("failed", e.to_string()),
+    };
+
+    match &result {
+        Ok(_) => ctx.event("info", "run_complete", json!(stats)).await,
+        Err(SyncError::Cancelled) => ctx.event("info", "run_cancelled", json!(stats)).await,
+        Err(e) => ctx.event("error", "run_failed", json!({ "error": e.to_string() })).await,
+    }
+
+    sync_runs::finish_sync_run(pool, &run.id, status, &stats_json, &error, &now()).await?;
+
+    match result {
+        Ok(_) => {
+            tracing::info!("Sync run {} ({}) complete: {:?}", run.id, mode.as_str(), stats);
+            Ok(stats)
+        }
+        Err(SyncError::Cancelled) => {
+            tracing::info!("Sync run {} ({}) cancelled: {:?}", run.id, mode.as_str(), stats);
+            Err(SyncError::Cancelled)
+        }
+        Err(e) => {
+            tracing::error!("Sync run {} ({}) failed: {}", run.id, mode.as_str(), e);
+            Err(e)
+        }
+    }
+}
+
+struct RunContext<'a> {
+    pool: &'a Pool<Sqlite>,
+    events: &'a watch::Sender<i64>,
+    cancel: Arc<AtomicBool>,
+    dry_run: bool,
+    ignore: &'a IgnoreRules,
+    thumbnails: &'a ThumbnailStore,
+    run_id: &'a str,
+}
+
+impl RunContext<'_> {
+    // Checked between files. Long single files are covered by hash_file_cancellable.
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn check_cancelled(&self) -> Result<(), SyncError> {
+        if self.is_cancelled() {
+            return Err(SyncError::Cancelled);
+        }
+
+        Ok(())
+    }
+
+    async fn hash(&self, path: &Path) -> Result<String, std::io::Error> {
+        hash_file_cancellable(path, self.cancel.clone()).await
+    }
+
+    // Events are best effort, a failure writing the log must not fail the run itself.
+    async fn event(&self, level: &str, kind: &str, data: serde_json::Value) {
+        match sync_runs::insert_sync_event(self.pool, self.run_id, level, kind, &data.to_string(), &now()).await {
+            Ok(id) => {
+                self.events.send_replace(id);
+            }
+            Err(e) => tracing::warn!("Failed writing {} event for sync run {}: {}", kind, self.run_id, e),
+        }
+    }
+}
+
+async fn scan_library(
+    ctx: &RunContext<'_>,
+    library_root: &Path,
+    mode: SyncMode,
+    options: &SyncOptions,
+    stats: &mut SyncStats,
+) -> Result<(), SyncError> {
+    ensure_library(library_root).await?;
+
+    let pool = ctx.pool;
+    let indexed: HashMap<String, String> = scan::list_indexed_projects(pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.folder_path, row.id))
+        .collect();
+
+    let library = walk::Library::open(library_root, ctx.ignore).await;
+    let on_disk: BTreeSet<String> = walk::list_project_folders(&library).await?.into_iter().collect();
+
+    // Targeted runs (e.g. from the watcher) only look at the given folders, the rest of the library is left alone.
+    let scope: BTreeSet<String> = match &options.folders {
+        Some(folders) => folders.iter().cloned().collect(),
+        None => on_disk.iter().chain(indexed.keys()).cloned().collect(),
+    };
+
+    // A renamed folder shows up as one folder gone and a new one. Keep the project (and its tags,
+    // description...) by moving it instead of deleting it and creating a new one.
+    let appeared: Vec<&String> = scope.iter().filter(|f| on_disk.contains(*f) && !indexed.contains_key(*f)).collect();
+    let disappeared: Vec<(&String, &String)> = scope.iter()
+        .filter(|f| !on_disk.contains(*f))
+        .filter_map(|f| indexed.get(f).map(|id| (f, id)))
+        .collect();
+
+    let mut moves = if appeared.is_empty() || disappeared.is_empty() {
+        HashMap::new()
+    } else {
+        moves::detect_moves(ctx, &library, &appeared, &disappeared).await?
+    };
+    let moved_away: BTreeSet<String> = moves.values().map(|m| m.from.clone()).collect();
+
+    for folder_path in &scope {
+        ctx.check_cancelled()?;
+
+        if let Some(moved) = moves.remove(folder_path) {
+            if !ctx.dry_run {
+                scan::move_project(pool, &moved.project_id, &moved.from, folder_path, &now()).await?;
+            }
+            stats.projects_moved += 1;
+            ctx.event("info", "project_moved", json!({
+                "project_id": moved.project_id,
+                "from": moved.from,
+                "to": folder_path,
+                "similarity": moved.similarity,
+                "dry_run": ctx.dry_run,
+            })).await;
+            tracing::info!("Project {} moved from {} to {}", moved.project_id, moved.from, folder_path);
+
+            scan_project(ctx, &library, folder_path, Some(&moved.project_id), mode, Some(&moved.hashes), stats).await?;
+            continue;
+        }
+
+        if on_disk.contains(folder_path) {
+            scan_project(ctx, &library, folder_path, indexed.get(folder_path), mode, None, stats).await?;
+            continue;
+        }
+
+        let Some(project_id) = indexed.get(folder_path) else {
+            continue;
+        };
+
+        if moved_away.contains(folder_path) {
+            continue;
+        }
+
+        // A folder gone for a while was already emptied by an earlier run.
+        let assets = scan::list_indexed_assets(pool, project_id).await?;
+        if assets.is_empty() {
+            continue;
+        }
+
+        if !ctx.dry_run {
+            scan::delete_missing_project_assets(pool, project_id, &now()).await?;
+            for asset in &assets {
+                ctx.thumbnails.remove(&asset.id).await;
+            }
+        }
+        stats.projects_missing += 1;
+        stats.assets_removed += assets.len() as u64;
+        ctx.event("info", "project_missing", json!({
+            "project_id": project_id,
+            "folder_path": folder_path,
+            "assets_removed": assets.len(),
+            "dry_run": ctx.dry_run,
+        })).await;
+        tracing::info!("Project folder {} is gone, removed the assets of project {}", folder_path, project_id);
+    }
+
+    Ok(())
+}
+
+// An unmounted share must not look like an empty library, that would drop every project.
+async fn ensure_library(library_root: &Path) -> Result<(), SyncError> {
+    if !tokio::fs::metadata(library_root).await.map(|m| m.is_dir()).unwrap_or(false) {
+        return Err(SyncError::LibraryNotFound(library_root.to_path_buf()));
+    }
+
+    Ok(())
+}
+
+enum AssetChange<'a> {
+    Add(ScannedAsset<'a>),
+    Update(String, ScannedAsset<'a>),
+    Remove(IndexedAssetRow),
+}
+
+// Hashing happens before the transaction is opened so we don't hold the write lock while reading big files.
+// One transaction per project so a failure halfway through a big library keeps what was already indexed.
+async fn scan_project(
+    ctx: &RunContext<'_>,
+    library: &walk::Library<'_>,
+    folder_path: &str,
+    project_id: Option<&String>,
+    mode: SyncMode,
+    hashed: Option<&HashMap<String, String>>,
+    stats: &mut SyncStats,
+) -> Result<(), SyncError> {
+    let pool = ctx.pool;
+    let project_dir = library.project_dir(folder_path);
+    let files = walk::list_project_files(library, folder_path).await?;
+
+    let mut known: HashMap<String, IndexedAssetRow> = match project_id {
+        Some(id) => scan::list_indexed_assets(pool, id)
+            .await?
+            .into_iter()
+            .map(|row| (row.file_path.clone(), row))
+            .collect(),
+        None => HashMap::new(),
+    };
+
+    let mimes: Vec<String> = files.iter().map(|file| guess_mime(&file.file_path)).collect();
+    let mut hashes: Vec<Option<String>> = vec![None; files.len()];
+    // (index in files, existing asset id) for every file that needs writing.
+    let mut pending: Vec<(usize, Option<String>)> = Vec::new();
+
+    for (i, file) in files.iter().enumerate() {
+        let kind = AssetKind::from_path(&file.file_path).as_str();
+        let existing = known.remove(&file.file_path);
+
+        let same_file = existing.as_ref().is_some_and(|row| {
+            row.size_bytes == file.size_bytes && row.mtime == file.mtime
+        });
+        let same_metadata = same_file && existing.as_ref().is_some_and(|row| {
+            row.kind == kind && row.mime == mimes[i]
+        });
+
+        if mode == SyncMode::Incremental && same_file && let Some(hash) = existing.as_ref().and_then(|row| row.file_hash.clone()) {
+            if same_metadata {
+                stats.assets_unchanged += 1;
+            } else {
+                // Only the classification changed (new kinds, new extensions), the file isn't read again.
+                hashes[i] = Some(hash);
+                pending.push((i, existing.map(|row| row.id)));
+            }
+            continue;
+        }
+
+        ctx.check_cancelled()?;
+
+        let result = match hashed.and_then(|hashed| hashed.get(&file.file_path)) {
+            Some(hash) => Ok(hash.clone()),
+            None => ctx.hash(&project_dir.join(&file.file_path)).await,
+        };
+
+        hashes[i] = match result {
+            Ok(hash) => Some(hash),
+            // Nothing of this project was written yet, so it is left as it was.
+            Err(_) if ctx.is_cancelled() => return Err(SyncError::Cancelled),
+            Err(e) => {
+                tracing::warn!("Failed hashing {}/{}: {}", folder_path, file.file_path, e);
+                stats.hash_failed += 1;
+                ctx.event("warn", "hash_failed", json!({
+                    "folder_path": folder_path,
+                    "file_path": file.file_path,
+                    "error": e.to_string(),
+                })).await;
+
+                // A read error doesn't change the file. Its hash stays, with what depends on it
+                // (ETag, thumbnails, geometry...), and the next sync tries again.
+                if same_file {
+                    if !same_metadata {
+                        hashes[i] = existing.as_ref().and_then(|row| row.file_hash.clone());
+                        pending.push((i, existing.map(|row| row.id)));
+                    }
+                    continue;
+                }
+                None
+            }
+        };
+
+        if same_metadata && existing.as_ref().and_then(|row| row.file_hash.as_ref()) == hashes[i].as_ref() {
+            stats.assets_unchanged += 1;
+            continue;
+        }
+
+        pending.push((i, existing.map(|row| row.id)));
+    }
+
+    let mut changes: Vec<AssetChange> = pending.into_iter().map(|(i, existing_id)| {
+        let asset = ScannedAsset {
+            file_path: &files[i].file_path,
+            kind: AssetKind::from_path(&files[i].file_path).as_str(),
+            size_bytes: files[i].size_bytes,
+            mtime: &files[i].mtime,
+            mime: &mimes[i],
+            file_hash: hashes[i].as_deref(),
+        };
+
+        match existing_id {
+            Some(id) => AssetChange::Update(id, asset),
+            None => AssetChange::Add(asset),
+        }
+    }).collect();
+
+    changes.extend(known.into_values().map(AssetChange::Remove));
+
+    if ctx.dry_run {
+        report_planned_changes(ctx, folder_path, project_id, &changes, stats).await;
+        return Ok(());
+    }
+
+    let (mut added, mut updated, mut removed) = (0u64, 0u64, 0u64);
+    let now = now();
+    let mut tx = pool.begin().await?;
+    let (project_id, created) = scan::upsert_project_for_folder(&mut tx, folder_path, &now).await?;
+
+    for change in &changes {
+        match change {
+            AssetChange::Add(asset) => {
+                scan::insert_scanned_asset(&mut tx, &project_id, asset, &now).await?;
+                added += 1;
+            }
+            AssetChange::Update(asset_id, asset) => {
+                scan::update_scanned_asset(&mut tx, asset_id, asset, &now).await?;
+                updated += 1;
+            }
+            AssetChange::Remove(row) => {
+                scan::delete_missing_asset(&mut tx, &project_id, &row.id, &now).await?;
+                removed += 1;
+            }
+        }
+    }
+
+    scan::mark_project_scanned(&mut tx, &project_id, &now).await?;
+    tx.commit().await?;
+
+    for change in &changes {
+        if let AssetChange::Remove(row) = change {
+            ctx.thumbnails.remove(&row.id).await;
+        }
+    }
+
+    stats.projects_scanned += 1;
+    stats.assets_added += added;
+    stats.assets_updated += updated;
+    stats.assets_removed += removed;
+    if created {
+        stats.projects_added += 1;
+    }
+
+    if created || !changes.is_empty() {
+        ctx.event("info", "project_upsert", json!({
+            "project_id": project_id,
+            "folder_path": folder_path,
+            "created": created,
+            "assets_added": added,
+            "assets_updated": updated,
+            "assets_removed": removed,
+            "dry_run": false,
+        })).await;
+    }
+
+    tracing::debug!("Scanned project {} ({} files, {} changes)", folder_path, files.len(), changes.len());
+
+    refresh_project_metadata(ctx, &project_dir, folder_path, &project_id, stats).await?;
+    refresh_project_geometry(ctx, &project_dir, folder_path, &project_id, stats).await?;
+    refresh_project_thumbnails(ctx, &project_dir, folder_path, &project_id, stats).await?;
+
+    if lima_db::queries::assets::fill_missing_main_image(pool, &project_id, &now).await? {
+        ctx.event("info", "main_image_set", json!({ "project_id": project_id, "folder_path": folder_path })).await;
+    }
+
+    Ok(())
+}
+
+// A file that can't be decoded is reported and tried again on the next run, it doesn't fail the sync.
+async fn refresh_project_thumbnails(
+    ctx: &RunContext<'_>,
+    project_dir: &Path,
+    folder_path: &str,
+    project_id: &str,
+    stats: &mut SyncStats,
+) -> Result<(), SyncError> {
+    let stale = scan::list_stale_thumbnails(ctx.pool, project_id).await?;
+    for asset in stale.iter().filter(|a| has_thumbnail_source(&a.kind, &a.file_path)) {
+        ctx.check_cancelled()?;
+
+        let source = project_dir.join(&asset.file_path);
+        match refresh_thumbnails(ctx.pool, ctx.thumbnails, &asset.id, &asset.kind, &source, &asset.file_hash).await {
+            Ok(()) => stats.thumbnails_generated += 1,
+            // Tried again next run, the file may get one when it is saved again.
+            Err(ThumbnailError::NoPreview) => {
+                tracing::debug!("No embedded preview in {}/{}", folder_path, asset.file_path);
+            }
+            Err(e) => {
+                tracing::warn!("Failed generating thumbnails for {}/{}: {}", folder_path, asset.file_path, e);
+                stats.thumbnails_failed += 1;
+                ctx.event("warn", "thumbnail_failed", json!({
+                    "folder_path": folder_path,
+                    "file_path": asset.file_path,
+                    "asset_id": asset.id,
+                    "error": e.to_string(),
+                })).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Same as thumbnails, an unreadable file is reported and doesn't fail the sync.
+async fn refresh_project_metadata(
+    ctx: &RunContext<'_>,
+    project_dir: &Path,
+    folder_path: &str,
+    project_id: &str,
+    stats: &mut SyncStats,
+) -> Result<(), SyncError> {
+    let stale = scan::list_stale_metadata(ctx.pool, project_id).await?;
+    for asset in stale.iter().filter(|a| has_metadata_source(&a.file_path)) {
+        ctx.check_cancelled()?;
+
+        let source = project_dir.join(&asset.file_path);
+        match refresh_metadata(ctx.pool, &asset.id, &source, &asset.file_hash).await {
+            Ok(_) => stats.metadata_extracted += 1,
+            Err(e) => {
+                tracing::warn!("Failed reading metadata of {}/{}: {}", folder_path, asset.file_path, e);
+                stats.metadata_failed += 1;
+                ctx.event("warn", "metadata_failed", json!({
+                    "folder_path": folder_path,
+                    "file_path": asset.file_path,
+                    "asset_id": asset.id,
+                    "error": e.to_string(),
+                })).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Same as thumbnails, a model that can't be loaded is reported and doesn't fail the sync.
+async fn refresh_project_geometry(
+    ctx: &RunContext<'_>,
+    project_dir: &Path,
+    folder_path: &str,
+    project_id: &str,
+    stats: &mut SyncStats,
+) -> Result<(), SyncError> {
+    let stale = scan::list_stale_geometry(ctx.pool, project_id).await?;
+    for asset in stale.iter().filter(|a| has_geometry_source(&a.file_path)) {
+        ctx.check_cancelled()?;
+
+        let source = project_dir.join(&asset.file_path);
+        match refresh_geometry(ctx.pool, &asset.id, &source, &asset.file_hash, &now()).await {
+            Ok(_) => stats.geometry_analyzed += 1,
+            Err(e) => {
+                tracing::warn!("Failed analyzing geometry of {}/{}: {}", folder_path, asset.file_path, e);
+                stats.geometry_failed += 1;
+                ctx.event("warn", "geometry_failed", json!({
+                    "folder_path": folder_path,
+                    "file_path": asset.file_path,
+                    "asset_id": asset.id,
+                    "error": e.to_string(),
+                })).await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Dry runs stop before the transaction. Same event and stats as a real run plus the files involved,
+// a new project has no id yet.
+async fn report_planned_changes(
+    ctx: &RunContext<'_>,
+    folder_path: &str,
+    project_id: Option<&String>,
+    changes: &[AssetChange<'_>],
+    stats: &mut SyncStats,
+) {
+    let (mut added, mut updated, mut removed) = (Vec::new(), Vec::new(), Vec::new());
+    for change in changes {
+        match change {
+            AssetChange::Add(asset) => added.push(asset.file_path),
+            AssetChange::Update(_, asset) => updated.push(asset.file_path),
+            AssetChange::Remove(row) => removed.push(row.file_path.as_str()),
+        }
+    }
+
+    let created = project_id.is_none();
+    stats.projects_scanned += 1;
+    stats.assets_added += added.len() as u64;
+    stats.assets_updated += updated.len() as u64;
+    stats.assets_removed += removed.len() as u64;
+    if created {
+        stats.projects_added += 1;
+    }
+
+    if created || !changes.is_empty() {
+        ctx.event("info", "project_upsert", json!({
+            "project_id": project_id,
+            "folder_path": folder_path,
+            "created": created,
+            "assets_added": added.len(),
+            "assets_updated": updated.len(),
+            "assets_removed": removed.len(),
+            "dry_run": true,
+            "added": added,
+            "updated": updated,
+            "removed": removed,
+        })).await;
+    }
+}
+
+fn now() -> String {
+    walk::format_time(SystemTime::now())
+}
@@ -0,0 +1,109 @@
+use std::collections::{HashMap, HashSet};
+
+use lima_db::queries::scan;
+
+use super::walk::{self, Library};
+use super::{RunContext, SyncError};
+
+// Share of distinct file hashes two folders must have in common to be the same project. Most of the
+// files, so files added or edited together with the rename don't break the match.
+const MOVE_SIMILARITY: f64 = 0.5;
+
+pub(super) struct ProjectMove {
+    pub project_id: String,
+    pub from: String,
+    pub similarity: f64,
+    /// file_path -> hash of the new folder, so the scan that follows doesn't read everything again.
+    pub hashes: HashMap<String, String>,
+}
+
+/// Pairs folders that disappeared with folders that appeared by comparing the hashes of their files.
+/// Returns the moves keyed by the new folder path.
+///
+/// `disappeared` is (folder_path, project_id). Only the new folders get hashed, the old ones are
+/// gone so their hashes come from the index.
+pub(super) async fn detect_moves(
+    ctx: &RunContext<'_>,
+    library: &Library<'_>,
+    appeared: &[&String],
+    disappeared: &[(&String, &String)],
+) -> Result<HashMap<String, ProjectMove>, SyncError> {
+    let mut old_sets: Vec<HashSet<String>> = Vec::with_capacity(disappeared.len());
+    for (_, project_id) in disappeared {
+        let hashes = scan::list_indexed_assets(ctx.pool, project_id)
+            .await?
+            .into_iter()
+            .filter_map(|row| row.file_hash)
+            .collect();
+        old_sets.push(hashes);
+    }
+
+    let mut new_hashes: Vec<HashMap<String, String>> = Vec::with_capacity(appeared.len());
+    for folder_path in appeared {
+        let project_dir = library.project_dir(folder_path);
+        let mut hashes = HashMap::new();
+
+        for file in walk::list_project_files(library, folder_path).await? {
+            ctx.check_cancelled()?;
+
+            // Failures are reported by the scan that follows, which tries again.
+            match ctx.hash(&project_dir.join(&file.file_path)).await {
+                Ok(hash) => {
+                    hashes.insert(file.file_path, hash);
+                }
+                Err(_) if ctx.is_cancelled() => return Err(SyncError::Cancelled),
+                Err(_) => {}
+            }
+        }
+
+        new_hashes.push(hashes);
+    }
+
+    let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+    for (n, hashes) in new_hashes.iter().enumerate() {
+        let new_set: HashSet<&String> = hashes.values().collect();
+
+        for (o, old_set) in old_sets.iter().enumerate() {
+            let similarity = similarity(&new_set, old_set);
+            if similarity >= MOVE_SIMILARITY {
+                candidates.push((similarity, n, o));
+            }
+        }
+    }
+
+    // Best matches first, every folder takes part in one move at most.
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut moves = HashMap::new();
+    let mut taken_old: HashSet<usize> = HashSet::new();
+
+    for (similarity, n, o) in candidates {
+        let to = appeared[n];
+        if moves.contains_key(to) || taken_old.contains(&o) {
+            continue;
+        }
+
+        taken_old.insert(o);
+        let (from, project_id) = disappeared[o];
+        moves.insert(to.clone(), ProjectMove {
+            project_id: project_id.clone(),
+            from: from.clone(),
+            similarity,
+            hashes: std::mem::take(&mut new_hashes[n]),
+        });
+    }
+
+    Ok(moves)
+}
+
+// Jaccard index of the distinct hashes. Empty folders never match anything.
+fn similarity(new_set: &HashSet<&String>, old_set: &HashSet<String>) -> f64 {
+    if new_set.is_empty() || old_set.is_empty() {
+        return 0.0;
+    }
+
+    let common = new_set.iter().filter(|hash| old_set.contains(hash.as_str())).count();
+    let union = new_set.len() + old_set.len() - common;
+
+    common as f64 / union as f64
+}
@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use tokio::fs;
+
+use crate::ignore_rules::{IgnoreRules, LibraryIgnore};
+
+#[derive(Debug)]
+pub struct DiskFile {
+    pub file_path: String,
+    pub size_bytes: i64,
+    pub mtime: String,
+}
+
+/// The library root with its ignore rules, loaded once per run so every folder sees the same rules.
+pub struct Library<'a> {
+    pub root: &'a Path,
+    pub ignore: LibraryIgnore,
+}
+
+impl<'a> Library<'a> {
+    pub async fn open(root: &'a Path, rules: &IgnoreRules) -> Self {
+        Self { root, ignore: rules.load_library(root).await }
+    }
+
+    pub fn project_dir(&self, folder_path: &str) -> PathBuf {
+        self.root.join(folder_path)
+    }
+}
+
+// Every top level folder in the library is a project.
+pub async fn list_project_folders(library: &Library<'_>) -> Result<Vec<String>, std::io::Error> {
+    let mut entries = fs::read_dir(library.root).await?;
+
+    let mut folders = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if !entry.file_type().await?.is_dir() {
+            continue;
+        }
+
+        match visible_name(&entry) {
+            Some(name) if !library.ignore.is_ignored_folder(&name) => folders.push(name),
+            _ => continue,
+        }
+    }
+
+    folders.sort();
+    Ok(folders)
+}
+
+/// Every file of the project, subfolders included. `file_path` is relative to the project folder
+/// and always uses '/'.
+pub async fn list_project_files(library: &Library<'_>, folder_path: &str) -> Result<Vec<DiskFile>, std::io::Error> {
+    let ignore = library.ignore.load_project(library.root, folder_path).await;
+    let project_dir = library.project_dir(folder_path);
+    let mut pending: Vec<String> = vec![String::new()];
+    let mut files = Vec::new();
+
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(project_dir.join(&dir)).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(name) = visible_name(&entry) else {
+                continue;
+            };
+
+            let file_path = if dir.is_empty() { name } else { format!("{}/{}", dir, name) };
+
+            // symlinks are skipped on purpose, we only index what lives in the library.
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                // An ignored folder is never walked, whatever is inside.
+                if !ignore.is_ignored(&file_path, true) {
+                    pending.push(file_path);
+                }
+                continue;
+            }
+
+            if !file_type.is_file() || ignore.is_ignored(&file_path, false) {
+                continue;
+            }
+
+            let metadata = entry.metadata().await?;
+            files.push(DiskFile {
+                file_path,
+                size_bytes: metadata.len() as i64,
+                mtime: format_time(metadata.modified()?),
+            });
+        }
+    }
+
+    files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+    Ok(files)
+}
+
+fn visible_name(entry: &fs::DirEntry) -> Option<String> {
+    let name = match entry.file_name().into_string() {
+        Ok(name) => name,
+        Err(raw) => {
+            tracing::warn!("Skipping non UTF-8 path: {:?}", raw);
+            return None;
+        }
+    };
+
+    if name.starts_with('.') {
+        return None;
+    }
+
+    Some(name)
+}
+
+pub fn format_time(time: SystemTime) -> String {
+    OffsetDateTime::from(time)
+        .format(&Rfc3339)
+        .unwrap_or_default()
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use lima_db::queries::sync_runs::{self, QueuedRun};
+use lima_domain::models::sync::{SyncMode, SyncOptions, SyncReason};
+use sqlx::{Pool, Sqlite};
+use tokio::sync::{Mutex, Notify, watch};
+
+use super::{SyncConfig, execute_run, now};
+
+/// Handle to the background sync worker.
+///
+/// The queue lives in `sync_runs` (status `queued`), the worker only gets woken up when something is added,
+/// so queued runs survive a restart. Runs are executed one at a time, oldest first.
+#[derive(Clone)]
+pub struct SyncQueue {
+    pool: Pool<Sqlite>,
+    wake: Arc<Notify>,
+    events: watch::Sender<i64>,
+    running: Arc<Mutex<Option<RunningRun>>>,
+}
+
+struct RunningRun {
+    id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+pub enum CancelOutcome {
+    /// The run was still queued and is now cancelled.
+    Cancelled,
+    /// The run is executing, it stops at the next file and ends up as cancelled.
+    Requested,
+    /// The run is not queued nor running.
+    NotActive,
+}
+
+impl SyncQueue {
+    pub fn start(pool: Pool<Sqlite>, config: SyncConfig) -> Self {
+        let wake = Arc::new(Notify::new());
+        let (events, _) = watch::channel(0);
+        let queue = Self { pool, wake, events, running: Arc::new(Mutex::new(None)) };
+        tokio::spawn(work(queue.clone(), config));
+
+        queue
+    }
+
+    /// Gets the id of the last `sync_events` row every time the worker writes one. Only a wake up
+    /// signal, the events themselves have to be read from the database.
+    pub fn subscribe_events(&self) -> watch::Receiver<i64> {
+        self.events.subscribe()
+    }
+
+    pub async fn enqueue(
+        &self,
+        mode: SyncMode,
+        reason: SyncReason,
+        options: &SyncOptions,
+    ) -> Result<QueuedRun, sqlx::Error> {
+        let options_json = serde_json::to_string(options).unwrap_or_else(|_| "{}".to_string());
+        let queued = sync_runs::enqueue_sync_run(&self.pool, mode.as_str(), reason.as_str(), &options_json, &now()).await?;
+        self.wake.notify_one();
+
+        Ok(queued)
+    }
+
+    pub async fn cancel(&self, run_id: &str) -> Result<CancelOutcome, sqlx::Error> {
+        // Held the whole time so the worker can't claim the run in between the two checks.
+        let running = self.running.lock().await;
+
+        if sync_runs::cancel_queued_sync_run(&self.pool, run_id, &now()).await? {
+            // Best effort, same as the events written by the worker.
+            if let Ok(id) = sync_runs::insert_sync_event(&self.pool, run_id, "info", "run_cancelled", "{}", &now()).await {
+                self.events.send_replace(id);
+            }
+            return Ok(CancelOutcome::Cancelled);
+        }
+
+        match running.as_ref() {
+            Some(run) if run.id == run_id => {
+                run.cancel.store(true, Ordering::Relaxed);
+                Ok(CancelOutcome::Requested)
+            }
+            _ => Ok(CancelOutcome::NotActive),
+        }
+    }
+
+}
+
+async fn work(queue: SyncQueue, config: SyncConfig) {
+    let pool = &queue.pool;
+
+    match sync_runs::fail_interrupted_sync_runs(pool, &now()).await {
+        Ok(0) => {}
+        Ok(n) => tracing::warn!("Marked {} interrupted sync runs as failed", n),
+        Err(e) => tracing::error!("Failed to clean up interrupted sync runs: {}", e),
+    }
+
+    loop {
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        // A cancel either finds the run still queued or registered as running, never in between.
+        let claimed = {
+            let mut running = queue.running.lock().await;
+            let claimed = sync_runs::claim_next_sync_run(pool, &now()).await;
+            if let Ok(Some(run)) = &claimed {
+                *running = Some(RunningRun { id: run.id.clone(), cancel: cancel.clone() });
+            }
+            claimed
+        };
+
+        match claimed {
+            Ok(Some(run)) => {
+                // execute_run records the outcome in sync_runs, nothing else to do with the result here.
+                let _ = execute_run(pool, &config, &queue.events, cancel, &run).await;
+                *queue.running.lock().await = None;
+            }
+            Ok(None) => queue.wake.notified().await,
+            Err(e) => {
+                tracing::error!("Failed to claim next sync run: {}", e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    }
+}
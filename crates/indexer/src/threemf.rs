@@ -0,0 +1,577 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use lima_domain::models::asset_metadata::AssetMetadata;
+use quick_xml::Reader;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use zip::ZipArchive;
+
+use crate::mesh::Mesh;
+
+const RELS_PATH: &str = "_rels/.rels";
+const DEFAULT_MODEL_PATH: &str = "3D/3dmodel.model";
+const MODEL_REL_TYPE: &str = "http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel";
+const THUMBNAIL_REL_TYPE: &str = "http://schemas.openxmlformats.org/package/2006/relationships/metadata/thumbnail";
+// Where slicers put their previews when the relationship is missing. PrusaSlicer and Cura use the
+// first one, Bambu Studio and Orca the plate previews.
+const THUMBNAIL_FALLBACKS: &[&str] = &["Metadata/thumbnail.png", "Metadata/plate_1.png", "Metadata/plate_1_small.png"];
+// A thumbnail bigger than this is not a thumbnail, don't load it in memory.
+const MAX_THUMBNAIL_BYTES: u64 = 32 * 1024 * 1024;
+// Components referencing components, deeper than this is a loop.
+const MAX_COMPONENT_DEPTH: usize = 16;
+// Components fanning out at every level multiply the mesh. Counts placed objects, vertices and
+// triangles, the biggest real models are a few million triangles.
+const MAX_PLACED_ELEMENTS: usize = 20_000_000;
+
+#[derive(Debug)]
+pub enum ThreeMfError {
+    Io(std::io::Error),
+    Zip(zip::result::ZipError),
+    Xml(quick_xml::Error),
+    /// The components flatten to more vertices and triangles than allowed.
+    TooLarge,
+}
+
+impl fmt::Display for ThreeMfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThreeMfError::Io(e) => write!(f, "Failed reading 3MF: {}", e),
+            ThreeMfError::Zip(e) => write!(f, "Invalid 3MF archive: {}", e),
+            ThreeMfError::Xml(e) => write!(f, "Invalid 3MF XML: {}", e),
+            ThreeMfError::TooLarge => write!(f, "3MF components expand to more than {} vertices and triangles", MAX_PLACED_ELEMENTS),
+        }
+    }
+}
+
+impl From<std::io::Error> for ThreeMfError {
+    fn from(e: std::io::Error) -> Self {
+        ThreeMfError::Io(e)
+    }
+}
+
+impl From<zip::result::ZipError> for ThreeMfError {
+    fn from(e: zip::result::ZipError) -> Self {
+        ThreeMfError::Zip(e)
+    }
+}
+
+impl From<quick_xml::Error> for ThreeMfError {
+    fn from(e: quick_xml::Error) -> Self {
+        ThreeMfError::Xml(e)
+    }
+}
+
+/// A 3MF package opened for reading. Only reads, the file is never modified. Blocking.
+pub struct ThreeMf {
+    archive: ZipArchive<BufReader<File>>,
+    model_path: String,
+    thumbnail_path: Option<String>,
+}
+
+impl ThreeMf {
+    pub fn open(path: &Path) -> Result<Self, ThreeMfError> {
+        let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+
+        let (mut model_path, mut thumbnail_path) = (None, None);
+        if let Ok(rels) = archive.by_name(RELS_PATH) {
+            for (rel_type, target) in read_relationships(BufReader::new(rels))? {
+                match rel_type.as_str() {
+                    MODEL_REL_TYPE => model_path = Some(target),
+                    THUMBNAIL_REL_TYPE => thumbnail_path = Some(target),
+                    _ => {}
+                }
+            }
+        }
+
+        let thumbnail_path = thumbnail_path
+            .filter(|p| archive.index_for_name(p).is_some())
+            .or_else(|| THUMBNAIL_FALLBACKS.iter().find(|p| archive.index_for_name(p).is_some()).map(|p| p.to_string()));
+
+        Ok(Self {
+            archive,
+            model_path: model_path.unwrap_or_else(|| DEFAULT_MODEL_PATH.to_string()),
+            thumbnail_path,
+        })
+    }
+
+    /// The embedded preview image, as stored (PNG or JPEG).
+    pub fn thumbnail(&mut self) -> Result<Option<Vec<u8>>, ThreeMfError> {
+        let Some(path) = &self.thumbnail_path else {
+            return Ok(None);
+        };
+
+        let entry = self.archive.by_name(path)?;
+        if entry.size() > MAX_THUMBNAIL_BYTES {
+            return Ok(None);
+        }
+
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.take(MAX_THUMBNAIL_BYTES).read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+
+    /// The `<metadata>` of the model. They come before the meshes, so big models aren't read through.
+    pub fn metadata(&mut self) -> Result<AssetMetadata, ThreeMfError> {
+        let entry = match self.archive.by_name(&self.model_path) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(AssetMetadata::default()),
+            Err(e) => return Err(e.into()),
+        };
+
+        read_model_metadata(BufReader::new(entry))
+    }
+
+    /// Every build item with its transform applied, in millimetres. Bambu Studio and Orca keep the
+    /// meshes in separate model files (production extension), they are followed too.
+    pub fn mesh(&mut self) -> Result<Mesh, ThreeMfError> {
+        self.mesh_within(MAX_PLACED_ELEMENTS)
+    }
+
+    fn mesh_within(&mut self, budget: usize) -> Result<Mesh, ThreeMfError> {
+        let main_path = self.model_path.clone();
+        let main = self.read_model_file(&main_path)?;
+
+        // No build section: every object of the main file, where it is.
+        let items: Vec<(String, String, Transform)> = if main.build.is_empty() {
+            main.objects.keys().map(|id| (main_path.clone(), id.clone(), IDENTITY)).collect()
+        } else {
+            main.build.iter()
+                .map(|(path, id, transform)| (path.clone().unwrap_or_else(|| main_path.clone()), id.clone(), *transform))
+                .collect()
+        };
+
+        let mut placed = Placed {
+            mesh: Mesh {
+                unit: Some(main.unit.clone()),
+                ..Default::default()
+            },
+            budget,
+        };
+        let scale = unit_scale(&main.unit);
+
+        let mut files = HashMap::from([(main_path, main)]);
+        for (path, object_id, transform) in items {
+            self.place(&mut files, &path, &object_id, transform, &mut placed, 0)?;
+        }
+        let mut mesh = placed.mesh;
+
+        for v in &mut mesh.vertices {
+            *v = v.map(|c| c * scale);
+        }
+
+        Ok(mesh)
+    }
+
+    fn place(
+        &mut self,
+        files: &mut HashMap<String, ModelFile>,
+        path: &str,
+        object_id: &str,
+        transform: Transform,
+        out: &mut Placed,
+        depth: usize,
+    ) -> Result<(), ThreeMfError> {
+        if depth > MAX_COMPONENT_DEPTH {
+            return Ok(());
+        }
+        if !files.contains_key(path) {
+            let file = self.read_model_file(path)?;
+            files.insert(path.to_string(), file);
+        }
+
+        let Some(object) = files[path].objects.get(object_id) else {
+            return Ok(());
+        };
+
+        // Empty objects count too, they can still fan out.
+        let cost = 1 + object.vertices.len() + object.triangles.len();
+        out.budget = out.budget.checked_sub(cost).ok_or(ThreeMfError::TooLarge)?;
+
+        let offset = out.mesh.vertices.len();
+        let count = object.vertices.len();
+        out.mesh.vertices.extend(object.vertices.iter().map(|v| apply(&transform, *v)));
+        for triangle in &object.triangles {
+            if triangle.iter().all(|&i| i < count) {
+                out.mesh.triangles.push(triangle.map(|i| i + offset));
+            }
+        }
+
+        for component in object.components.clone() {
+            let component_path = component.path.as_deref().unwrap_or(path);
+            let combined = compose(&component.transform, &transform);
+            self.place(files, component_path, &component.object_id, combined, out, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+    // A missing model file is an empty one, some exporters leave dangling references.
+    fn read_model_file(&mut self, path: &str) -> Result<ModelFile, ThreeMfError> {
+        match self.archive.by_name(path) {
+            Ok(entry) => read_model_file(BufReader::new(entry)),
+            Err(zip::result::ZipError::FileNotFound) => Ok(ModelFile::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// The flattened mesh, and what may still be added to it.
+struct Placed {
+    mesh: Mesh,
+    budget: usize,
+}
+
+// 3MF transforms are 4x3, rows are the images of x, y, z and the translation.
+type Transform = [[f32; 3]; 4];
+
+const IDENTITY: Transform = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 0.0]];
+
+fn apply(m: &Transform, [x, y, z]: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|c| x * m[0][c] + y * m[1][c] + z * m[2][c] + m[3][c])
+}
+
+// `child` first, then `parent`.
+fn compose(child: &Transform, parent: &Transform) -> Transform {
+    let linear = |[x, y, z]: [f32; 3]| -> [f32; 3] {
+        std::array::from_fn(|c| x * parent[0][c] + y * parent[1][c] + z * parent[2][c])
+    };
+    [linear(child[0]), linear(child[1]), linear(child[2]), apply(parent, child[3])]
+}
+
+fn parse_transform(value: &str) -> Option<Transform> {
+    let numbers: Vec<f32> = value.split_whitespace().map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    if numbers.len() != 12 {
+        return None;
+    }
+    Some(std::array::from_fn(|row| [numbers[row * 3], numbers[row * 3 + 1], numbers[row * 3 + 2]]))
+}
+
+fn unit_scale(unit: &str) -> f32 {
+    match unit {
+        "micron" => 0.001,
+        "centimeter" => 10.0,
+        "inch" => 25.4,
+        "foot" => 304.8,
+        "meter" => 1000.0,
+        _ => 1.0,
+    }
+}
+
+#[derive(Debug)]
+struct ModelFile {
+    unit: String,
+    objects: HashMap<String, ModelObject>,
+    /// (model file when not this one, object id, transform) of every build item.
+    build: Vec<(Option<String>, String, Transform)>,
+}
+
+impl Default for ModelFile {
+    fn default() -> Self {
+        Self { unit: "millimeter".to_string(), objects: HashMap::new(), build: Vec::new() }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ModelObject {
+    vertices: Vec<[f32; 3]>,
+    triangles: Vec<[usize; 3]>,
+    components: Vec<Component>,
+}
+
+#[derive(Debug, Clone)]
+struct Component {
+    /// Another model file of the package, the current one when None.
+    path: Option<String>,
+    object_id: String,
+    transform: Transform,
+}
+
+fn read_model_file<R: std::io::BufRead>(input: R) -> Result<ModelFile, ThreeMfError> {
+    let mut reader = Reader::from_reader(input);
+    let mut buf = Vec::new();
+    let mut file = ModelFile::default();
+    let mut current: Option<(String, ModelObject)> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"model" => {
+                    if let Some(unit) = attribute(&e, b"unit") {
+                        file.unit = unit;
+                    }
+                }
+                b"object" => {
+                    current = attribute(&e, b"id").map(|id| (id, ModelObject::default()));
+                }
+                b"vertex" => {
+                    if let Some((_, object)) = current.as_mut() {
+                        let [x, y, z] = [b"x", b"y", b"z"].map(|name| number::<f32>(&e, name));
+                        if let (Some(x), Some(y), Some(z)) = (x, y, z) {
+                            object.vertices.push([x, y, z]);
+                        }
+                    }
+                }
+                b"triangle" => {
+                    if let Some((_, object)) = current.as_mut() {
+                        let [v1, v2, v3] = [b"v1", b"v2", b"v3"].map(|name| number::<usize>(&e, name));
+                        if let (Some(v1), Some(v2), Some(v3)) = (v1, v2, v3) {
+                            object.triangles.push([v1, v2, v3]);
+                        }
+                    }
+                }
+                b"component" => {
+                    if let (Some((_, object)), Some(object_id)) = (current.as_mut(), attribute(&e, b"objectid")) {
+                        object.components.push(Component {
+                            path: path_attribute(&e),
+                            object_id,
+                            transform: transform_attribute(&e),
+                        });
+                    }
+                }
+                b"item" => {
+                    if let Some(object_id) = attribute(&e, b"objectid") {
+                        file.build.push((path_attribute(&e), object_id, transform_attribute(&e)));
+                    }
+                }
+                _ => {}
+            },
+            Event::End(e) if e.local_name().as_ref() == b"object" => {
+                if let Some((id, object)) = current.take() {
+                    file.objects.insert(id, object);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(file)
+}
+
+fn number<T: std::str::FromStr>(element: &quick_xml::events::BytesStart<'_>, name: &[u8]) -> Option<T> {
+    attribute(element, name)?.trim().parse().ok()
+}
+
+fn transform_attribute(element: &quick_xml::events::BytesStart<'_>) -> Transform {
+    attribute(element, b"transform").and_then(|t| parse_transform(&t)).unwrap_or(IDENTITY)
+}
+
+// `p:path` of the production extension, whatever the prefix.
+fn path_attribute(element: &quick_xml::events::BytesStart<'_>) -> Option<String> {
+    element.attributes().flatten()
+        .find(|a| a.key.local_name().as_ref() == b"path")
+        .and_then(|a| a.unescape_value().ok())
+        .map(|path| path.trim_start_matches('/').to_string())
+}
+
+// (Type, Target) of every relationship, targets without their leading '/' to match zip entry names.
+fn read_relationships<R: std::io::BufRead>(input: R) -> Result<Vec<(String, String)>, ThreeMfError> {
+    let mut reader = Reader::from_reader(input);
+    let mut buf = Vec::new();
+    let mut relationships = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                let rel_type = attribute(&e, b"Type");
+                let target = attribute(&e, b"Target");
+                if let (Some(rel_type), Some(target)) = (rel_type, target) {
+                    relationships.push((rel_type, target.trim_start_matches('/').to_string()));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(relationships)
+}
+
+fn read_model_metadata<R: std::io::BufRead>(input: R) -> Result<AssetMetadata, ThreeMfError> {
+    let mut reader = Reader::from_reader(input);
+    let mut buf = Vec::new();
+    let mut metadata = AssetMetadata::default();
+    // Name of the <metadata> being read and its text so far.
+    let mut current: Option<(String, String)> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if e.local_name().as_ref() == b"metadata" => {
+                current = attribute(&e, b"name").map(|name| (name, String::new()));
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"resources" => break,
+            Event::Text(text) => {
+                if let Some((_, value)) = current.as_mut() {
+                    value.push_str(&text.decode().map_err(quick_xml::Error::from)?);
+                }
+            }
+            Event::CData(text) => {
+                if let Some((_, value)) = current.as_mut() {
+                    value.push_str(&text.decode().map_err(quick_xml::Error::from)?);
+                }
+            }
+            Event::GeneralRef(reference) => {
+                if let Some((_, value)) = current.as_mut() {
+                    if let Some(c) = reference.resolve_char_ref()? {
+                        value.push(c);
+                    } else if let Some(resolved) = resolve_predefined_entity(&reference.decode().map_err(quick_xml::Error::from)?) {
+                        value.push_str(resolved);
+                    }
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"metadata" => {
+                if let Some((name, value)) = current.take() {
+                    set_field(&mut metadata, &name, value);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(metadata)
+}
+
+// Names differ a bit between slicers and the spec, the first value found wins.
+fn set_field(metadata: &mut AssetMetadata, name: &str, value: String) {
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+
+    // Names may carry a namespace prefix, e.g. "BambuStudio:Designer".
+    let name = name.rsplit(':').next().unwrap_or(name).to_ascii_lowercase();
+    let (field, value) = match name.as_str() {
+        "title" => (&mut metadata.title, value.to_string()),
+        "designer" => (&mut metadata.designer, value.to_string()),
+        "license" | "licenseterms" => (&mut metadata.license, value.to_string()),
+        "description" => (&mut metadata.description, html_to_text(value)),
+        _ => return,
+    };
+
+    if field.is_none() && !value.is_empty() {
+        *field = Some(value);
+    }
+}
+
+// Bambu Studio and MakerWorld store the description as HTML, keep only the text.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let tag = rest[start + 1..start + end].trim().to_ascii_lowercase();
+        if tag.starts_with("br") || tag == "/p" || tag == "/div" || tag == "/li" || tag.starts_with("/h") {
+            text.push('\n');
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    // Trimmed lines, runs of blank lines kept as a single paragraph break.
+    let mut out = String::with_capacity(text.len());
+    let mut paragraph = false;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            paragraph = !out.is_empty();
+            continue;
+        }
+        if !out.is_empty() {
+            out.push_str(if paragraph { "\n\n" } else { "\n" });
+        }
+        out.push_str(line);
+        paragraph = false;
+    }
+
+    out
+}
+
+fn attribute(element: &quick_xml::events::BytesStart<'_>, name: &[u8]) -> Option<String> {
+    let attribute = element.try_get_attribute(name).ok()??;
+    attribute.unescape_value().ok().map(|value| value.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    // One triangle, then `levels` objects each made of 10 copies of the one below.
+    fn fan_out(name: &str, levels: usize) -> PathBuf {
+        let mut model = String::from(r#"<model unit="millimeter"><resources><object id="1"><mesh><vertices>"#);
+        model.push_str(r#"<vertex x="0" y="0" z="0"/><vertex x="1" y="0" z="0"/><vertex x="0" y="1" z="0"/>"#);
+        model.push_str(r#"</vertices><triangles><triangle v1="0" v2="1" v3="2"/></triangles></mesh></object>"#);
+        for level in 2..=levels + 1 {
+            model.push_str(&format!(r#"<object id="{}"><components>"#, level));
+            for _ in 0..10 {
+                model.push_str(&format!(r#"<component objectid="{}"/>"#, level - 1));
+            }
+            model.push_str("</components></object>");
+        }
+        model.push_str(&format!(r#"</resources><build><item objectid="{}"/></build></model>"#, levels + 1));
+
+        let path = std::env::temp_dir().join(format!("lima-threemf-{}-{}.3mf", name, std::process::id()));
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file(DEFAULT_MODEL_PATH, SimpleFileOptions::default()).unwrap();
+        zip.write_all(model.as_bytes()).unwrap();
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn components_are_flattened() {
+        let path = fan_out("flatten", 2);
+        let mesh = ThreeMf::open(&path).unwrap().mesh().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(mesh.triangles.len(), 100);
+        assert_eq!(mesh.vertices.len(), 300);
+    }
+
+    #[test]
+    fn fan_out_past_the_budget_fails() {
+        let path = fan_out("budget", 4);
+        let mut three_mf = ThreeMf::open(&path).unwrap();
+        // 10^4 triangles at 5 elements each, and 1111 component objects at 1.
+        let within = three_mf.mesh_within(60_000).map(|mesh| mesh.triangles.len());
+        let over = three_mf.mesh_within(40_000);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(within.unwrap(), 10_000);
+        assert!(matches!(over, Err(ThreeMfError::TooLarge)));
+    }
+
+    #[test]
+    fn deep_fan_out_stops_at_the_budget() {
+        let path = fan_out("empty", 16);
+        let result = ThreeMf::open(&path).unwrap().mesh_within(1_000);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(ThreeMfError::TooLarge)));
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageReader, Rgb, RgbImage};
+use lima_db::queries::assets::set_thumbnail_hash;
+use lima_domain::models::thumbnail::{LARGE_THUMBNAIL, THUMBNAIL_SIZES, thumbnail_file_name};
+use sqlx::{Pool, Sqlite};
+
+use crate::gcode::{GcodeError, read_gcode};
+use crate::mesh::{MeshError, MeshFormat, load_mesh};
+use crate::render::render_preview;
+use crate::threemf::{ThreeMf, ThreeMfError};
+
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Debug)]
+pub enum ThumbnailError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Mesh(MeshError),
+    ThreeMf(ThreeMfError),
+    Gcode(GcodeError),
+    /// The 3MF or G-code has no embedded preview. Not a failure, it depends on the slicer settings.
+    NoPreview,
+    /// Nothing to draw, e.g. every triangle is degenerate.
+    EmptyRender,
+    Db(sqlx::Error),
+}
+
+impl fmt::Display for ThumbnailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThumbnailError::Io(e) => write!(f, "Filesystem error: {}", e),
+            ThumbnailError::Image(e) => write!(f, "Image error: {}", e),
+            ThumbnailError::Mesh(e) => write!(f, "Mesh error: {}", e),
+            ThumbnailError::ThreeMf(e) => write!(f, "{}", e),
+            ThumbnailError::Gcode(e) => write!(f, "{}", e),
+            ThumbnailError::NoPreview => write!(f, "No embedded preview"),
+            ThumbnailError::EmptyRender => write!(f, "Nothing to render"),
+            ThumbnailError::Db(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+impl From<std::io::Error> for ThumbnailError {
+    fn from(e: std::io::Error) -> Self {
+        ThumbnailError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for ThumbnailError {
+    fn from(e: image::ImageError) -> Self {
+        ThumbnailError::Image(e)
+    }
+}
+
+impl From<MeshError> for ThumbnailError {
+    fn from(e: MeshError) -> Self {
+        ThumbnailError::Mesh(e)
+    }
+}
+
+impl From<ThreeMfError> for ThumbnailError {
+    fn from(e: ThreeMfError) -> Self {
+        ThumbnailError::ThreeMf(e)
+    }
+}
+
+impl From<GcodeError> for ThumbnailError {
+    fn from(e: GcodeError) -> Self {
+        ThumbnailError::Gcode(e)
+    }
+}
+
+impl From<sqlx::Error> for ThumbnailError {
+    fn from(e: sqlx::Error) -> Self {
+        ThumbnailError::Db(e)
+    }
+}
+
+/// Thumbnails are stored as `<root>/<asset id>/<hash>-<size>.jpg`, one folder per asset so they are
+/// easy to drop with the asset.
+#[derive(Debug, Clone)]
+pub struct ThumbnailStore {
+    root: PathBuf,
+}
+
+impl ThumbnailStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn asset_dir(&self, asset_id: &str) -> PathBuf {
+        self.root.join(asset_id)
+    }
+
+    /// Decodes the image at `source` and writes every thumbnail size for it.
+    pub async fn generate_from_image(&self, asset_id: &str, source: &Path, source_hash: &str) -> Result<(), ThumbnailError> {
+        let source = source.to_path_buf();
+        let image = tokio::task::spawn_blocking(move || decode_image(&source))
+            .await
+            .map_err(std::io::Error::other)??;
+
+        self.write(asset_id, source_hash, image).await
+    }
+
+    /// Renders a preview of the STL/OBJ/glTF model at `source` and writes every thumbnail size for it.
+    pub async fn generate_from_mesh(&self, asset_id: &str, source: &Path, source_hash: &str) -> Result<(), ThumbnailError> {
+        let source = source.to_path_buf();
+        let image = tokio::task::spawn_blocking(move || {
+            let mesh = load_mesh(&source)?;
+            // Twice the biggest size, scaling it down is our anti-aliasing.
+            render_preview(&mesh, LARGE_THUMBNAIL * 2).ok_or(ThumbnailError::EmptyRender)
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+
+        self.write(asset_id, source_hash, DynamicImage::ImageRgb8(image)).await
+    }
+
+    /// Uses the preview the slicer embedded in the 3MF at `source`. The 3MF itself is only read.
+    pub async fn generate_from_3mf(&self, asset_id: &str, source: &Path, source_hash: &str) -> Result<(), ThumbnailError> {
+        let source = source.to_path_buf();
+        let image = tokio::task::spawn_blocking(move || {
+            let bytes = ThreeMf::open(&source)?.thumbnail()?.ok_or(ThumbnailError::NoPreview)?;
+            Ok::<_, ThumbnailError>(image::load_from_memory(&bytes)?)
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+
+        self.write(asset_id, source_hash, image).await
+    }
+
+    /// Uses the biggest preview the slicer embedded in the G-code at `source`.
+    pub async fn generate_from_gcode(&self, asset_id: &str, source: &Path, source_hash: &str) -> Result<(), ThumbnailError> {
+        let source = source.to_path_buf();
+        let image = tokio::task::spawn_blocking(move || {
+            let bytes = read_gcode(&source)?.thumbnail.ok_or(ThumbnailError::NoPreview)?;
+            Ok::<_, ThumbnailError>(image::load_from_memory(&bytes)?)
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+
+        self.write(asset_id, source_hash, image).await
+    }
+
+    /// Writes every thumbnail size of `image` and drops the ones made from older versions of the file.
+    pub async fn write(&self, asset_id: &str, source_hash: &str, image: DynamicImage) -> Result<(), ThumbnailError> {
+        let dir = self.asset_dir(asset_id);
+        let source_hash = source_hash.to_string();
+
+        tokio::task::spawn_blocking(move || write_sizes(&dir, &source_hash, &image))
+            .await
+            .map_err(std::io::Error::other)?
+    }
+
+    /// Best effort, a leftover folder only costs disk space.
+    pub async fn remove(&self, asset_id: &str) {
+        match tokio::fs::remove_dir_all(self.asset_dir(asset_id)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed removing thumbnails of asset {}: {}", asset_id, e),
+        }
+    }
+}
+
+/// Images get thumbnails of themselves, STL, OBJ and glTF models a rendered preview, 3MF and G-code
+/// files their embedded preview. Other files have none.
+pub fn has_thumbnail_source(kind: &str, file_path: &str) -> bool {
+    match kind {
+        "image" | "gcode" => true,
+        "model" => MeshFormat::from_path(file_path).is_some() || is_3mf(file_path),
+        _ => false,
+    }
+}
+
+fn is_3mf(file_path: &str) -> bool {
+    file_path.to_ascii_lowercase().ends_with(".3mf")
+}
+
+/// Generates the thumbnails of an asset and records which version of the file they come from, so
+/// they are only generated again when the file changes. Check [`has_thumbnail_source`] first.
+pub async fn refresh_thumbnails(
+    pool: &Pool<Sqlite>,
+    store: &ThumbnailStore,
+    asset_id: &str,
+    kind: &str,
+    source: &Path,
+    source_hash: &str,
+) -> Result<(), ThumbnailError> {
+    match kind {
+        "gcode" => store.generate_from_gcode(asset_id, source, source_hash).await?,
+        "model" if is_3mf(&source.to_string_lossy()) => store.generate_from_3mf(asset_id, source, source_hash).await?,
+        "model" => store.generate_from_mesh(asset_id, source, source_hash).await?,
+        _ => store.generate_from_image(asset_id, source, source_hash).await?,
+    }
+
+    set_thumbnail_hash(pool, asset_id, source_hash).await?;
+    Ok(())
+}
+
+// Phones store pictures sideways plus an EXIF orientation, apply it or half the thumbnails are rotated.
+fn decode_image(path: &Path) -> Result<DynamicImage, ThumbnailError> {
+    let mut decoder = ImageReader::open(path)?.with_guessed_format()?.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+fn write_sizes(dir: &Path, source_hash: &str, image: &DynamicImage) -> Result<(), ThumbnailError> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut current = Vec::with_capacity(THUMBNAIL_SIZES.len());
+    for size in THUMBNAIL_SIZES {
+        let name = thumbnail_file_name(source_hash, size);
+        let resized = if image.width() > size || image.height() > size {
+            image.resize(size, size, FilterType::Lanczos3)
+        } else {
+            image.clone()
+        };
+
+        // Written next to the final name and renamed, a reader never gets half a file.
+        let tmp = dir.join(format!("{}.tmp", name));
+        let mut out = BufWriter::new(std::fs::File::create(&tmp)?);
+        JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY).encode_image(&flatten(&resized))?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        std::fs::rename(&tmp, dir.join(&name))?;
+
+        current.push(name);
+    }
+
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if !current.iter().any(|name| entry.file_name() == name.as_str()) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+// JPEG has no alpha, transparent parts go on white instead of whatever color hides under them.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+
+    let rgba = image.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
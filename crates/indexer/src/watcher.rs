@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use lima_domain::models::sync::{SyncMode, SyncOptions, SyncReason};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::ignore_rules::{IGNORE_FILE_NAME, IgnoreRules, LibraryIgnore, ProjectIgnore};
+use crate::sync::SyncQueue;
+
+const EVENT_QUEUE_SIZE: usize = 4096;
+const TICK: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct WatcherConfig {
+    /// Quiet period after the last event in a project folder before it gets synced.
+    pub debounce: Duration,
+    /// Once the event queue overflows events can't be trusted anymore. Until things calm down we run
+    /// a full scan at most once per interval instead of targeted syncs.
+    pub overflow_rescan_interval: Duration,
+}
+
+impl Default for WatcherConfig {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_secs(2),
+            overflow_rescan_interval: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Watches the library (best effort) and queues `incremental` runs with reason `watcher` for the
+/// project folders that changed. Changes to ignored files don't trigger anything. The watcher lives as
+/// long as the tokio runtime.
+pub fn start_watcher(
+    library_root: &Path,
+    queue: SyncQueue,
+    ignore: IgnoreRules,
+    config: WatcherConfig,
+) -> Result<(), notify::Error> {
+    // notify reports absolute paths, canonicalize so we can map them back to project folders.
+    let library_root = library_root.canonicalize()?;
+    let (tx, rx) = mpsc::channel::<Event>(EVENT_QUEUE_SIZE);
+    let overflowed = Arc::new(AtomicBool::new(false));
+
+    let flag = overflowed.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        match res {
+            // Reads (including our own hashing) show up as access events, they never change the index.
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+            Ok(event) => {
+                if event.need_rescan() || tx.try_send(event).is_err() {
+                    flag.store(true, Ordering::Relaxed);
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Library watcher error: {}", e);
+                flag.store(true, Ordering::Relaxed);
+            }
+        }
+    })?;
+
+    watcher.watch(&library_root, RecursiveMode::Recursive)?;
+    tracing::info!("Watching library at {}", library_root.display());
+
+    tokio::spawn(watch_loop(watcher, library_root, rx, overflowed, queue, ignore, config));
+
+    Ok(())
+}
+
+async fn watch_loop(
+    // Dropping the watcher stops the events, so the loop owns it.
+    _watcher: RecommendedWatcher,
+    library_root: PathBuf,
+    mut events: mpsc::Receiver<Event>,
+    overflowed: Arc<AtomicBool>,
+    queue: SyncQueue,
+    ignore: IgnoreRules,
+    config: WatcherConfig,
+) {
+    let mut filter = EventFilter::load(&library_root, ignore).await;
+    // Set when the library .limaignore changes, every project may be affected.
+    let mut rules_changed: Option<Instant> = None;
+    let mut pending: HashMap<String, Instant> = HashMap::new();
+    let mut degraded = Degraded::default();
+    let mut tick = tokio::time::interval(TICK);
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let Some(event) = event else { break };
+
+                for path in &event.paths {
+                    match filter.classify(path).await {
+                        Some(Change::Rules) => rules_changed = Some(Instant::now()),
+                        Some(Change::Project(folder)) => {
+                            pending.insert(folder, Instant::now());
+                        }
+                        None => {}
+                    }
+                }
+            }
+            _ = tick.tick() => {
+                if overflowed.swap(false, Ordering::Relaxed) {
+                    pending.clear();
+                    rules_changed = None;
+                    if degraded.overflow() {
+                        tracing::warn!("Library watcher queue overflowed, falling back to full scans");
+                        enqueue(&queue, SyncMode::Full, SyncOptions::default()).await;
+                    }
+                    continue;
+                }
+
+                if degraded.active() {
+                    let dirty = !pending.is_empty();
+                    pending.clear();
+
+                    match degraded.tick(dirty, config.overflow_rescan_interval) {
+                        DegradedAction::FullScan => enqueue(&queue, SyncMode::Full, SyncOptions::default()).await,
+                        DegradedAction::Recovered => tracing::info!("Library watcher recovered, back to targeted syncs"),
+                        DegradedAction::Wait => {}
+                    }
+                    continue;
+                }
+
+                if rules_changed.is_some_and(|last| last.elapsed() >= config.debounce) {
+                    rules_changed = None;
+                    pending.clear();
+                    tracing::debug!("Library ignore rules changed, syncing every project");
+                    enqueue(&queue, SyncMode::Incremental, SyncOptions::default()).await;
+                    continue;
+                }
+
+                let ready: Vec<String> = pending
+                    .iter()
+                    .filter(|(_, last)| last.elapsed() >= config.debounce)
+                    .map(|(folder, _)| folder.clone())
+                    .collect();
+
+                if ready.is_empty() {
+                    continue;
+                }
+
+                for folder in &ready {
+                    pending.remove(folder);
+                }
+
+                tracing::debug!("Library watcher detected changes in {:?}", ready);
+                enqueue(&queue, SyncMode::Incremental, SyncOptions::for_folders(ready)).await;
+            }
+        }
+    }
+
+    tracing::warn!("Library watcher stopped");
+}
+
+#[derive(Default)]
+struct Degraded {
+    last_overflow: Option<Instant>,
+    last_full_scan: Option<Instant>,
+    dirty: bool,
+}
+
+enum DegradedAction {
+    Wait,
+    FullScan,
+    Recovered,
+}
+
+impl Degraded {
+    fn active(&self) -> bool {
+        self.last_overflow.is_some()
+    }
+
+    // Returns true when we just entered degraded mode and a full scan must be queued right away.
+    fn overflow(&mut self) -> bool {
+        let entering = !self.active();
+        let now = Instant::now();
+
+        self.last_overflow = Some(now);
+        if entering {
+            self.last_full_scan = Some(now);
+            self.dirty = false;
+        } else {
+            self.dirty = true;
+        }
+
+        entering
+    }
+
+    fn tick(&mut self, dirty: bool, interval: Duration) -> DegradedAction {
+        self.dirty |= dirty;
+
+        if self.last_full_scan.is_some_and(|last| last.elapsed() < interval) {
+            return DegradedAction::Wait;
+        }
+
+        if self.dirty {
+            self.last_full_scan = Some(Instant::now());
+            self.dirty = false;
+            return DegradedAction::FullScan;
+        }
+
+        if self.last_overflow.is_some_and(|last| last.elapsed() >= interval) {
+            *self = Degraded::default();
+            return DegradedAction::Recovered;
+        }
+
+        DegradedAction::Wait
+    }
+}
+
+async fn enqueue(queue: &SyncQueue, mode: SyncMode, options: SyncOptions) {
+    if let Err(e) = queue.enqueue(mode, SyncReason::Watcher, &options).await {
+        tracing::error!("Library watcher failed to queue a sync run: {}", e);
+    }
+}
+
+enum Change {
+    /// The library `.limaignore`.
+    Rules,
+    Project(String),
+}
+
+// Maps event paths to project folders with the same rules the scanner uses. Project rules are cached
+// and dropped when their `.limaignore` changes.
+struct EventFilter {
+    library_root: PathBuf,
+    rules: IgnoreRules,
+    library: LibraryIgnore,
+    projects: HashMap<String, ProjectIgnore>,
+}
+
+impl EventFilter {
+    async fn load(library_root: &Path, rules: IgnoreRules) -> Self {
+        let library = rules.load_library(library_root).await;
+        Self { library_root: library_root.to_path_buf(), rules, library, projects: HashMap::new() }
+    }
+
+    async fn classify(&mut self, path: &Path) -> Option<Change> {
+        let relative = path.strip_prefix(&self.library_root).ok()?;
+        let mut components = relative.components().map(|c| match c {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        });
+
+        let folder = components.next()??;
+        let rest: Vec<&str> = components.collect::<Option<_>>()?;
+
+        if rest.is_empty() && folder == IGNORE_FILE_NAME {
+            self.library = self.rules.load_library(&self.library_root).await;
+            self.projects.clear();
+            return Some(Change::Rules);
+        }
+
+        if folder.starts_with('.') || self.library.is_ignored_folder(folder) {
+            return None;
+        }
+
+        if rest.is_empty() {
+            return Some(Change::Project(folder.to_string()));
+        }
+
+        if rest.len() == 1 && rest[0] == IGNORE_FILE_NAME {
+            self.projects.remove(folder);
+            return Some(Change::Project(folder.to_string()));
+        }
+
+        // Hidden files are never indexed either.
+        if rest.iter().any(|name| name.starts_with('.')) {
+            return None;
+        }
+
+        if !self.projects.contains_key(folder) {
+            let ignore = self.library.load_project(&self.library_root, folder).await;
+            self.projects.insert(folder.to_string(), ignore);
+        }
+
+        let file_path = rest.join("/");
+        if self.projects[folder].is_ignored(&file_path, path.is_dir()) {
+            return None;
+        }
+
+        Some(Change::Project(folder.to_string()))
+    }
+}
@@ -1,12 +1,13 @@
 use axum::{
     Router,
     extract::DefaultBodyLimit,
-    routing::{get, post, delete, patch, get_service},
+    middleware,
+    routing::{get, post, delete, patch, head, options, get_service},
     http::StatusCode,
 };
 use tower::ServiceBuilder;
 use tower_http::{catch_panic::CatchPanicLayer, services::{ServeDir, ServeFile}};
-use std::{env, net::SocketAddr, sync::Arc};
+use std::{env, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 use tokio::net::TcpListener;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
@@ -14,6 +15,7 @@ use utoipa_swagger_ui::SwaggerUi;
 mod routes;
 mod state;
 mod models;
+mod scheduler;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -30,10 +32,52 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let library_dir = ServeDir::new("data/library");
     let thumbs_dir = ServeDir::new("data/state/thumbnails");
 
+    let ignore_patterns: Vec<String> = env::var("LIMA_IGNORE_PATTERNS")
+        .map(|v| v.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect())
+        .unwrap_or_default();
+    let ignore = lima_indexer::ignore_rules::IgnoreRules::new(&ignore_patterns);
+
+    let library_root = PathBuf::from("data/library");
+    let thumbnails = lima_indexer::thumbnails::ThumbnailStore::new("data/state/thumbnails");
+    let sync = lima_indexer::sync::SyncQueue::start(db.pool().clone(), lima_indexer::sync::SyncConfig {
+        library_root: library_root.clone(),
+        bundles_root: PathBuf::from("data/state/bundles"),
+        ignore: ignore.clone(),
+        thumbnails: thumbnails.clone(),
+    });
+
+    if env::var("LIMA_WATCHER_ENABLED").map(|v| v != "false").unwrap_or(true) {
+        let mut watcher_config = lima_indexer::watcher::WatcherConfig::default();
+        if let Some(ms) = env::var("LIMA_WATCHER_DEBOUNCE_MS").ok().and_then(|v| v.parse().ok()) {
+            watcher_config.debounce = Duration::from_millis(ms);
+        }
+
+        // The API is still useful without the watcher, manual and scheduled syncs keep working.
+        if let Err(e) = lima_indexer::watcher::start_watcher(&library_root, sync.clone(), ignore.clone(), watcher_config) {
+            tracing::error!("Failed to start library watcher: {}", e);
+        }
+    }
+
+    scheduler::start_scheduler(sync.clone(), scheduler::ScheduleConfig::from_env());
+
     let state = state::AppState {
         db: Arc::new(db),
+        sync,
+        ignore,
+        thumbnails,
     };
 
+    // tus resumable uploads into bundles. Every response carries the protocol version.
+    let uploads = Router::new()
+        .route("/uploads", options(routes::uploads::options::upload_options))
+        .route("/uploads", post(routes::uploads::create::create_upload))
+        .route("/uploads/{bundle_id}/{upload_id}", head(routes::uploads::offset::upload_offset))
+        .route("/uploads/{bundle_id}/{upload_id}", patch(routes::uploads::append::upload_append)
+            .route_layer(DefaultBodyLimit::disable()),
+        )
+        .route("/uploads/{bundle_id}/{upload_id}", delete(routes::uploads::delete::upload_delete))
+        .route_layer(middleware::from_fn(routes::uploads::tus_resumable));
+
     let api = Router::new()
         .route("/health", get(routes::health::health_check))
         .route("/projects", get(routes::project::list_projects))
@@ -42,15 +86,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/projects/{project_id}", get(routes::project_detail::project_detail))
         .route("/projects/{project_id}", patch(routes::project_update::project_update))
         .route("/projects/{project_id}/import", post(routes::project_import::project_import))
+        .route("/projects/{project_id}/archive", get(routes::project_archive::project_archive))
+        .route("/projects/{project_id}/assets", get(routes::assets::list::list_project_assets))
+        .route("/projects/{project_id}/assets/{asset_id}", get(routes::assets::detail::asset_detail))
         .route("/projects/{project_id}/assets/{asset_id}", delete(routes::assets::delete::asset_delete))
+        .route("/projects/{project_id}/assets/{asset_id}/download", get(routes::assets::download::asset_download))
+        .route("/assets", get(routes::assets::list::list_assets))
+        .route("/asset-kinds", get(routes::asset_kinds::list_asset_kinds))
         
         .route("/tags", get(routes::tags::list::list_tags))
         .route("/tags", post(routes::tags::create::create_tag))
+
+        .route("/printers", get(routes::printers::list::list_printers))
+        .route("/printers", post(routes::printers::create::create_printer))
+        .route("/printers/{printer_id}", get(routes::printers::detail::printer_detail))
+        .route("/printers/{printer_id}", patch(routes::printers::update::printer_update))
+        .route("/printers/{printer_id}", delete(routes::printers::delete::printer_delete))
+
+        .route("/sync/run", post(routes::sync::run::run_sync))
+        .route("/sync/status", get(routes::sync::status::sync_status))
+        .route("/sync/runs", get(routes::sync::list::list_sync_runs))
+        .route("/sync/runs/{run_id}", get(routes::sync::detail::sync_run_detail))
+        .route("/sync/runs/{run_id}/events", get(routes::sync::events::list_sync_events))
+        .route("/sync/runs/{run_id}/cancel", post(routes::sync::cancel::cancel_sync_run))
+        .route("/sync/events/stream", get(routes::sync::stream::stream_sync_events))
         
         .route("/bundles", post(routes::bundle_create::create_bundle)
             .route_layer(DefaultBodyLimit::disable()),
         )
-        .route("/bundles/{bundle_id}", delete(routes::bundle_delete::bundle_delete));
+        .route("/bundles", get(routes::bundle_list::list_bundles))
+        .route("/bundles/{bundle_id}", get(routes::bundle_detail::bundle_detail))
+        .route("/bundles/{bundle_id}", delete(routes::bundle_delete::bundle_delete))
+        .route("/bundles/{bundle_id}/files", post(routes::bundle_append::bundle_append)
+            .route_layer(DefaultBodyLimit::disable()),
+        )
+        .merge(uploads);
     
     let app = Router::new()
         .nest("/api", api)
@@ -85,14 +155,43 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         routes::project_detail::project_detail,
         routes::project_update::project_update,
         routes::project_import::project_import,
+        routes::project_archive::project_archive,
 
+        routes::assets::list::list_project_assets,
+        routes::assets::list::list_assets,
+        routes::assets::detail::asset_detail,
+        routes::assets::download::asset_download,
         routes::assets::delete::asset_delete,
+        routes::asset_kinds::list_asset_kinds,
 
         routes::tags::list::list_tags,
         routes::tags::create::create_tag,
 
+        routes::printers::list::list_printers,
+        routes::printers::create::create_printer,
+        routes::printers::detail::printer_detail,
+        routes::printers::update::printer_update,
+        routes::printers::delete::printer_delete,
+
+        routes::sync::run::run_sync,
+        routes::sync::status::sync_status,
+        routes::sync::list::list_sync_runs,
+        routes::sync::detail::sync_run_detail,
+        routes::sync::events::list_sync_events,
+        routes::sync::cancel::cancel_sync_run,
+        routes::sync::stream::stream_sync_events,
+
         routes::bundle_create::create_bundle,
+        routes::bundle_list::list_bundles,
+        routes::bundle_detail::bundle_detail,
+        routes::bundle_append::bundle_append,
         routes::bundle_delete::bundle_delete,
+
+        routes::uploads::options::upload_options,
+        routes::uploads::create::create_upload,
+        routes::uploads::offset::upload_offset,
+        routes::uploads::append::upload_append,
+        routes::uploads::delete::upload_delete,
     ),
     components(schemas(
         crate::models::http_error::ApiErrorBody,
@@ -104,10 +203,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         routes::project_create::CreateProjectResponse,
         routes::project_update::PatchProjectRequest,
         routes::project_import::ImportProjectRequest,
+        routes::project_import::ImportProjectResponse,
+        routes::project_import::ImportedFileResponse,
+        routes::project_archive::ProjectArchiveParams,
+        routes::project_archive::ProjectArchiveManifest,
 
         routes::project_detail::ProjectDetailResponse,
         routes::project_detail::ProjectAssetResponse,
+        routes::project_detail::ProjectAssetGroupResponse,
+        routes::project_detail::ProjectDetailParams,
         routes::project_detail::ProjectTagResponse,
+        routes::project_detail::ProjectSuggestionsResponse,
+
+        routes::assets::AssetResponse,
+        routes::assets::list::ListAssetsParams,
+        routes::assets::list::ListAssetsResponse,
+        routes::assets::detail::AssetDetailResponse,
+        routes::asset_kinds::AssetKindResponse,
+        routes::asset_kinds::ListAssetKindsResponse,
 
         routes::tags::list::ListTagsResponse,
         routes::tags::list::ListTagsParams,
@@ -115,8 +228,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         routes::tags::create::CreateTagRequest,
         routes::tags::create::CreateTagResponse,
+
+        routes::printers::PrinterResponse,
+        routes::printers::list::ListPrintersResponse,
+        routes::printers::create::CreatePrinterRequest,
+        routes::printers::update::PatchPrinterRequest,
+
+        routes::sync::SyncRunResponse,
+        routes::sync::run::RunSyncRequest,
+        routes::sync::status::SyncStatusResponse,
+        routes::sync::list::ListSyncRunsParams,
+        routes::sync::list::ListSyncRunsResponse,
+        routes::sync::events::ListSyncEventsParams,
+        routes::sync::events::ListSyncEventsResponse,
+        routes::sync::events::SyncEventResponse,
+        routes::sync::stream::StreamSyncEventsParams,
+        lima_domain::models::thumbnail::ThumbnailUrls,
+        lima_domain::models::asset_metadata::AssetMetadata,
+        lima_domain::models::asset::AssetKind,
+        lima_domain::models::geometry::AssetGeometry,
+        lima_domain::models::geometry::BoundingBox,
+        lima_domain::models::printer::BuildVolume,
+        lima_domain::models::sync::SyncMode,
+        lima_domain::models::sync::SyncStats,
         
         routes::bundle_create::CreateBundleResponse,
+        routes::bundle_detail::BundleDetailResponse,
+        routes::bundle_list::BundleSummaryResponse,
+        routes::bundle_list::ListBundlesResponse,
+        lima_domain::models::bundle::FileMeta,
+        lima_domain::models::bundle::ImportConflict,
+        lima_domain::models::bundle::ImportAction,
     )),
 )]
 pub struct ApiDoc;
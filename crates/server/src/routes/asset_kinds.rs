@@ -0,0 +1,34 @@
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+use lima_domain::models::asset::AssetKind;
+
+#[derive(Serialize, ToSchema)]
+pub struct AssetKindResponse {
+    pub kind: AssetKind,
+    /// Lowercase, without the dot. Empty for `other`, the kind of every other extension.
+    pub extensions: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListAssetKindsResponse {
+    pub items: Vec<AssetKindResponse>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/asset-kinds",
+    responses(
+        (status = 200, description = "Asset kinds and the extensions they are given to", body = ListAssetKindsResponse),
+    )
+)]
+pub async fn list_asset_kinds() -> Json<ListAssetKindsResponse> {
+    Json(ListAssetKindsResponse {
+        items: AssetKind::ALL.into_iter()
+            .map(|kind| AssetKindResponse {
+                kind,
+                extensions: kind.extensions().iter().map(|ext| ext.to_string()).collect(),
+            })
+            .collect(),
+    })
+}
@@ -26,7 +26,10 @@ pub async fn asset_delete(
         &project_id,
         &asset_id,
     ).await {
-        Ok(_) => Ok(StatusCode::OK),
+        Ok(_) => {
+            _state.thumbnails.remove(&asset_id).await;
+            Ok(StatusCode::OK)
+        },
         Err(DeleteAssetError::NotFound { project_id: _ }) => {
             return Err(ApiErrorResponse::new(
                 StatusCode::NOT_FOUND,
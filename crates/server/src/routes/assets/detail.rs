@@ -0,0 +1,76 @@
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+use lima_domain::models::asset_metadata::AssetMetadata;
+use lima_domain::models::geometry::AssetGeometry;
+use lima_domain::models::thumbnail::ThumbnailUrls;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::find_asset;
+
+#[derive(Serialize, ToSchema)]
+pub struct AssetDetailResponse {
+    pub id: String,
+    pub project_id: String,
+    pub file_path: String,
+    pub kind: String,
+    pub mime: String,
+    pub size_bytes: i64,
+    pub mtime: String,
+    /// Hex hash of the content, null until the indexer computed it.
+    pub file_hash: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub thumbnails: Option<ThumbnailUrls>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<AssetMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geometry: Option<AssetGeometry>,
+    /// Where `GET .../download` serves the file.
+    pub download_url: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/assets/{asset_id}",
+    params(
+        ("project_id" = String, Path, description = "The ID of the project containing the asset"),
+        ("asset_id" = String, Path, description = "The ID of the asset"),
+    ),
+    responses(
+        (status = 200, description = "Asset details", body = AssetDetailResponse),
+        (status = 404, description = "Asset not found in the specified project", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn asset_detail(
+    State(state): State<AppState>,
+    Path((project_id, asset_id)): Path<(String, String)>,
+) -> Result<Json<AssetDetailResponse>, ApiErrorResponse> {
+    let asset = find_asset(&state, &project_id, &asset_id).await?;
+
+    let geometry = lima_db::queries::geometry::get_assets_geometry(state.db.pool(), std::slice::from_ref(&asset.id))
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading asset geometry").with_cause(&e.to_string()))?
+        .pop()
+        .map(|(_, geometry)| geometry);
+
+    Ok(Json(AssetDetailResponse {
+        thumbnails: asset.thumbnail_hash.map(|hash| ThumbnailUrls::new(&asset.id, &hash)),
+        download_url: format!("/api/projects/{}/assets/{}/download", asset.project_id, asset.id),
+        id: asset.id,
+        project_id: asset.project_id,
+        file_path: asset.file_path,
+        kind: asset.kind,
+        mime: asset.mime,
+        size_bytes: asset.size_bytes,
+        mtime: asset.mtime,
+        file_hash: asset.file_hash,
+        created_at: asset.created_at,
+        updated_at: asset.updated_at,
+        // A row that doesn't parse is read again on the next change of the file, not an error.
+        metadata: asset.metadata_json.and_then(|json| serde_json::from_str(&json).ok()),
+        geometry,
+    }))
+}
@@ -0,0 +1,161 @@
+use std::path::PathBuf;
+
+use axum::body::Body;
+use axum::extract::{Path, Request, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::find_asset;
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/assets/{asset_id}/download",
+    params(
+        ("project_id" = String, Path, description = "The ID of the project containing the asset"),
+        ("asset_id" = String, Path, description = "The ID of the asset"),
+        ("Range" = Option<String>, Header, description = "Single byte range, like `bytes=0-1023`. Other ranges are ignored"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+    ),
+    responses(
+        (status = 200, description = "The file, as an attachment"),
+        (status = 206, description = "The requested range of the file"),
+        (status = 304, description = "The cached copy is still current"),
+        (status = 404, description = "Asset not found, or its file is gone", body = ApiErrorBody),
+        (status = 416, description = "Range outside of the file"),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn asset_download(
+    State(state): State<AppState>,
+    Path((project_id, asset_id)): Path<(String, String)>,
+    mut request: Request,
+) -> Result<Response, ApiErrorResponse> {
+    let asset = find_asset(&state, &project_id, &asset_id).await?;
+
+    let path: PathBuf = ["data", "library", &asset.folder_path, &asset.file_path].iter().collect();
+    if !tokio::fs::metadata(&path).await.is_ok_and(|metadata| metadata.is_file()) {
+        return Err(ApiErrorResponse::new(StatusCode::NOT_FOUND, "asset_file_missing", "The file of the asset is gone").with_cause(&asset.file_path));
+    }
+
+    // The hash is the one of the last sync, an edit since then keeps the old ETag until the next one.
+    let etag = asset.file_hash.as_ref().and_then(|hash| HeaderValue::from_str(&format!("\"{}\"", hash)).ok());
+
+    if let Some(etag) = &etag {
+        if matches_etag(request.headers(), header::IF_NONE_MATCH, etag) {
+            return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag.clone())]).into_response());
+        }
+        // A range of another version of the file would corrupt the client's copy, send it whole.
+        if request.headers().contains_key(header::IF_RANGE) && !matches_etag(request.headers(), header::IF_RANGE, etag) {
+            request.headers_mut().remove(header::RANGE);
+        }
+    }
+
+    // RFC 9110: a Range that can't be parsed or asks for several parts is ignored, the file is sent whole.
+    let ranges = request.headers().get_all(header::RANGE).iter().count();
+    if ranges > 1 || request.headers().get(header::RANGE).is_some_and(|range| !is_single_byte_range(range)) {
+        request.headers_mut().remove(header::RANGE);
+    }
+
+    // ServeFile handles HEAD, Range (206 / 416) and streams the body.
+    let mut response = ServeFile::new(&path)
+        .oneshot(request)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, "asset_read_failed", "Failed reading the file of the asset").with_cause(&e.to_string()))?
+        .map(Body::new);
+
+    let satisfiable = response.status() != StatusCode::RANGE_NOT_SATISFIABLE;
+    let headers = response.headers_mut();
+    if let Some(etag) = etag {
+        headers.insert(header::ETAG, etag);
+    }
+    if let Ok(mime) = HeaderValue::from_str(&asset.mime)
+        && !asset.mime.is_empty()
+        && satisfiable
+    {
+        headers.insert(header::CONTENT_TYPE, mime);
+    }
+    let file_name = asset.file_path.rsplit('/').next().unwrap_or(&asset.file_path);
+    if let Ok(disposition) = HeaderValue::from_str(&content_disposition(file_name)) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+
+    Ok(response)
+}
+
+// `bytes=first-last`, `bytes=first-` or `bytes=-suffix`. Whether it fits the file is ServeFile's call.
+fn is_single_byte_range(value: &HeaderValue) -> bool {
+    let Some(spec) = value.to_str().ok().and_then(|value| value.trim().split_once('=')) else {
+        return false;
+    };
+    if !spec.0.trim().eq_ignore_ascii_case("bytes") {
+        return false;
+    }
+    let Some((first, last)) = spec.1.trim().split_once('-') else {
+        return false;
+    };
+
+    // Digits only, `parse` would take a sign.
+    let number = |n: &str| if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) { n.parse::<u64>().ok() } else { None };
+    match (number(first), number(last)) {
+        (Some(first), Some(last)) => first <= last,
+        (Some(_), None) => last.is_empty(),
+        (None, Some(_)) => first.is_empty(),
+        (None, None) => false,
+    }
+}
+
+fn matches_etag(headers: &HeaderMap, name: header::HeaderName, etag: &HeaderValue) -> bool {
+    let Some(value) = headers.get(name).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+
+    value.split(',').map(str::trim).any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+// RFC 6266: a plain ASCII `filename` for old clients and the exact name as UTF-8 in `filename*`.
+pub(crate) fn content_disposition(file_name: &str) -> String {
+    let fallback: String = file_name.chars()
+        .map(|c| if (c.is_ascii_graphic() || c == ' ') && c != '"' && c != '\\' { c } else { '_' })
+        .collect();
+
+    let mut encoded = String::new();
+    for byte in file_name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single(range: &str) -> bool {
+        is_single_byte_range(&HeaderValue::from_str(range).unwrap())
+    }
+
+    #[test]
+    fn single_ranges_are_kept() {
+        for range in ["bytes=0-1023", "bytes=100-", "bytes=-500", "Bytes=0-0", "bytes=5-5"] {
+            assert!(single(range), "{}", range);
+        }
+    }
+
+    #[test]
+    fn other_ranges_are_ignored() {
+        for range in ["bytes=abc", "bytes=0-1,5-6", "bytes=", "bytes=-", "bytes=5-2", "items=0-1", "0-1", "bytes=1-x", "bytes=+1-2", "bytes=99999999999999999999-"] {
+            assert!(!single(range), "{}", range);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use lima_db::queries::assets_list::{AssetFilter, AssetSort};
+use lima_db::queries::projects_detail::GetProjectError;
+use lima_domain::models::asset::AssetKind;
+use lima_domain::pagination::{decode_cursor, encode_cursor};
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::{AssetResponse, map_asset};
+
+#[derive(Deserialize, ToSchema)]
+pub struct ListAssetsParams {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub kind: Option<String>,
+    pub mime: Option<String>,
+    pub name: Option<String>,
+    /// `name` (default), `size` or `mtime`.
+    pub sort: Option<String>,
+    /// `asc` (default) or `desc`.
+    pub order: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListAssetsResponse {
+    pub items: Vec<AssetResponse>,
+    pub next_cursor: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/assets",
+    params(
+        ("project_id" = String, Path, description = "The ID of the project"),
+        ("limit" = Option<i64>, Query, description = "Maximum number of assets to return (default: 50, max: 200)"),
+        ("cursor" = Option<String>, Query, description = "Opaque pagination cursor, only valid with the same sort and order"),
+        ("kind" = Option<String>, Query, description = "Only assets of this kind, see /api/asset-kinds"),
+        ("mime" = Option<String>, Query, description = "Only assets of this MIME type"),
+        ("name" = Option<String>, Query, description = "Only assets whose path contains this text (case insensitive)"),
+        ("sort" = Option<String>, Query, description = "`name` (path, default), `size` or `mtime`"),
+        ("order" = Option<String>, Query, description = "`asc` (default) or `desc`"),
+    ),
+    responses(
+        (status = 200, description = "Page of the project's assets", body = ListAssetsResponse),
+        (status = 400, description = "Invalid parameter provided", body = ApiErrorBody),
+        (status = 404, description = "Project not found", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn list_project_assets(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(params): Query<ListAssetsParams>,
+) -> Result<Json<ListAssetsResponse>, ApiErrorResponse> {
+    match lima_db::queries::projects_detail::get_project(state.db.pool(), &project_id).await {
+        Ok(_) => {}
+        Err(GetProjectError::NotFound) => {
+            return Err(ApiErrorResponse::new(StatusCode::NOT_FOUND, "project_not_found", "Project not found"));
+        }
+        Err(e) => {
+            return Err(ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading project").with_cause(&e.to_string()));
+        }
+    }
+
+    list(&state, Some(&project_id), params).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/assets",
+    params(
+        ("limit" = Option<i64>, Query, description = "Maximum number of assets to return (default: 50, max: 200)"),
+        ("cursor" = Option<String>, Query, description = "Opaque pagination cursor, only valid with the same sort and order"),
+        ("kind" = Option<String>, Query, description = "Only assets of this kind, see /api/asset-kinds"),
+        ("mime" = Option<String>, Query, description = "Only assets of this MIME type"),
+        ("name" = Option<String>, Query, description = "Only assets whose path contains this text (case insensitive)"),
+        ("sort" = Option<String>, Query, description = "`name` (path, default), `size` or `mtime`"),
+        ("order" = Option<String>, Query, description = "`asc` (default) or `desc`"),
+    ),
+    responses(
+        (status = 200, description = "Page of the assets of every project", body = ListAssetsResponse),
+        (status = 400, description = "Invalid parameter provided", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn list_assets(
+    State(state): State<AppState>,
+    Query(params): Query<ListAssetsParams>,
+) -> Result<Json<ListAssetsResponse>, ApiErrorResponse> {
+    list(&state, None, params).await
+}
+
+async fn list(
+    state: &AppState,
+    project_id: Option<&str>,
+    params: ListAssetsParams,
+) -> Result<Json<ListAssetsResponse>, ApiErrorResponse> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+
+    let sort = match params.sort.as_deref() {
+        None => AssetSort::Name,
+        Some(value) => AssetSort::parse(value).ok_or_else(|| {
+            ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_sort", "sort must be name, size or mtime").with_cause(value)
+        })?,
+    };
+
+    let descending = match params.order.as_deref() {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => {
+            return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_order", "order must be asc or desc").with_cause(other));
+        }
+    };
+
+    if let Some(kind) = params.kind.as_deref()
+        && AssetKind::parse(kind).is_none()
+    {
+        return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_kind", "Unknown asset kind").with_cause(kind));
+    }
+
+    let cursor = match params.cursor {
+        Some(ref c) => Some(decode_cursor(c).map_err(|e| ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_cursor", "Invalid cursor parameter").with_cause(&e))?),
+        None => None,
+    };
+
+    if sort == AssetSort::Size && cursor.as_ref().is_some_and(|c| c.rank.is_none()) {
+        return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_cursor", "Invalid cursor parameter").with_cause("Cursor is missing the size"));
+    }
+
+    let filter = AssetFilter {
+        project_id,
+        kind: params.kind.as_deref(),
+        mime: params.mime.as_deref(),
+        name: params.name.as_deref().filter(|name| !name.is_empty()),
+    };
+
+    let rows = lima_db::queries::assets_list::list_assets(state.db.pool(), &filter, sort, descending, limit, cursor)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed listing assets").with_cause(&e.to_string()))?;
+
+    let ids: Vec<String> = rows.iter().map(|row| row.id.clone()).collect();
+    let mut geometry = lima_db::queries::geometry::get_assets_geometry(state.db.pool(), &ids)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading asset geometry").with_cause(&e.to_string()))?
+        .into_iter()
+        .collect();
+
+    let next_cursor = rows.last().map(|last| encode_cursor(&sort.cursor_after(last)));
+
+    Ok(Json(ListAssetsResponse {
+        items: rows.into_iter().map(|row| map_asset(row, &mut geometry)).collect(),
+        next_cursor,
+    }))
+}
@@ -1 +1,69 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use serde::Serialize;
+use utoipa::ToSchema;
+use lima_db::queries::assets::AssetDetailRow;
+use lima_db::queries::assets_list::AssetListRow;
+use lima_domain::models::asset_metadata::AssetMetadata;
+use lima_domain::models::geometry::AssetGeometry;
+use lima_domain::models::thumbnail::ThumbnailUrls;
+
+use crate::state::AppState;
+use crate::models::http_error::ApiErrorResponse;
+
 pub mod delete;
+pub mod detail;
+pub mod download;
+pub mod list;
+
+#[derive(Serialize, ToSchema)]
+pub struct AssetResponse {
+    pub id: String,
+    pub project_id: String,
+    pub file_path: String,
+    pub kind: String,
+    pub mime: String,
+    pub size_bytes: i64,
+    pub mtime: String,
+    /// Images and models with a preview have thumbnails, once they are generated.
+    pub thumbnails: Option<ThumbnailUrls>,
+    /// What was read from inside the file, only 3MF and G-code files have some.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<AssetMetadata>,
+    /// Bounding box, volume... of STL, OBJ, 3MF and glTF models, once the indexer measured them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geometry: Option<AssetGeometry>,
+}
+
+fn map_asset(row: AssetListRow, geometry: &mut HashMap<String, AssetGeometry>) -> AssetResponse {
+    AssetResponse {
+        thumbnails: row.thumbnail_hash.map(|hash| ThumbnailUrls::new(&row.id, &hash)),
+        geometry: geometry.remove(&row.id),
+        id: row.id,
+        project_id: row.project_id,
+        file_path: row.file_path,
+        kind: row.kind,
+        mime: row.mime,
+        size_bytes: row.size_bytes,
+        mtime: row.mtime,
+        // A row that doesn't parse is read again on the next change of the file, not an error.
+        metadata: row.metadata_json.and_then(|json| serde_json::from_str(&json).ok()),
+    }
+}
+
+async fn find_asset(state: &AppState, project_id: &str, asset_id: &str) -> Result<AssetDetailRow, ApiErrorResponse> {
+    match lima_db::queries::assets::get_asset(state.db.pool(), project_id, asset_id).await {
+        Ok(Some(asset)) => Ok(asset),
+        Ok(None) => Err(ApiErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            "asset_not_found",
+            "Asset not found in the specified project",
+        )),
+        Err(e) => Err(ApiErrorResponse::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "db_failure",
+            "DB failed reading asset",
+        ).with_cause(&e.to_string())),
+    }
+}
@@ -0,0 +1,180 @@
+use axum::{Json, extract::{Multipart, Path, State}, http::StatusCode};
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use crate::routes::bundle_create::{CreateBundleResponse, add_to_bundle_meta, find_bundle_folder, receive_bundle_files, remove_received_files};
+
+#[utoipa::path(
+    post,
+    path = "/api/bundles/{bundle_id}/files",
+    params(
+        ("bundle_id" = String, Path, description = "The ID of the bundle to add files to"),
+    ),
+    request_body(
+        content_type = "multipart/form-data",
+        description = "More files for the bundle, same fields as when creating it, `extract` included. Names already in the bundle are rejected."
+    ),
+    responses(
+        (status = 200, description = "Files added. Some files may have failed or be duplicates check payload", body = CreateBundleResponse),
+        (status = 400, description = "Errors on the received files, or every file is ignored", body = ApiErrorBody),
+        (status = 404, description = "Bundle not found", body = ApiErrorBody),
+        (status = 409, description = "Every file is already in the bundle", body = ApiErrorBody),
+        (status = 500, description = "Failed creating files or updating the bundle meta file", body = ApiErrorBody),
+    )
+)]
+pub async fn bundle_append(
+    State(state): State<AppState>,
+    Path(bundle_id): Path<String>,
+    mut multipart: Multipart,
+) -> Result<Json<CreateBundleResponse>, ApiErrorResponse> {
+    let bundle_folder = find_bundle_folder(&bundle_id).await?;
+
+    let received = receive_bundle_files(&state, &mut multipart, &bundle_folder).await?;
+
+    if received.files.is_empty() {
+        if !received.duplicate_files.is_empty() {
+            return Err(ApiErrorResponse::new(
+                StatusCode::CONFLICT,
+                "duplicate_files",
+                "Every file is already in the bundle"
+            ).with_details(serde_json::json!({ "duplicate_files": received.duplicate_files })));
+        }
+
+        return Err(ApiErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "no_valid_files",
+            "No valid files were uploaded in the bundle"
+        ));
+    }
+
+    let files: Vec<String> = received.files.iter().map(|f| f.name.clone()).collect();
+    // Files missing from meta.json wouldn't be imported, and would block sending them again.
+    if let Err(e) = add_to_bundle_meta(&bundle_folder, received.files).await {
+        tracing::error!("Failed updating the meta file for {} bundle: {}", bundle_id, e);
+        remove_received_files(&bundle_folder, &files).await;
+        return Err(ApiErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "meta_creation_failed",
+            "Failed updating the bundle meta file"
+        ).with_cause(&e.to_string()));
+    }
+
+    Ok(Json(CreateBundleResponse {
+        id: bundle_id,
+        files,
+        failed_files: received.failed_files,
+        ignored_files: received.ignored_files,
+        duplicate_files: received.duplicate_files,
+        extracted_archives: received.extracted_archives,
+        failed_extractions: received.failed_extractions,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use axum::body::{Body, Bytes};
+    use axum::extract::{FromRequest, Request};
+    use axum::response::IntoResponse;
+    use futures_util::StreamExt;
+    use lima_indexer::ignore_rules::IgnoreRules;
+    use lima_indexer::sync::{SyncConfig, SyncQueue};
+    use lima_indexer::thumbnails::ThumbnailStore;
+
+    use super::*;
+    use crate::routes::bundle_create::{create_bundle, read_bundle_meta};
+
+    const BOUNDARY: &str = "lima-test-boundary";
+
+    fn part(file_name: &str, content: &str) -> String {
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"files\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n{}\r\n",
+            BOUNDARY, file_name, content
+        )
+    }
+
+    // The chunks, then the connection drops once `drop_after` exists. The parser reads up to an
+    // error before giving the fields before it, so it only comes when the handler got that far.
+    async fn multipart(chunks: Vec<String>, drop_after: Option<PathBuf>) -> Multipart {
+        let mut items: Vec<Result<Bytes, std::io::Error>> = chunks.into_iter().map(|chunk| Ok(Bytes::from(chunk))).collect();
+        if drop_after.is_some() {
+            items.push(Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "connection reset")));
+        }
+        let stream = futures_util::stream::iter(items).then(move |item| {
+            let drop_after = drop_after.clone();
+            async move {
+                if let (Err(_), Some(path)) = (&item, drop_after) {
+                    while !path.exists() {
+                        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                    }
+                }
+                item
+            }
+        });
+        let request = Request::builder()
+            .header("content-type", format!("multipart/form-data; boundary={}", BOUNDARY))
+            .body(Body::from_stream(stream))
+            .unwrap();
+        Multipart::from_request(request, &()).await.unwrap()
+    }
+
+    // The handlers work under `data/` in the current folder, like the server.
+    async fn state(root: &std::path::Path) -> AppState {
+        std::fs::create_dir_all(root.join("data/state/bundles")).unwrap();
+        std::env::set_current_dir(root).unwrap();
+
+        let db = lima_db::Db::connect("sqlite:data/state/lima.db?mode=rwc").await.unwrap();
+        db.migrate().await.unwrap();
+        let ignore = IgnoreRules::new::<&str>(&[]);
+        let thumbnails = ThumbnailStore::new("data/state/thumbnails");
+        let sync = SyncQueue::start(db.pool().clone(), SyncConfig {
+            library_root: PathBuf::from("data/library"),
+            bundles_root: PathBuf::from("data/state/bundles"),
+            ignore: ignore.clone(),
+            thumbnails: thumbnails.clone(),
+        });
+
+        AppState { db: Arc::new(db), sync, ignore, thumbnails }
+    }
+
+    #[tokio::test]
+    async fn interrupted_append_can_be_sent_again() {
+        let root = std::env::temp_dir().join(format!("lima-bundle-append-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let state = state(&root).await;
+
+        let created = create_bundle(
+            State(state.clone()),
+            multipart(vec![part("base.stl", "solid base"), format!("--{}--\r\n", BOUNDARY)], None).await,
+        ).await.unwrap().1.0;
+        let bundle_folder: PathBuf = ["data", "state", "bundles", &created.id].iter().collect();
+
+        // `stls/lid.stl` arrives whole, the connection drops in the middle of `stls/hinge.stl`.
+        let hinge = part("stls/hinge.stl", "solid hinge");
+        let interrupted = bundle_append(
+            State(state.clone()),
+            Path(created.id.clone()),
+            multipart(vec![part("stls/lid.stl", "solid lid"), hinge[..hinge.len() - 8].to_string()], Some(bundle_folder.join("stls/hinge.stl"))).await,
+        ).await;
+        let status = interrupted.err().map(|e| e.into_response().status());
+        let left = std::fs::read_dir(&bundle_folder).unwrap().map(|e| e.unwrap().file_name()).collect::<Vec<_>>();
+
+        let retried = bundle_append(
+            State(state.clone()),
+            Path(created.id.clone()),
+            multipart(vec![part("stls/lid.stl", "solid lid"), hinge.clone(), format!("--{}--\r\n", BOUNDARY)], None).await,
+        ).await;
+        let meta = read_bundle_meta(&bundle_folder).await.unwrap().unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(status, Some(StatusCode::BAD_REQUEST));
+        assert_eq!(left.len(), 2, "only base.stl and meta.json stay: {:?}", left);
+        let retried = retried.unwrap().0;
+        assert_eq!(retried.files, ["stls/lid.stl", "stls/hinge.stl"]);
+        assert!(retried.duplicate_files.is_empty());
+        let names: Vec<&str> = meta.files.iter().map(|file| file.name.as_str()).collect();
+        assert_eq!(names, ["base.stl", "stls/lid.stl", "stls/hinge.stl"]);
+    }
+}
@@ -1,22 +1,47 @@
 use axum::{Json, extract::{Multipart, State}, http::StatusCode};
-use mime_guess::MimeGuess;
 use serde::{Serialize};
 use time::OffsetDateTime;
 use uuid::Uuid;
-use std::{path::PathBuf};
-use tokio::{fs, io::AsyncWriteExt};
+use std::path::{Path, PathBuf};
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
 use utoipa::ToSchema;
 use std::io::{Error, ErrorKind};
-use lima_domain::models::bundle::{BundleMeta, FileMeta};
+use lima_domain::models::bundle::{BUNDLE_META_FILE, BundleMeta, FileMeta};
+use lima_domain::models::asset::{AssetKind, asset_directory, guess_mime, sanitize_asset_path};
+use lima_indexer::extract::{ArchiveFormat, SkipReason, extract_archive};
+use lima_indexer::hash::hash_file;
+use lima_indexer::ignore_rules::IgnoreRules;
 
 use crate::state::AppState;
 use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
 
+// Uploads finishing together in one bundle would otherwise drop each other's entries.
+static BUNDLE_META_LOCK: Mutex<()> = Mutex::const_new(());
+
 #[derive(Serialize, ToSchema)]
 pub struct CreateBundleResponse {
     pub id: String,
     pub files: Vec<String>,
     pub failed_files: Vec<String>,
+    /// Files matching the ignore patterns, they are not stored.
+    pub ignored_files: Vec<String>,
+    /// Names already in the bundle or sent twice, only the first file is kept.
+    pub duplicate_files: Vec<String>,
+    /// Archives replaced by their content, with `extract=true`.
+    pub extracted_archives: Vec<String>,
+    /// Archives that couldn't be extracted (corrupt, encrypted or too big once expanded). They are
+    /// stored as they are.
+    pub failed_extractions: Vec<String>,
+}
+
+/// What a multipart upload left in the bundle folder.
+pub(crate) struct ReceivedFiles {
+    pub files: Vec<FileMeta>,
+    pub failed_files: Vec<String>,
+    pub ignored_files: Vec<String>,
+    pub duplicate_files: Vec<String>,
+    pub extracted_archives: Vec<String>,
+    pub failed_extractions: Vec<String>,
 }
 
 #[utoipa::path(
@@ -24,23 +49,21 @@ pub struct CreateBundleResponse {
     path = "/api/bundles",
     request_body(
         content_type = "multipart/form-data",
-        description = "Set of files to upload using files or files[] fields."
+        description = "Set of files to upload using files or files[] fields. File names may be paths relative to the project folder, like `stls/base.stl`. With an `extract` field set to `true`, ZIP, 7z, tar and tar.gz archives are replaced by the files they contain."
     ),
     responses(
         (status = 201, description = "Bundle created. Some files may have failed check payload", body = CreateBundleResponse),
-        (status = 400, description = "Errors on the received files", body = ApiErrorBody),
+        (status = 400, description = "Errors on the received files, or every file is ignored", body = ApiErrorBody),
         (status = 500, description = "Failed creating files or folders", body = ApiErrorBody),
     )
 )]
 pub async fn create_bundle(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     mut multipart: Multipart,
 ) -> Result<(StatusCode, Json<CreateBundleResponse>), ApiErrorResponse> {
     // TODO: move logic to db module
     let bundle_id = Uuid::new_v4().to_string();
     let bundle_folder: PathBuf = ["data", "state", "bundles", &bundle_id].iter().collect();
-    let mut failed_files: Vec<String> = Vec::new();
-    let mut files_metadata: Vec<FileMeta> = Vec::new();
 
     fs::create_dir_all(&bundle_folder).await.map_err(|e| {
         tracing::error!("Failed to create bundle directory: {}", e);
@@ -51,6 +74,114 @@ pub async fn create_bundle(
         ).with_cause(&e.to_string())
     })?;
 
+    let received = match receive_bundle_files(&state, &mut multipart, &bundle_folder).await {
+        Ok(received) => received,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&bundle_folder).await;
+            return Err(e);
+        }
+    };
+    let files_metadata = received.files;
+
+    if files_metadata.is_empty() {
+        let _ = fs::remove_dir_all(&bundle_folder).await;
+
+        return Err(ApiErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "no_valid_files",
+            "No valid files were uploaded in the bundle"
+        ));
+    }
+
+    write_bundle_meta(
+        &bundle_folder,
+        files_metadata.clone(),
+    ).await.map_err(|e| {
+        tracing::error!("Failed creating the meta file for {} bundle", bundle_id);
+        ApiErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "meta_creation_failed",
+            "Failed creating the bundle meta file"
+        ).with_cause(&e.to_string())
+    })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateBundleResponse {
+            id: bundle_id,
+            files: files_metadata.iter().map(|f| f.name.clone()).collect(),
+            failed_files: received.failed_files,
+            ignored_files: received.ignored_files,
+            duplicate_files: received.duplicate_files,
+            extracted_archives: received.extracted_archives,
+            failed_extractions: received.failed_extractions,
+        }),
+    ))
+}
+
+/// Stores the `files` / `files[]` fields of `multipart` in `bundle_folder`, which must exist, and
+/// expands the archives among them when the `extract` field is `true`. Files that can't be stored
+/// are reported, only a broken multipart body fails the request. It then leaves nothing behind, so
+/// the same files can be sent again.
+pub(crate) async fn receive_bundle_files(
+    state: &AppState,
+    multipart: &mut Multipart,
+    bundle_folder: &Path,
+) -> Result<ReceivedFiles, ApiErrorResponse> {
+    let mut received = ReceivedFiles {
+        files: Vec::new(),
+        failed_files: Vec::new(),
+        ignored_files: Vec::new(),
+        duplicate_files: Vec::new(),
+        extracted_archives: Vec::new(),
+        failed_extractions: Vec::new(),
+    };
+    let mut extract = false;
+
+    if let Err(e) = receive_fields(state, multipart, bundle_folder, &mut received, &mut extract).await {
+        let names: Vec<String> = received.files.iter().map(|file| file.name.clone()).collect();
+        remove_received_files(bundle_folder, &names).await;
+        return Err(e);
+    }
+
+    if extract {
+        expand_archives(state, bundle_folder, &mut received).await;
+    }
+
+    Ok(received)
+}
+
+/// Removes files stored by a request that then failed.
+pub(crate) async fn remove_received_files(bundle_folder: &Path, names: &[String]) {
+    for name in names {
+        remove_stored_file(bundle_folder, name).await;
+    }
+}
+
+// With the folders that only held it, up to the first one that still has something in it.
+async fn remove_stored_file(bundle_folder: &Path, name: &str) {
+    let path = bundle_folder.join(name);
+    if let Err(e) = fs::remove_file(&path).await {
+        tracing::warn!("Failed removing {} after a failed upload: {}", path.display(), e);
+        return;
+    }
+
+    let mut dir = path.parent();
+    while let Some(parent) = dir.filter(|parent| *parent != bundle_folder) {
+        if fs::remove_dir(parent).await.is_err() {
+            break;
+        }
+        dir = parent.parent();
+    }
+}
+
+async fn receive_fields(
+    state: &AppState,
+    multipart: &mut Multipart,
+    bundle_folder: &Path,
+    received: &mut ReceivedFiles,
+    extract: &mut bool,
+) -> Result<(), ApiErrorResponse> {
     while let Some(field) = multipart.next_field().await.map_err(|e| {
         tracing::error!("bundle multipart next_field error: {e}");
         ApiErrorResponse::new(
@@ -60,6 +191,19 @@ pub async fn create_bundle(
         ).with_cause(&e.to_string())
     })? {
         let field_name = field.name().unwrap_or("").to_string();
+        if field_name == "extract" {
+            // Anywhere in the form, archives are only expanded once everything arrived.
+            *extract = match field.text().await.as_deref().map(str::trim) {
+                Ok("true") => true,
+                Ok("false") => false,
+                _ => return Err(ApiErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    "invalid_extract",
+                    "extract must be true or false"
+                )),
+            };
+            continue;
+        }
         if field_name != "files" && field_name != "files[]" {
             tracing::debug!("Skipping unexpected field: {}", field_name);
             continue;
@@ -71,18 +215,38 @@ pub async fn create_bundle(
                 Some(name ) => name,
                 None => {
                     tracing::error!("Invalid filename in uploaded bundle: {}", raw_name);
-                    failed_files.push(raw_name);
+                    received.failed_files.push(raw_name);
                     continue;
                 }
             };
 
-        
+        // Same rules as the library scan, otherwise the next sync would drop the file again.
+        if is_ignored_upload(&state.ignore, &file_name) {
+            tracing::debug!("Skipping ignored file in uploaded bundle: {}", file_name);
+            received.ignored_files.push(file_name);
+            continue;
+        }
+
         let file_destination = bundle_folder.join(&file_name);
-        let mut file = match fs::File::create(&file_destination).await {
+        if let Some(parent) = file_destination.parent()
+            && let Err(e) = fs::create_dir_all(parent).await
+        {
+            tracing::error!("Failed to create directory {}: {}", parent.display(), e);
+            received.failed_files.push(file_name);
+            continue;
+        }
+
+        // Never replaces a file, whether it came with this request or an earlier one.
+        let mut file = match fs::OpenOptions::new().write(true).create_new(true).open(&file_destination).await {
             Ok(f) => f,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                tracing::debug!("Skipping duplicate file in uploaded bundle: {}", file_name);
+                received.duplicate_files.push(file_name);
+                continue;
+            }
             Err(e) => {
                 tracing::error!("Failed to create file {}: {}", file_destination.display(), e);
-                failed_files.push(file_name);
+                received.failed_files.push(file_name);
                 continue;
             }
         };
@@ -96,64 +260,102 @@ pub async fn create_bundle(
                     size += chunk.len() as i64;
                     if let Err(e) = file.write_all(&chunk).await {
                         tracing::error!("Failed to write to file {}: {}", file_destination.display(), e);
-                        failed_files.push(file_name);
-                        let _ = fs::remove_file(&file_destination).await;
+                        remove_stored_file(bundle_folder, &file_name).await;
+                        received.failed_files.push(file_name);
                         break;
                     }
                 }
                 Ok(None) => {
                     tracing::debug!("Uploaded file: {}, dst: {}, size: {}", file_name, file_destination.display(), size);
 
-                    files_metadata.push(FileMeta {
+                    received.files.push(FileMeta {
                         name: file_name.clone(),
                         size,
                         mtime: extract_mtime(&file).await.ok(),
                         mime: guess_mime(&file_name),
-                        kind: extract_kind(&file_name).to_string(),
-                        checksum: compute_checksum(&file_destination).await.ok(),
+                        kind: AssetKind::from_path(&file_name).as_str().to_string(),
+                        checksum: hash_file(&file_destination).await.ok(),
                     });
                     break;
                 },
                 Err(e) => {
                     tracing::error!("error reading chunk for file {}: {}", file_name, e);
-                    failed_files.push(file_name);
-                    let _ = fs::remove_file(&file_destination).await;
+                    remove_stored_file(bundle_folder, &file_name).await;
+                    received.failed_files.push(file_name);
                     break;
                 }
             }
         }
     }
 
-    if files_metadata.is_empty() {
-        let _ = fs::remove_dir_all(&bundle_folder).await;
+    Ok(())
+}
 
-        return Err(ApiErrorResponse::new(
-            StatusCode::BAD_REQUEST,
-            "no_valid_files",
-            "No valid files were uploaded in the bundle"
-        ));
-    }
+/// Dotfiles and the global ignore patterns.
+pub(crate) fn is_ignored_upload(ignore: &IgnoreRules, name: &str) -> bool {
+    name.split('/').any(|segment| segment.starts_with('.')) || ignore.is_ignored_file_name(name)
+}
 
-    write_bundle_meta(
-        &bundle_folder,
-        files_metadata.clone(),
-    ).await.map_err(|e| {
-        tracing::error!("Failed creating the meta file for {} bundle", bundle_id);
-        ApiErrorResponse::new(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "meta_creation_failed",
-            "Failed creating the bundle meta file"
-        ).with_cause(&e.to_string())
-    })?;
+// Files of an archive go next to it, in the folders of the archive, and the archive itself goes away.
+async fn expand_archives(state: &AppState, bundle_folder: &Path, received: &mut ReceivedFiles) {
+    let mut files = Vec::with_capacity(received.files.len());
 
-    Ok((
-        StatusCode::CREATED,
-        Json(CreateBundleResponse {
-            id: bundle_id,
-            files: files_metadata.iter().map(|f| f.name.clone()).collect(),
-            failed_files: failed_files,
-        }),
-    ))
+    for file in std::mem::take(&mut received.files) {
+        let Some(format) = ArchiveFormat::from_path(&file.name) else {
+            files.push(file);
+            continue;
+        };
+
+        let archive = bundle_folder.join(&file.name);
+        let root = bundle_folder.to_path_buf();
+        let prefix = asset_directory(&file.name).to_string();
+        let ignore = state.ignore.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            extract_archive(&archive, format, &root, &prefix, &|name| is_ignored_upload(&ignore, name))
+        }).await;
+
+        let extraction = match result {
+            Ok(Ok(extraction)) => extraction,
+            Ok(Err(e)) => {
+                tracing::warn!("Failed extracting {} in uploaded bundle: {}", file.name, e);
+                received.failed_extractions.push(file.name.clone());
+                files.push(file);
+                continue;
+            }
+            Err(e) => {
+                tracing::error!("Extraction of {} panicked: {}", file.name, e);
+                received.failed_extractions.push(file.name.clone());
+                files.push(file);
+                continue;
+            }
+        };
+
+        for name in extraction.files {
+            match read_file_meta(bundle_folder, &name).await {
+                Ok(file_meta) => files.push(file_meta),
+                Err(e) => {
+                    tracing::error!("Failed reading extracted file {}: {}", name, e);
+                    let _ = fs::remove_file(bundle_folder.join(&name)).await;
+                    received.failed_files.push(name);
+                }
+            }
+        }
+        for (name, reason) in extraction.skipped {
+            match reason {
+                SkipReason::Ignored => received.ignored_files.push(name),
+                SkipReason::Duplicate => received.duplicate_files.push(name),
+                SkipReason::UnsafePath => received.failed_files.push(name),
+            }
+        }
+
+        if let Err(e) = fs::remove_file(bundle_folder.join(&file.name)).await {
+            tracing::warn!("Failed removing extracted archive {}: {}", file.name, e);
+        }
+        tracing::debug!("Extracted {} in uploaded bundle", file.name);
+        received.extracted_archives.push(file.name);
+    }
+
+    received.files = files;
 }
 
 fn sanitize_filename(name: &str) -> Option<String> {
@@ -162,12 +364,13 @@ fn sanitize_filename(name: &str) -> Option<String> {
         return None;
     }
 
-    if name.contains("..") || name.contains('/') || name.contains('\\') || name.contains('\0') {
-        tracing::error!("file name contains invalid characters: {}", name);
-        return None;
+    // Nested paths are fine as long as they stay inside the bundle. meta.json at the root is ours.
+    let sanitized = sanitize_asset_path(name).filter(|name| name != BUNDLE_META_FILE);
+    if sanitized.is_none() {
+        tracing::error!("file name contains invalid characters or path segments: {}", name);
     }
 
-    Some(name.to_string())
+    sanitized
 }
 
 pub async fn write_bundle_meta(
@@ -182,7 +385,7 @@ pub async fn write_bundle_meta(
     };
 
 
-    let meta_path = bundle_dir.join("meta.json");
+    let meta_path = bundle_dir.join(BUNDLE_META_FILE);
     let json = serde_json::to_vec_pretty(&bundle_meta)
         .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
 
@@ -194,22 +397,56 @@ pub async fn write_bundle_meta(
     Ok(())
 }
 
-// TODO: move to models and enum so we keep track of the support we provide.
-fn extract_kind(filename: &str) -> &'static str {
-    let lower = filename.to_ascii_lowercase();
-    if lower.ends_with(".png") || lower.ends_with(".jpg") || lower.ends_with(".jpeg") || lower.ends_with(".webp") {
-        "image"
-    } else if lower.ends_with(".stl") || lower.ends_with(".obj") || lower.ends_with(".3mf") || lower.ends_with(".fbx") || lower.ends_with(".glb") || lower.ends_with(".gltf") {
-        "model"
-    } else {
-        "other"
+/// `None` until the first file of the bundle is stored.
+pub async fn read_bundle_meta(bundle_dir: &Path) -> Result<Option<BundleMeta>, Error> {
+    match fs::read(bundle_dir.join(BUNDLE_META_FILE)).await {
+        Ok(json) => serde_json::from_slice(&json)
+            .map(Some)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Folder of an existing bundle. IDs come from URLs, anything but a UUID would be a path.
+pub async fn find_bundle_folder(bundle_id: &str) -> Result<PathBuf, ApiErrorResponse> {
+    let bundle_folder: PathBuf = ["data", "state", "bundles", bundle_id].iter().collect();
+    let exists = Uuid::parse_str(bundle_id).is_ok() && fs::metadata(&bundle_folder).await.is_ok_and(|m| m.is_dir());
+    if !exists {
+        return Err(ApiErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            "bundle_not_found",
+            "The specified bundle does not exist",
+        ));
     }
+
+    Ok(bundle_folder)
 }
 
-fn guess_mime(path: &str) -> String {
-    MimeGuess::from_path(path)
-    .first_or_octet_stream()
-    .to_string()
+/// Adds `files` to the meta file of an existing bundle. Files of a bundle are never replaced, these
+/// were just stored under names the bundle didn't have.
+pub async fn add_to_bundle_meta(bundle_dir: &PathBuf, files_meta: Vec<FileMeta>) -> Result<(), Error> {
+    let _guard = BUNDLE_META_LOCK.lock().await;
+
+    let mut files = read_bundle_meta(bundle_dir).await?.map(|meta| meta.files).unwrap_or_default();
+    files.extend(files_meta);
+
+    write_bundle_meta(bundle_dir, files).await
+}
+
+/// Describes `name`, already stored in the bundle. Reads the whole file for the checksum.
+pub async fn read_file_meta(bundle_dir: &Path, name: &str) -> Result<FileMeta, Error> {
+    let path = bundle_dir.join(name);
+    let file = fs::File::open(&path).await?;
+
+    Ok(FileMeta {
+        name: name.to_string(),
+        size: file.metadata().await?.len() as i64,
+        mtime: extract_mtime(&file).await.ok(),
+        mime: guess_mime(name),
+        kind: AssetKind::from_path(name).as_str().to_string(),
+        checksum: Some(hash_file(&path).await?),
+    })
 }
 
 async fn extract_mtime(file: &fs::File) -> Result<String, Error> {
@@ -221,22 +458,3 @@ async fn extract_mtime(file: &fs::File) -> Result<String, Error> {
             Error::new(ErrorKind::Other, format!("Failed to format mtime: {}", e))
         })
 }
-
-async fn compute_checksum(path: &PathBuf) -> Result<String, Error> {
-    use tokio::{fs::File, io::AsyncReadExt};
-    use sha2::{Sha256, Digest};
-
-    let mut hasher = Sha256::new();
-    let mut reader = File::open(path).await?;
-    let mut buffer = [0u8; 8192];
-
-    loop {
-        let n = reader.read(&mut buffer).await?;
-        if n == 0 {
-            break;
-        }
-        hasher.update(&buffer[..n]);
-    }
-
-    Ok(format!("{:x}", hasher.finalize()))
-}
\ No newline at end of file
@@ -0,0 +1,71 @@
+use std::path::Path as FsPath;
+use std::time::SystemTime;
+
+use axum::{Json, extract::{Path, State}, http::StatusCode};
+use serde::Serialize;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+use utoipa::ToSchema;
+use lima_domain::models::bundle::FileMeta;
+use lima_indexer::sync::bundle_last_modified;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use crate::routes::bundle_create::{find_bundle_folder, read_bundle_meta};
+
+#[derive(Serialize, ToSchema)]
+pub struct BundleDetailResponse {
+    pub id: String,
+    /// When files were last added. Missing until a first file is stored.
+    pub uploaded_at: Option<String>,
+    /// Last write in the bundle. `bundle_gc` removes bundles by the time since then.
+    pub last_modified: String,
+    pub age_secs: u64,
+    pub total_size: i64,
+    pub files: Vec<FileMeta>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/bundles/{bundle_id}",
+    params(
+        ("bundle_id" = String, Path, description = "The ID of the bundle to inspect"),
+    ),
+    responses(
+        (status = 200, description = "Bundle meta and its files", body = BundleDetailResponse),
+        (status = 404, description = "Bundle not found", body = ApiErrorBody),
+        (status = 500, description = "Failed reading the bundle", body = ApiErrorBody),
+    )
+)]
+pub async fn bundle_detail(
+    State(_state): State<AppState>,
+    Path(bundle_id): Path<String>,
+) -> Result<Json<BundleDetailResponse>, ApiErrorResponse> {
+    let bundle_folder = find_bundle_folder(&bundle_id).await?;
+
+    read_bundle(bundle_id, &bundle_folder, SystemTime::now()).await.map(Json).map_err(|e| {
+        tracing::error!("Failed reading bundle {}: {}", bundle_folder.display(), e);
+        ApiErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "bundle_read_failed",
+            "Failed reading the bundle"
+        ).with_cause(&e.to_string())
+    })
+}
+
+pub(crate) async fn read_bundle(id: String, bundle_folder: &FsPath, now: SystemTime) -> Result<BundleDetailResponse, std::io::Error> {
+    let meta = read_bundle_meta(bundle_folder).await?;
+    let last_modified = bundle_last_modified(bundle_folder).await?;
+    let (uploaded_at, files) = match meta {
+        Some(meta) => (Some(meta.uploaded_at), meta.files),
+        None => (None, Vec::new()),
+    };
+
+    Ok(BundleDetailResponse {
+        id,
+        uploaded_at,
+        last_modified: OffsetDateTime::from(last_modified).format(&Rfc3339).map_err(std::io::Error::other)?,
+        age_secs: now.duration_since(last_modified).unwrap_or_default().as_secs(),
+        total_size: files.iter().map(|file| file.size).sum(),
+        files,
+    })
+}
@@ -0,0 +1,84 @@
+use std::time::SystemTime;
+
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Serialize;
+use tokio::fs;
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use crate::routes::bundle_detail::read_bundle;
+
+#[derive(Serialize, ToSchema)]
+pub struct BundleSummaryResponse {
+    pub id: String,
+    pub uploaded_at: Option<String>,
+    pub last_modified: String,
+    pub age_secs: u64,
+    pub file_count: usize,
+    pub total_size: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListBundlesResponse {
+    pub items: Vec<BundleSummaryResponse>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/bundles",
+    responses(
+        (status = 200, description = "Staged bundles, most recently modified first", body = ListBundlesResponse),
+        (status = 500, description = "Failed reading the bundles folder", body = ApiErrorBody),
+    )
+)]
+pub async fn list_bundles(
+    State(_state): State<AppState>,
+) -> Result<Json<ListBundlesResponse>, ApiErrorResponse> {
+    let read_failed = |e: std::io::Error| {
+        tracing::error!("Failed listing bundles: {}", e);
+        ApiErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "bundle_read_failed",
+            "Failed reading the bundles folder"
+        ).with_cause(&e.to_string())
+    };
+
+    let mut entries = match fs::read_dir(["data", "state", "bundles"].iter().collect::<std::path::PathBuf>()).await {
+        Ok(entries) => entries,
+        // Nothing was ever uploaded.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Json(ListBundlesResponse { items: Vec::new() })),
+        Err(e) => return Err(read_failed(e)),
+    };
+
+    let now = SystemTime::now();
+    let mut items = Vec::new();
+    while let Some(entry) = entries.next_entry().await.map_err(read_failed)? {
+        if !entry.file_type().await.is_ok_and(|file_type| file_type.is_dir()) {
+            continue;
+        }
+
+        let id = entry.file_name().to_string_lossy().into_owned();
+        // Imported or collected while being listed, or a broken meta file. Still worth listing the rest.
+        let bundle = match read_bundle(id, &entry.path(), now).await {
+            Ok(bundle) => bundle,
+            Err(e) => {
+                tracing::warn!("Skipping bundle {} in list: {}", entry.path().display(), e);
+                continue;
+            }
+        };
+
+        items.push(BundleSummaryResponse {
+            id: bundle.id,
+            uploaded_at: bundle.uploaded_at,
+            last_modified: bundle.last_modified,
+            age_secs: bundle.age_secs,
+            file_count: bundle.files.len(),
+            total_size: bundle.total_size,
+        });
+    }
+
+    items.sort_by(|a, b| a.age_secs.cmp(&b.age_secs).then_with(|| a.id.cmp(&b.id)));
+
+    Ok(Json(ListBundlesResponse { items }))
+}
@@ -5,9 +5,17 @@ pub mod project_delete;
 pub mod project_detail;
 pub mod project_update;
 pub mod project_import;
+pub mod project_archive;
 
 pub mod assets;
+pub mod asset_kinds;
 pub mod tags;
+pub mod printers;
+pub mod sync;
 
 pub mod bundle_create;
+pub mod bundle_append;
+pub mod bundle_detail;
+pub mod bundle_list;
 pub mod bundle_delete;
+pub mod uploads;
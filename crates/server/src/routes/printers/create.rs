@@ -0,0 +1,53 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use utoipa::ToSchema;
+use lima_domain::models::printer::BuildVolume;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::{PrinterResponse, save_error, validate_printer};
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreatePrinterRequest {
+    pub name: String,
+    pub build_volume: BuildVolume,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/printers",
+    request_body = CreatePrinterRequest,
+    responses(
+        (status = 201, description = "Printer created successfully", body = PrinterResponse),
+        (status = 400, description = "Invalid name or build volume", body = ApiErrorBody),
+        (status = 409, description = "Printer with same name already exists", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn create_printer(
+    State(state): State<AppState>,
+    Json(payload): Json<CreatePrinterRequest>,
+) -> Result<(StatusCode, Json<PrinterResponse>), ApiErrorResponse> {
+    validate_printer(Some(&payload.name), Some(&payload.build_volume))?;
+
+    let now = OffsetDateTime::now_utc().format(&Rfc3339).map_err(|e| {
+        ApiErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "time_format_error",
+            "Failed to format current time",
+        ).with_cause(&e.to_string())
+    })?;
+
+    let printer = lima_db::queries::printers::create_printer(
+        state.db.pool(),
+        payload.name.trim(),
+        &payload.build_volume,
+        &now,
+    )
+    .await
+    .map_err(save_error)?;
+
+    Ok((StatusCode::CREATED, Json(printer.into())))
+}
@@ -0,0 +1,35 @@
+use axum::{extract::{Path, State}, http::StatusCode};
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+
+#[utoipa::path(
+    delete,
+    path = "/api/printers/{printer_id}",
+    params(
+        ("printer_id" = String, Path, description = "The ID of the printer to delete"),
+    ),
+    responses(
+        (status = 200, description = "Printer deleted successfully"),
+        (status = 404, description = "Printer not found", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn printer_delete(
+    State(state): State<AppState>,
+    Path(printer_id): Path<String>,
+) -> Result<StatusCode, ApiErrorResponse> {
+    match lima_db::queries::printers::delete_printer(state.db.pool(), &printer_id).await {
+        Ok(true) => Ok(StatusCode::OK),
+        Ok(false) => Err(ApiErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            "printer_not_found",
+            "Printer not found",
+        )),
+        Err(e) => Err(ApiErrorResponse::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "db_failure",
+            "DB failed deleting printer",
+        ).with_cause(&e.to_string())),
+    }
+}
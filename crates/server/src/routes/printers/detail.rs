@@ -0,0 +1,36 @@
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::PrinterResponse;
+
+#[utoipa::path(
+    get,
+    path = "/api/printers/{printer_id}",
+    params(
+        ("printer_id" = String, Path, description = "The ID of the printer"),
+    ),
+    responses(
+        (status = 200, description = "Printer details", body = PrinterResponse),
+        (status = 404, description = "Printer not found", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn printer_detail(
+    State(state): State<AppState>,
+    Path(printer_id): Path<String>,
+) -> Result<Json<PrinterResponse>, ApiErrorResponse> {
+    match lima_db::queries::printers::get_printer(state.db.pool(), &printer_id).await {
+        Ok(Some(printer)) => Ok(Json(printer.into())),
+        Ok(None) => Err(ApiErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            "printer_not_found",
+            "Printer not found",
+        )),
+        Err(e) => Err(ApiErrorResponse::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "db_failure",
+            "DB failed reading printer",
+        ).with_cause(&e.to_string())),
+    }
+}
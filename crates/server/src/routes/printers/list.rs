@@ -0,0 +1,32 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::PrinterResponse;
+
+#[derive(Serialize, ToSchema)]
+pub struct ListPrintersResponse {
+    pub items: Vec<PrinterResponse>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/printers",
+    responses(
+        (status = 200, description = "List of printers, by name", body = ListPrintersResponse),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn list_printers(
+    State(state): State<AppState>,
+) -> Result<Json<ListPrintersResponse>, ApiErrorResponse> {
+    let printers = lima_db::queries::printers::list_printers(state.db.pool())
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed listing printers").with_cause(&e.to_string()))?;
+
+    Ok(Json(ListPrintersResponse {
+        items: printers.into_iter().map(PrinterResponse::from).collect(),
+    }))
+}
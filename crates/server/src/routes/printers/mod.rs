@@ -0,0 +1,53 @@
+use axum::http::StatusCode;
+use serde::Serialize;
+use utoipa::ToSchema;
+use lima_db::queries::printers::PrinterRow;
+use lima_domain::models::printer::BuildVolume;
+
+use crate::models::http_error::ApiErrorResponse;
+
+pub mod list;
+pub mod create;
+pub mod detail;
+pub mod update;
+pub mod delete;
+
+#[derive(Serialize, ToSchema)]
+pub struct PrinterResponse {
+    pub id: String,
+    pub name: String,
+    pub build_volume: BuildVolume,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<PrinterRow> for PrinterResponse {
+    fn from(row: PrinterRow) -> Self {
+        Self {
+            build_volume: row.build_volume(),
+            id: row.id,
+            name: row.name,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+fn validate_printer(name: Option<&str>, volume: Option<&BuildVolume>) -> Result<(), ApiErrorResponse> {
+    if name.is_some_and(|name| name.trim().is_empty()) {
+        return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_printer", "Printer name can't be empty"));
+    }
+    if volume.is_some_and(|volume| !volume.is_valid()) {
+        return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_printer", "Build volume sizes must be positive millimetres"));
+    }
+    Ok(())
+}
+
+fn save_error(e: impl std::fmt::Display) -> ApiErrorResponse {
+    let msg = e.to_string();
+    if msg.contains("UNIQUE") || msg.contains("unique") {
+        ApiErrorResponse::new(StatusCode::CONFLICT, "existing_printer", "Printer with same name already exists").with_cause(&msg)
+    } else {
+        ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed saving printer").with_cause(&msg)
+    }
+}
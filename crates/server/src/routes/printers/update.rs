@@ -0,0 +1,65 @@
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use serde::Deserialize;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+use utoipa::ToSchema;
+use lima_domain::models::printer::BuildVolume;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::{PrinterResponse, save_error, validate_printer};
+
+#[derive(Deserialize, ToSchema)]
+pub struct PatchPrinterRequest {
+    pub name: Option<String>,
+    pub build_volume: Option<BuildVolume>,
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/printers/{printer_id}",
+    request_body = PatchPrinterRequest,
+    params(
+        ("printer_id" = String, Path, description = "The ID of the printer to update"),
+    ),
+    responses(
+        (status = 200, description = "Printer updated successfully", body = PrinterResponse),
+        (status = 400, description = "Invalid name or build volume", body = ApiErrorBody),
+        (status = 404, description = "Printer not found", body = ApiErrorBody),
+        (status = 409, description = "Printer with same name already exists", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn printer_update(
+    State(state): State<AppState>,
+    Path(printer_id): Path<String>,
+    Json(payload): Json<PatchPrinterRequest>,
+) -> Result<Json<PrinterResponse>, ApiErrorResponse> {
+    if payload.name.is_none() && payload.build_volume.is_none() {
+        return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "missing_fields", "At least one field must be provided for update."));
+    }
+    validate_printer(payload.name.as_deref(), payload.build_volume.as_ref())?;
+
+    let now = OffsetDateTime::now_utc().format(&Rfc3339).map_err(|e| {
+        ApiErrorResponse::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "time_format_error",
+            "Failed to format current time",
+        ).with_cause(&e.to_string())
+    })?;
+
+    let printer = lima_db::queries::printers::update_printer(
+        state.db.pool(),
+        &printer_id,
+        payload.name.as_deref().map(str::trim),
+        payload.build_volume.as_ref(),
+        &now,
+    )
+    .await
+    .map_err(save_error)?;
+
+    match printer {
+        Some(printer) => Ok(Json(printer.into())),
+        None => Err(ApiErrorResponse::new(StatusCode::NOT_FOUND, "printer_not_found", "Printer not found")),
+    }
+}
@@ -14,6 +14,7 @@ pub struct ListProjectsParams {
     pub limit: Option<i64>,
     pub cursor: Option<String>,
     pub query: Option<String>,
+    pub fits_printer: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -30,6 +31,7 @@ pub struct ListProjectsResponse {
         ("limit" = Option<i64>, Query, description = "Maximum number of projects to return (default: 50, max: 200)"),
         ("cursor" = Option<String>, Query, description = "Opaque pagination cursor"),
         ("query" = Option<String>, Query, description = "Search query to filter projects"),
+        ("fits_printer" = Option<String>, Query, description = "Printer ID, only projects with a model that fits its build volume (footprint may be turned 90°)"),
     ),
     responses(
         (status = 200, description = "List of projects", body = ListProjectsResponse),
@@ -48,6 +50,17 @@ pub async fn list_projects(
         None => None,
     };
 
+    let fits = match params.fits_printer.as_deref() {
+        Some(printer_id) => {
+            let printer = lima_db::queries::printers::get_printer(state.db.pool(), printer_id)
+                .await
+                .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading printer").with_cause(&e.to_string()))?
+                .ok_or_else(|| ApiErrorResponse::new(StatusCode::BAD_REQUEST, "unknown_printer", "fits_printer is not a known printer"))?;
+            Some(printer.build_volume())
+        }
+        None => None,
+    };
+
     // TODO: likely move this to its own method
     if let Some(query) = params.query.as_deref().filter(|query| !query.trim().is_empty()) {
 
@@ -62,6 +75,7 @@ pub async fn list_projects(
             query,
             limit,
             cursor,
+            fits,
         )
         .await
         .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed searching projects").with_cause(&e.to_string()))?;
@@ -87,6 +101,7 @@ pub async fn list_projects(
         state.db.pool(),
         limit,
         cursor,
+        fits,
     )
     .await
     .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed listing projects").with_cause(&e.to_string()))?;
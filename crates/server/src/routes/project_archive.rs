@@ -0,0 +1,169 @@
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+use lima_db::queries::projects_detail::{GetProjectError, ProjectAssetRow};
+use lima_domain::models::asset::AssetKind;
+use lima_indexer::archive::{ZipStream, worth_compressing};
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use crate::routes::assets::download::content_disposition;
+
+const MANIFEST_NAME: &str = "lima.json";
+// Chunks sent to the response, and how many may wait for a slow client.
+const CHUNK_SIZE: usize = 64 * 1024;
+const CHUNKS_IN_FLIGHT: usize = 8;
+
+#[derive(Deserialize, ToSchema)]
+pub struct ProjectArchiveParams {
+    /// Comma separated asset kinds, like `model,gcode`. Every asset when missing.
+    pub kind: Option<String>,
+}
+
+/// Written as `lima.json` at the root of the archive.
+#[derive(Serialize, ToSchema)]
+pub struct ProjectArchiveManifest {
+    pub name: String,
+    pub description: String,
+    pub tags: Vec<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/archive",
+    params(
+        ("project_id" = String, Path, description = "The ID of the project to download"),
+        ("kind" = Option<String>, Query, description = "Only assets of these kinds, comma separated (`model,gcode`)"),
+    ),
+    responses(
+        (status = 200, description = "ZIP of the project's files and a lima.json, streamed", content_type = "application/zip"),
+        (status = 400, description = "Invalid parameter provided", body = ApiErrorBody),
+        (status = 404, description = "Project not found", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn project_archive(
+    State(state): State<AppState>,
+    Path(project_id): Path<String>,
+    Query(params): Query<ProjectArchiveParams>,
+) -> Result<Response, ApiErrorResponse> {
+    let kinds = match params.kind.as_deref() {
+        None => None,
+        Some(value) => {
+            let kinds = value.split(',')
+                .map(|kind| AssetKind::parse(kind.trim()).ok_or_else(|| {
+                    ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_kind", "Unknown asset kind").with_cause(kind)
+                }))
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(kinds)
+        }
+    };
+
+    let project = match lima_db::queries::projects_detail::get_project(state.db.pool(), &project_id).await {
+        Ok(project) => project,
+        Err(GetProjectError::NotFound) => {
+            return Err(ApiErrorResponse::new(StatusCode::NOT_FOUND, "project_not_found", "Project not found"));
+        }
+        Err(e) => {
+            return Err(ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading project").with_cause(&e.to_string()));
+        }
+    };
+
+    let tags = lima_db::queries::projects_detail::get_project_tags(state.db.pool(), &project_id)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading project tags").with_cause(&e.to_string()))?;
+
+    let assets: Vec<ProjectAssetRow> = lima_db::queries::projects_detail::get_project_assets(state.db.pool(), &project_id)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading project assets").with_cause(&e.to_string()))?
+        .into_iter()
+        .filter(|asset| kinds.as_ref().is_none_or(|kinds| kinds.iter().any(|kind| kind.as_str() == asset.kind)))
+        // A lima.json of an earlier export is replaced by the current one.
+        .filter(|asset| asset.file_path != MANIFEST_NAME)
+        .collect();
+
+    let manifest = ProjectArchiveManifest {
+        name: project.name.clone(),
+        description: project.description.clone(),
+        tags: tags.into_iter().map(|tag| tag.name).collect(),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(|e| {
+        ApiErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, "archive_failed", "Failed writing lima.json").with_cause(&e.to_string())
+    })?;
+
+    let project_dir: PathBuf = ["data", "library", &project.folder_path].iter().collect();
+    let (tx, mut rx) = mpsc::channel::<std::io::Result<Vec<u8>>>(CHUNKS_IN_FLIGHT);
+
+    // Files are read and compressed on a blocking thread, the response only holds a few chunks.
+    tokio::task::spawn_blocking(move || {
+        let out = BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter { tx: tx.clone() });
+        if let Err(e) = write_archive(out, &project_dir, &assets, &manifest) {
+            tracing::warn!("Archive of {} stopped: {}", project_dir.display(), e);
+            // Fails the response so the client doesn't keep a truncated zip. Nothing to do when
+            // it is the client that went away.
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+
+    let stream = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    let file_name = format!("{}.zip", project.folder_path.rsplit('/').next().unwrap_or(&project.folder_path));
+
+    let mut response = Body::from_stream(stream).into_response();
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+    if let Ok(disposition) = HeaderValue::from_str(&content_disposition(&file_name)) {
+        headers.insert(header::CONTENT_DISPOSITION, disposition);
+    }
+
+    Ok(response)
+}
+
+fn write_archive(out: impl Write, project_dir: &std::path::Path, assets: &[ProjectAssetRow], manifest: &[u8]) -> std::io::Result<()> {
+    let mut zip = ZipStream::new(out);
+
+    for asset in assets {
+        let path = project_dir.join(&asset.file_path);
+        // Deleted since the last sync. The rest of the project is still worth sending.
+        let (file, metadata) = match std::fs::File::open(&path).and_then(|file| file.metadata().map(|m| (file, m))) {
+            Ok(opened) => opened,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::warn!("Skipping missing {} in archive", path.display());
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let modified = metadata.modified().unwrap_or(std::time::SystemTime::now());
+        let compress = worth_compressing(&asset.kind, &asset.file_path);
+        zip.add_file(&asset.file_path, file, metadata.len(), modified, compress)?;
+    }
+
+    zip.add_file(MANIFEST_NAME, manifest, manifest.len() as u64, std::time::SystemTime::now(), true)?;
+    zip.finish()?.flush()
+}
+
+// Hands what the zip writer produces to the response body.
+struct ChannelWriter {
+    tx: mpsc::Sender<std::io::Result<Vec<u8>>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx.blocking_send(Ok(buf.to_vec()))
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "client went away"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -20,9 +20,18 @@ pub async fn project_delete(
     State(state): State<AppState>,
     Path(project_id): Path<String>,
 ) -> Result<StatusCode, ApiErrorResponse> {
+    // Read before the rows are gone, the thumbnails are keyed by asset id.
+    let assets = lima_db::queries::projects_detail::get_project_assets(state.db.pool(), &project_id)
+        .await
+        .unwrap_or_default();
 
     match lima_db::queries::projects_delete::delete_project(state.db.pool(), &project_id).await {
-        Ok(_) => Ok(StatusCode::OK),
+        Ok(_) => {
+            for asset in assets {
+                state.thumbnails.remove(&asset.id).await;
+            }
+            Ok(StatusCode::OK)
+        },
         Err(DeleteProjectError::NotFound) => {
             return Err(ApiErrorResponse::new(
                 StatusCode::NOT_FOUND,
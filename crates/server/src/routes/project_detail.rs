@@ -1,7 +1,13 @@
-use axum::{ http::StatusCode, extract::{Path, State}, Json };
-use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use axum::{ http::StatusCode, extract::{Path, Query, State}, Json };
+use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use lima_db::queries::projects_detail::{ProjectTagRow, ProjectAssetRow, GetProjectError};
+use lima_domain::models::asset::asset_directory;
+use lima_domain::models::asset_metadata::AssetMetadata;
+use lima_domain::models::geometry::AssetGeometry;
+use lima_domain::models::thumbnail::ThumbnailUrls;
 
 use crate::state::AppState;
 use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
@@ -13,21 +19,62 @@ pub struct ProjectDetailResponse {
     pub description: String,
     pub folder_path: String,
     pub main_image_id: Option<String>,
+    /// Thumbnails of the main image, once they are generated.
+    pub thumbnails: Option<ThumbnailUrls>,
     pub created_at: String,
     pub updated_at: String,
     pub last_scanned_at: Option<String>,
 
     pub assets : Vec<ProjectAssetResponse>,
+    /// Only with `group_by=directory`. The same assets, one group per folder of the project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_groups: Option<Vec<ProjectAssetGroupResponse>>,
     pub tags : Vec<ProjectTagResponse>,
+    /// Values read from the project's 3MF files for an empty description or tag list. Only offered,
+    /// apply them with `PATCH /projects/{project_id}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestions: Option<ProjectSuggestionsResponse>,
     // TODO: add collections?
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct ProjectDetailParams {
+    /// Only `directory` is supported.
+    pub group_by: Option<String>,
+}
+
 #[derive(Serialize, ToSchema)]
+pub struct ProjectAssetGroupResponse {
+    /// Folder relative to the project folder, empty for the project folder itself.
+    pub directory: String,
+    pub assets: Vec<ProjectAssetResponse>,
+}
+
+#[derive(Serialize, ToSchema, Clone)]
 pub struct ProjectAssetResponse {
     pub id: String,
     pub file_path: String,
     pub kind: String,
     pub size_bytes: i64,
+    /// Images and models with a preview have thumbnails, once they are generated.
+    pub thumbnails: Option<ThumbnailUrls>,
+    /// What was read from inside the file, only 3MF and G-code files have some.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<AssetMetadata>,
+    /// Bounding box, volume... of STL, OBJ, 3MF and glTF models, once the indexer measured them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geometry: Option<AssetGeometry>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ProjectSuggestionsResponse {
+    /// The asset the values come from.
+    pub asset_id: String,
+    pub file_path: String,
+    /// Only when the project has no description.
+    pub description: Option<String>,
+    /// Designer and license, only when the project has no tags.
+    pub tags: Vec<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -43,9 +90,11 @@ pub struct ProjectTagResponse {
     path = "/api/projects/{project_id}",
     params(
         ("project_id" = String, Path, description = "The ID of the project to retrieve"),
+        ("group_by" = Option<String>, Query, description = "Set to `directory` to also get the assets grouped by folder in asset_groups"),
     ),
     responses(
         (status = 200, description = "Project details retrieved successfully", body = ProjectDetailResponse),
+        (status = 400, description = "Invalid parameter provided", body = ApiErrorBody),
         (status = 404, description = "Project not found", body = ApiErrorBody),
         (status = 500, description = "Internal server error", body = ApiErrorBody),
     )
@@ -53,7 +102,19 @@ pub struct ProjectTagResponse {
 pub async fn project_detail(
     State(state): State<AppState>,
     Path(project_id): Path<String>,
+    Query(params): Query<ProjectDetailParams>,
 ) -> Result<(StatusCode, Json<ProjectDetailResponse>), ApiErrorResponse> {
+    let grouped = match params.group_by.as_deref() {
+        None => false,
+        Some("directory") => true,
+        Some(other) => {
+            return Err(ApiErrorResponse::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_group_by",
+                "group_by only supports directory",
+            ).with_cause(other));
+        }
+    };
 
     let project = match lima_db::queries::projects_detail::get_project(
         state.db.pool(),
@@ -104,6 +165,27 @@ pub async fn project_detail(
         }
     };
 
+    let geometry = match lima_db::queries::geometry::get_project_geometry(
+        state.db.pool(),
+        &project_id,
+    ).await {
+        Ok(geometry) => geometry.into_iter().collect(),
+        Err(e) => {
+            return Err(ApiErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "internal_error",
+                "An internal server error occurred",
+            ).with_cause(&e.to_string()));
+        }
+    };
+
+    let assets = map_assets(project_assets, geometry);
+    let thumbnails = project.main_image_id.as_ref()
+        .and_then(|main_image_id| assets.iter().find(|asset| asset.id == *main_image_id))
+        .and_then(|asset| asset.thumbnails.clone());
+    let asset_groups = grouped.then(|| group_by_directory(&assets));
+    let suggestions = suggest(&assets, project.description.trim().is_empty(), project_tags.is_empty());
+
     Ok((
         StatusCode::OK,
         Json(ProjectDetailResponse {
@@ -112,23 +194,71 @@ pub async fn project_detail(
             description: project.description,
             folder_path: project.folder_path,
             main_image_id: project.main_image_id,
+            thumbnails,
             created_at: project.created_at,
             updated_at: project.updated_at,
             last_scanned_at: project.last_scanned_at,
-            assets: map_assets(project_assets),
+            assets,
+            asset_groups,
             tags: map_tags(project_tags),
+            suggestions,
         }),
     ))
 
 }
 
-fn map_assets(db_assets: Vec<ProjectAssetRow>) -> Vec<ProjectAssetResponse> {
+fn map_assets(db_assets: Vec<ProjectAssetRow>, mut geometry: HashMap<String, AssetGeometry>) -> Vec<ProjectAssetResponse> {
     db_assets.into_iter().map(|asset| {
         ProjectAssetResponse {
+            thumbnails: asset.thumbnail_hash.map(|hash| ThumbnailUrls::new(&asset.id, &hash)),
+            geometry: geometry.remove(&asset.id),
             id: asset.id,
             file_path: asset.file_path,
             kind: asset.kind,
             size_bytes: asset.size_bytes,
+            // A row that doesn't parse is read again on the next change of the file, not an error.
+            metadata: asset.metadata_json.and_then(|json| serde_json::from_str(&json).ok()),
+        }
+    }).collect()
+}
+
+// The first asset, by path, that has something for what the project is missing.
+fn suggest(assets: &[ProjectAssetResponse], wants_description: bool, wants_tags: bool) -> Option<ProjectSuggestionsResponse> {
+    assets.iter().find_map(|asset| {
+        let metadata = asset.metadata.as_ref()?;
+        let description = wants_description
+            .then(|| metadata.description.clone().or_else(|| metadata.title.clone()))
+            .flatten();
+        let tags: Vec<String> = if wants_tags {
+            [&metadata.designer, &metadata.license].into_iter().flatten().cloned().collect()
+        } else {
+            Vec::new()
+        };
+
+        if description.is_none() && tags.is_empty() {
+            return None;
+        }
+
+        Some(ProjectSuggestionsResponse {
+            asset_id: asset.id.clone(),
+            file_path: asset.file_path.clone(),
+            description,
+            tags,
+        })
+    })
+}
+
+// Assets come sorted by path, so every group is sorted too.
+fn group_by_directory(assets: &[ProjectAssetResponse]) -> Vec<ProjectAssetGroupResponse> {
+    let mut groups: BTreeMap<&str, Vec<ProjectAssetResponse>> = BTreeMap::new();
+    for asset in assets {
+        groups.entry(asset_directory(&asset.file_path)).or_default().push(asset.clone());
+    }
+
+    groups.into_iter().map(|(directory, assets)| {
+        ProjectAssetGroupResponse {
+            directory: directory.to_string(),
+            assets,
         }
     }).collect()
 }
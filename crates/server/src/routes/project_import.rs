@@ -3,11 +3,19 @@ use axum::{
     http::StatusCode,
     Json,
 };
+use serde::Serialize;
 use utoipa::ToSchema;
 use time::OffsetDateTime;
 use time::format_description::well_known::Rfc3339;
-use lima_db::queries::projects_detail::GetProjectError;
-use lima_db::queries::projects_import::ImportFromBundleError;
+use std::path::PathBuf;
+use lima_db::queries::projects_detail::{GetProjectError, ProjectAssetRow};
+use lima_domain::models::asset_metadata::has_metadata_source;
+use lima_domain::models::geometry::has_geometry_source;
+use lima_indexer::geometry::refresh_geometry;
+use lima_indexer::metadata::refresh_metadata;
+use lima_indexer::thumbnails::{ThumbnailError, has_thumbnail_source, refresh_thumbnails};
+use lima_db::queries::projects_import::{ImportFromBundleError, ImportedFile};
+use lima_domain::models::bundle::{ImportAction, ImportConflict};
 
 use crate::state::AppState;
 use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
@@ -17,6 +25,37 @@ use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
 pub struct ImportProjectRequest {
     pub bundle_id: String,
     pub new_main_image: Option<String>,
+    /// For bundle files whose name is already taken in the project. `fail` when missing.
+    #[serde(default)]
+    pub conflict: ImportConflict,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportedFileResponse {
+    /// Name of the file in the bundle.
+    pub name: String,
+    pub action: ImportAction,
+    /// Where the content is in the project. For skipped files, the file that was already there.
+    pub file_path: String,
+    /// The existing asset for skipped files. Missing when the existing file isn't indexed yet.
+    pub asset_id: Option<String>,
+}
+
+impl From<ImportedFile> for ImportedFileResponse {
+    fn from(file: ImportedFile) -> Self {
+        Self {
+            name: file.name,
+            action: file.action,
+            file_path: file.file_path,
+            asset_id: file.asset_id,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportProjectResponse {
+    /// Every file of the bundle, in the order they were imported.
+    pub files: Vec<ImportedFileResponse>,
 }
 
 #[utoipa::path(
@@ -27,10 +66,10 @@ pub struct ImportProjectRequest {
         ("project_id" = String, Path, description = "The ID of the project to import assets into"),
     ),
     responses(
-        (status = 200, description = "Project imported successfully"),
+        (status = 200, description = "Project imported successfully", body = ImportProjectResponse),
         (status = 400, description = "Bad request", body = ApiErrorBody),
         (status = 404, description = "Project or bundle not found", body = ApiErrorBody),
-        (status = 409, description = "Conflict during import", body = ApiErrorBody),
+        (status = 409, description = "A file name is taken in the project and the conflict strategy says to fail. Nothing is imported", body = ApiErrorBody),
         (status = 412, description = "Precondition failed", body = ApiErrorBody),
         (status = 500, description = "Internal server error", body = ApiErrorBody),
         (status = 503, description = "Service unavailable", body = ApiErrorBody),
@@ -40,7 +79,7 @@ pub async fn project_import(
     State(app_state): State<AppState>,
     Path(project_id): Path<String>,
     Json(payload): Json<ImportProjectRequest>,
-) -> Result<StatusCode, ApiErrorResponse> {
+) -> Result<Json<ImportProjectResponse>, ApiErrorResponse> {
     if payload.bundle_id.is_empty() {
         return Err(ApiErrorResponse::new(
             StatusCode::BAD_REQUEST,
@@ -70,10 +109,11 @@ pub async fn project_import(
         }
     };
 
-    let assets = lima_db::queries::projects_import::import_assets_from_bundle(
+    let outcome = lima_db::queries::projects_import::import_assets_from_bundle(
         app_state.db.pool(),
         &project.id,
         &payload.bundle_id,
+        payload.conflict,
     ).await.map_err(|e| { match e {
         ImportFromBundleError::BundleNotFound => {
             ApiErrorResponse::new(
@@ -103,6 +143,13 @@ pub async fn project_import(
                 "Bundle has an invalid format",
             ).with_cause(&format!("missing file: {}", name))
         },
+        ImportFromBundleError::InvalidPath { name } => {
+            ApiErrorResponse::new(
+                StatusCode::PRECONDITION_FAILED,
+                "invalid_file_path",
+                "Bundle has an invalid format",
+            ).with_cause(&format!("file path leaves the project folder: {}", name))
+        },
         ImportFromBundleError::Conflict { name } => {
             ApiErrorResponse::new(
                 StatusCode::CONFLICT,
@@ -126,6 +173,7 @@ pub async fn project_import(
         },
 
     }})?;
+    let assets = outcome.assets;
 
     let now = OffsetDateTime::now_utc().format(&Rfc3339).map_err(|e| {
         ApiErrorResponse::new(
@@ -181,5 +229,86 @@ pub async fn project_import(
         
     }
 
-    Ok(StatusCode::OK)
+    read_metadata(&app_state, &project.id, &project.folder_path, &assets).await;
+    analyze_geometry(&app_state, &project.id, &project.folder_path, &assets, &now).await;
+    generate_thumbnails(&app_state, &project.id, &project.folder_path, &assets).await;
+
+    // Imports without any image still get a main image, the preview of a model.
+    if let Err(e) = lima_db::queries::assets::fill_missing_main_image(app_state.db.pool(), &project.id, &now).await {
+        tracing::warn!("Failed setting a main image for project {}: {}", project.id, e);
+    }
+
+    Ok(Json(ImportProjectResponse {
+        files: outcome.files.into_iter().map(ImportedFileResponse::from).collect(),
+    }))
+}
+
+// The import itself already succeeded, a failure here is logged and the next sync tries again.
+async fn generate_thumbnails(app_state: &AppState, project_id: &str, folder_path: &str, imported: &[ProjectAssetRow]) {
+    let stale = match lima_db::queries::scan::list_stale_thumbnails(app_state.db.pool(), project_id).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("Failed listing thumbnails to generate for project {}: {}", project_id, e);
+            return;
+        }
+    };
+
+    let project_dir: PathBuf = ["data", "library", folder_path].iter().collect();
+    let wanted = stale.iter()
+        .filter(|a| imported.iter().any(|i| i.id == a.id))
+        .filter(|a| has_thumbnail_source(&a.kind, &a.file_path));
+
+    for asset in wanted {
+        let source = project_dir.join(&asset.file_path);
+        match refresh_thumbnails(app_state.db.pool(), &app_state.thumbnails, &asset.id, &asset.kind, &source, &asset.file_hash).await {
+            Ok(()) | Err(ThumbnailError::NoPreview) => {}
+            Err(e) => tracing::warn!("Failed generating thumbnails for {}/{}: {}", folder_path, asset.file_path, e),
+        }
+    }
+}
+
+// Same as thumbnails, the next sync tries again.
+async fn read_metadata(app_state: &AppState, project_id: &str, folder_path: &str, imported: &[ProjectAssetRow]) {
+    let stale = match lima_db::queries::scan::list_stale_metadata(app_state.db.pool(), project_id).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("Failed listing metadata to read for project {}: {}", project_id, e);
+            return;
+        }
+    };
+
+    let project_dir: PathBuf = ["data", "library", folder_path].iter().collect();
+    let wanted = stale.iter()
+        .filter(|a| imported.iter().any(|i| i.id == a.id))
+        .filter(|a| has_metadata_source(&a.file_path));
+
+    for asset in wanted {
+        let source = project_dir.join(&asset.file_path);
+        if let Err(e) = refresh_metadata(app_state.db.pool(), &asset.id, &source, &asset.file_hash).await {
+            tracing::warn!("Failed reading metadata of {}/{}: {}", folder_path, asset.file_path, e);
+        }
+    }
+}
+
+// Same as thumbnails, the next sync tries again.
+async fn analyze_geometry(app_state: &AppState, project_id: &str, folder_path: &str, imported: &[ProjectAssetRow], now: &str) {
+    let stale = match lima_db::queries::scan::list_stale_geometry(app_state.db.pool(), project_id).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("Failed listing models to analyze for project {}: {}", project_id, e);
+            return;
+        }
+    };
+
+    let project_dir: PathBuf = ["data", "library", folder_path].iter().collect();
+    let wanted = stale.iter()
+        .filter(|a| imported.iter().any(|i| i.id == a.id))
+        .filter(|a| has_geometry_source(&a.file_path));
+
+    for asset in wanted {
+        let source = project_dir.join(&asset.file_path);
+        if let Err(e) = refresh_geometry(app_state.db.pool(), &asset.id, &source, &asset.file_hash, now).await {
+            tracing::warn!("Failed analyzing geometry of {}/{}: {}", folder_path, asset.file_path, e);
+        }
+    }
 }
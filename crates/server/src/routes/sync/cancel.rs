@@ -0,0 +1,50 @@
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use lima_indexer::sync::CancelOutcome;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::SyncRunResponse;
+
+#[utoipa::path(
+    post,
+    path = "/api/sync/runs/{run_id}/cancel",
+    params(
+        ("run_id" = String, Path, description = "The ID of the sync run to cancel"),
+    ),
+    responses(
+        (status = 200, description = "Queued run cancelled", body = SyncRunResponse),
+        (status = 202, description = "Running run asked to stop. It ends up as cancelled with the stats of what was done", body = SyncRunResponse),
+        (status = 404, description = "Sync run not found", body = ApiErrorBody),
+        (status = 409, description = "Sync run already finished", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn cancel_sync_run(
+    State(state): State<AppState>,
+    Path(run_id): Path<String>,
+) -> Result<(StatusCode, Json<SyncRunResponse>), ApiErrorResponse> {
+    let outcome = state.sync.cancel(&run_id)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "Failed to cancel sync run").with_cause(&e.to_string()))?;
+
+    let run = lima_db::queries::sync_runs::get_sync_run(state.db.pool(), &run_id)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading sync run").with_cause(&e.to_string()))?
+        .ok_or_else(|| ApiErrorResponse::new(StatusCode::NOT_FOUND, "sync_run_not_found", "Sync run not found"))?;
+
+    match outcome {
+        CancelOutcome::Cancelled => {
+            tracing::info!("Queued sync run {} cancelled", run_id);
+            Ok((StatusCode::OK, Json(run.into())))
+        }
+        CancelOutcome::Requested => {
+            tracing::info!("Cancel requested for sync run {}", run_id);
+            Ok((StatusCode::ACCEPTED, Json(run.into())))
+        }
+        CancelOutcome::NotActive => Err(ApiErrorResponse::new(
+            StatusCode::CONFLICT,
+            "sync_run_finished",
+            "Sync run already finished",
+        )),
+    }
+}
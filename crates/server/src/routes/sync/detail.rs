@@ -0,0 +1,36 @@
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::SyncRunResponse;
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/runs/{run_id}",
+    params(
+        ("run_id" = String, Path, description = "The ID of the sync run"),
+    ),
+    responses(
+        (status = 200, description = "Sync run details", body = SyncRunResponse),
+        (status = 404, description = "Sync run not found", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn sync_run_detail(
+    State(state): State<AppState>,
+    Path(run_id): Path<String>,
+) -> Result<Json<SyncRunResponse>, ApiErrorResponse> {
+    match lima_db::queries::sync_runs::get_sync_run(state.db.pool(), &run_id).await {
+        Ok(Some(run)) => Ok(Json(run.into())),
+        Ok(None) => Err(ApiErrorResponse::new(
+            StatusCode::NOT_FOUND,
+            "sync_run_not_found",
+            "Sync run not found",
+        )),
+        Err(e) => Err(ApiErrorResponse::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "db_failure",
+            "DB failed reading sync run",
+        ).with_cause(&e.to_string())),
+    }
+}
@@ -0,0 +1,106 @@
+use axum::{
+    Json, extract::{Path, Query, State}, http::StatusCode
+};
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use lima_db::queries::sync_runs::SyncEventRow;
+
+use lima_domain::pagination::{Cursor, decode_cursor, encode_cursor};
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorBody, ApiErrorResponse};
+
+#[derive(Deserialize, ToSchema)]
+pub struct ListSyncEventsParams {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SyncEventResponse {
+    pub id: i64,
+    pub run_id: String,
+    pub created_at: String,
+    pub level: String,
+    pub kind: String,
+    pub data: serde_json::Value,
+}
+
+impl From<SyncEventRow> for SyncEventResponse {
+    fn from(row: SyncEventRow) -> Self {
+        Self {
+            id: row.id,
+            run_id: row.run_id,
+            created_at: row.created_at,
+            level: row.level,
+            kind: row.kind,
+            data: serde_json::from_str(&row.data_json).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListSyncEventsResponse {
+    pub items: Vec<SyncEventResponse>,
+    pub next_cursor: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/runs/{run_id}/events",
+    params(
+        ("run_id" = String, Path, description = "The ID of the sync run"),
+        ("limit" = Option<i64>, Query, description = "Maximum number of events to return (default: 100, max: 500)"),
+        ("cursor" = Option<String>, Query, description = "Opaque pagination cursor"),
+    ),
+    responses(
+        (status = 200, description = "Sync run events, oldest first", body = ListSyncEventsResponse),
+        (status = 400, description = "Invalid parameter provided", body = ApiErrorBody),
+        (status = 404, description = "Sync run not found", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn list_sync_events(
+    State(state): State<AppState>,
+    Path(run_id): Path<String>,
+    Query(params): Query<ListSyncEventsParams>,
+) -> Result<Json<ListSyncEventsResponse>, ApiErrorResponse> {
+    let limit = params.limit.unwrap_or(100).clamp(1, 500);
+
+    let after_id = match params.cursor {
+        Some(ref c) => {
+            let cursor = decode_cursor(c).map_err(|e| ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_cursor", "Invalid cursor parameter").with_cause(&e))?;
+            Some(cursor.id.parse::<i64>().map_err(|e| ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_cursor", "Invalid cursor parameter").with_cause(&e.to_string()))?)
+        }
+        None => None,
+    };
+
+    let run = lima_db::queries::sync_runs::get_sync_run(state.db.pool(), &run_id)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading sync run").with_cause(&e.to_string()))?;
+
+    if run.is_none() {
+        return Err(ApiErrorResponse::new(StatusCode::NOT_FOUND, "sync_run_not_found", "Sync run not found"));
+    }
+
+    let events = lima_db::queries::sync_runs::list_sync_events(
+        state.db.pool(),
+        &run_id,
+        limit,
+        after_id,
+    )
+    .await
+    .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed listing sync events").with_cause(&e.to_string()))?;
+
+    let next_cursor = events.last().map(|last| {
+        encode_cursor(&Cursor {
+            updated_at: last.created_at.clone(),
+            id: last.id.to_string(),
+            rank: None,
+        })
+    });
+
+    Ok(Json(ListSyncEventsResponse {
+        items: events.into_iter().map(Into::into).collect(),
+        next_cursor,
+    }))
+}
@@ -0,0 +1,68 @@
+use axum::{
+    Json, extract::{Query, State}, http::StatusCode
+};
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+use lima_domain::pagination::{Cursor, decode_cursor, encode_cursor};
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorBody, ApiErrorResponse};
+use super::SyncRunResponse;
+
+#[derive(Deserialize, ToSchema)]
+pub struct ListSyncRunsParams {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListSyncRunsResponse {
+    pub items: Vec<SyncRunResponse>,
+    pub next_cursor: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/runs",
+    params(
+        ("limit" = Option<i64>, Query, description = "Maximum number of runs to return (default: 50, max: 200)"),
+        ("cursor" = Option<String>, Query, description = "Opaque pagination cursor"),
+    ),
+    responses(
+        (status = 200, description = "List of sync runs, newest first", body = ListSyncRunsResponse),
+        (status = 400, description = "Invalid parameter provided", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn list_sync_runs(
+    State(state): State<AppState>,
+    Query(params): Query<ListSyncRunsParams>,
+) -> Result<Json<ListSyncRunsResponse>, ApiErrorResponse> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 200);
+
+    let cursor = match params.cursor {
+        Some(ref c) => Some(decode_cursor(c).map_err(|e| ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_cursor", "Invalid cursor parameter").with_cause(&e))?),
+        None => None,
+    };
+
+    let runs = lima_db::queries::sync_runs::list_sync_runs(
+        state.db.pool(),
+        limit,
+        cursor,
+    )
+    .await
+    .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed listing sync runs").with_cause(&e.to_string()))?;
+
+    let next_cursor = runs.last().map(|last| {
+        encode_cursor(&Cursor {
+            updated_at: last.created_at.clone(),
+            id: last.id.clone(),
+            rank: None,
+        })
+    });
+
+    Ok(Json(ListSyncRunsResponse {
+        items: runs.into_iter().map(Into::into).collect(),
+        next_cursor,
+    }))
+}
@@ -0,0 +1,43 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+use lima_db::queries::sync_runs::SyncRunRow;
+use lima_domain::models::sync::SyncStats;
+
+pub mod run;
+pub mod status;
+pub mod list;
+pub mod detail;
+pub mod events;
+pub mod cancel;
+pub mod stream;
+
+#[derive(Serialize, ToSchema)]
+pub struct SyncRunResponse {
+    pub id: String,
+    pub mode: String,
+    pub reason: String,
+    pub status: String,
+    pub options: serde_json::Value,
+    pub stats: SyncStats,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub error: String,
+}
+
+impl From<SyncRunRow> for SyncRunResponse {
+    fn from(row: SyncRunRow) -> Self {
+        Self {
+            id: row.id,
+            mode: row.mode,
+            reason: row.reason,
+            status: row.status,
+            options: serde_json::from_str(&row.options_json).unwrap_or_default(),
+            stats: serde_json::from_str(&row.stats_json).unwrap_or_default(),
+            created_at: row.created_at,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+            error: row.error,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Deserialize;
+use utoipa::ToSchema;
+use lima_domain::models::sync::{SyncMode, SyncOptions, SyncReason};
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::SyncRunResponse;
+
+#[derive(Deserialize, ToSchema)]
+pub struct RunSyncRequest {
+    pub mode: Option<SyncMode>,
+    /// Limit the run to these project folders.
+    pub folders: Option<Vec<String>>,
+    /// Only report what would change (as the run events and stats), nothing is written.
+    pub dry_run: Option<bool>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/sync/run",
+    request_body = RunSyncRequest,
+    responses(
+        (status = 202, description = "Sync run queued. If an identical run was already waiting that run is returned", body = SyncRunResponse),
+        (status = 400, description = "Invalid folder given, or folders / dry run given for a maintenance run", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn run_sync(
+    State(state): State<AppState>,
+    body: Option<Json<RunSyncRequest>>,
+) -> Result<(StatusCode, Json<SyncRunResponse>), ApiErrorResponse> {
+    let body = body.map(|Json(body)| body);
+    let mode = body.as_ref().and_then(|body| body.mode).unwrap_or(SyncMode::Incremental);
+    let dry_run = body.as_ref().and_then(|body| body.dry_run).unwrap_or(false);
+    if dry_run && matches!(mode, SyncMode::BundleGc | SyncMode::Verify) {
+        return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_options", "Dry runs only apply to full and incremental runs"));
+    }
+
+    let mut options = match body.and_then(|body| body.folders) {
+        Some(_) if matches!(mode, SyncMode::BundleGc | SyncMode::Verify) => {
+            return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_options", "Folders only apply to full and incremental runs"));
+        }
+        Some(folders) => {
+            if folders.iter().any(|folder| !is_project_folder(folder)) {
+                return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_folder", "Folders must be top level project folders"));
+            }
+            SyncOptions::for_folders(folders)
+        }
+        None => SyncOptions::default(),
+    };
+    options.dry_run = dry_run;
+
+    let queued = state.sync.enqueue(mode, SyncReason::Manual, &options)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "Failed to queue sync run").with_cause(&e.to_string()))?;
+
+    tracing::info!("Sync run {} queued (mode: {}, new: {})", queued.id, mode.as_str(), queued.created);
+
+    let run = lima_db::queries::sync_runs::get_sync_run(state.db.pool(), &queued.id)
+        .await
+        .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "Failed to read sync run").with_cause(&e.to_string()))?
+        .ok_or_else(|| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "Queued sync run disappeared"))?;
+
+    Ok((StatusCode::ACCEPTED, Json(run.into())))
+}
+
+fn is_project_folder(folder: &str) -> bool {
+    !folder.is_empty() && folder != "." && folder != ".." && !folder.contains('/') && !folder.contains('\\') && !folder.contains('\0')
+}
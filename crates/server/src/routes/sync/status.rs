@@ -0,0 +1,40 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::SyncRunResponse;
+
+#[derive(Serialize, ToSchema)]
+pub struct SyncStatusResponse {
+    pub running: Option<SyncRunResponse>,
+    pub queued: i64,
+    pub last_finished: Option<SyncRunResponse>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/status",
+    responses(
+        (status = 200, description = "Current and last sync status", body = SyncStatusResponse),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn sync_status(
+    State(state): State<AppState>,
+) -> Result<Json<SyncStatusResponse>, ApiErrorResponse> {
+    let running = lima_db::queries::sync_runs::get_running_sync_run(state.db.pool()).await.map_err(db_error)?;
+    let queued = lima_db::queries::sync_runs::count_queued_sync_runs(state.db.pool()).await.map_err(db_error)?;
+    let last_finished = lima_db::queries::sync_runs::get_last_finished_sync_run(state.db.pool()).await.map_err(db_error)?;
+
+    Ok(Json(SyncStatusResponse {
+        running: running.map(Into::into),
+        queued,
+        last_finished: last_finished.map(Into::into),
+    }))
+}
+
+fn db_error(e: impl ToString) -> ApiErrorResponse {
+    ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading sync status").with_cause(&e.to_string())
+}
@@ -0,0 +1,143 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream};
+use lima_db::Db;
+use lima_db::queries::sync_runs::{self, SyncEventRow};
+use serde::Deserialize;
+use tokio::sync::watch;
+use utoipa::ToSchema;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorBody, ApiErrorResponse};
+use super::events::SyncEventResponse;
+
+const BATCH_SIZE: i64 = 100;
+const HEARTBEAT: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize, ToSchema)]
+pub struct StreamSyncEventsParams {
+    /// Only stream the events of this run.
+    pub run_id: Option<String>,
+    /// Same as the `Last-Event-ID` header, for clients that can't set headers. The header wins.
+    pub last_event_id: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/sync/events/stream",
+    params(
+        ("run_id" = Option<String>, Query, description = "Only stream the events of this run. Its events are replayed from the start"),
+        ("last_event_id" = Option<i64>, Query, description = "Resume after this event id (same as the Last-Event-ID header)"),
+        ("Last-Event-ID" = Option<i64>, Header, description = "Resume after this event id. Sent by EventSource when it reconnects"),
+    ),
+    responses(
+        (status = 200, description = "Server-Sent Events stream. Every event is a SyncEventResponse as JSON with the event id as SSE id", body = SyncEventResponse, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid Last-Event-ID", body = ApiErrorBody),
+        (status = 404, description = "Sync run not found", body = ApiErrorBody),
+        (status = 503, description = "Failure to connect to the database", body = ApiErrorBody),
+    )
+)]
+pub async fn stream_sync_events(
+    State(state): State<AppState>,
+    Query(params): Query<StreamSyncEventsParams>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiErrorResponse> {
+    let last_event_id = match headers.get("last-event-id") {
+        Some(value) => Some(
+            value.to_str().ok().and_then(|v| v.trim().parse::<i64>().ok()).ok_or_else(|| {
+                ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_last_event_id", "Last-Event-ID must be a sync event id")
+            })?,
+        ),
+        None => params.last_event_id,
+    };
+
+    // Subscribe before reading the starting point so nothing written in between is missed.
+    let updates = state.sync.subscribe_events();
+
+    let after_id = match (last_event_id, &params.run_id) {
+        (Some(id), _) => id,
+        (None, Some(run_id)) => {
+            let run = sync_runs::get_sync_run(state.db.pool(), run_id)
+                .await
+                .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading sync run").with_cause(&e.to_string()))?;
+
+            if run.is_none() {
+                return Err(ApiErrorResponse::new(StatusCode::NOT_FOUND, "sync_run_not_found", "Sync run not found"));
+            }
+
+            0
+        }
+        // Following everything starts from now, the history is in /sync/runs/{run_id}/events.
+        (None, None) => sync_runs::get_last_sync_event_id(state.db.pool())
+            .await
+            .map_err(|e| ApiErrorResponse::new(StatusCode::SERVICE_UNAVAILABLE, "db_failure", "DB failed reading sync events").with_cause(&e.to_string()))?,
+    };
+
+    let tail = Tail {
+        db: state.db.clone(),
+        run_id: params.run_id,
+        after_id,
+        updates,
+        pending: VecDeque::new(),
+    };
+
+    let events = stream::unfold(tail, |mut tail| async move {
+        let row = tail.next().await?;
+        Some((Ok(to_event(row)), tail))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::new().interval(HEARTBEAT).text("heartbeat")))
+}
+
+// The database is the source of truth, the watch channel only says when it's worth looking again.
+// A slow client just reads bigger batches, it can't lose events.
+struct Tail {
+    db: Arc<Db>,
+    run_id: Option<String>,
+    after_id: i64,
+    updates: watch::Receiver<i64>,
+    pending: VecDeque<SyncEventRow>,
+}
+
+impl Tail {
+    // None once the sync worker is gone, which ends the stream.
+    async fn next(&mut self) -> Option<SyncEventRow> {
+        loop {
+            if let Some(row) = self.pending.pop_front() {
+                self.after_id = row.id;
+                return Some(row);
+            }
+
+            self.updates.borrow_and_update();
+
+            let rows = match &self.run_id {
+                Some(run_id) => sync_runs::list_sync_events(self.db.pool(), run_id, BATCH_SIZE, Some(self.after_id)).await,
+                None => sync_runs::list_all_sync_events(self.db.pool(), BATCH_SIZE, self.after_id).await,
+            };
+
+            match rows {
+                Ok(rows) if !rows.is_empty() => self.pending.extend(rows),
+                Ok(_) => self.updates.changed().await.ok()?,
+                Err(e) => {
+                    tracing::warn!("Failed reading sync events for the stream: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    }
+}
+
+fn to_event(row: SyncEventRow) -> Event {
+    let id = row.id.to_string();
+    let data = serde_json::to_string(&SyncEventResponse::from(row)).unwrap_or_default();
+
+    Event::default().id(id).data(data)
+}
@@ -0,0 +1,118 @@
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::{UPLOAD_OFFSET, Upload, header_u64, storage_error};
+
+const CONTENT_TYPE: &str = "application/offset+octet-stream";
+
+#[utoipa::path(
+    patch,
+    path = "/api/uploads/{bundle_id}/{upload_id}",
+    params(
+        ("bundle_id" = String, Path, description = "The bundle the upload belongs to"),
+        ("upload_id" = String, Path, description = "The ID of the upload"),
+        ("Tus-Resumable" = String, Header, description = "`1.0.0`"),
+        ("Upload-Offset" = u64, Header, description = "Where the sent bytes go, the current offset of the upload"),
+    ),
+    request_body(
+        content_type = "application/offset+octet-stream",
+        description = "The next bytes of the file. On a dropped connection what arrived is kept, ask for the offset with HEAD and continue from there."
+    ),
+    responses(
+        (status = 204, description = "Bytes stored. The file is added to the bundle once the offset reaches the length", headers(
+            ("Upload-Offset" = u64, description = "The new offset"),
+        )),
+        (status = 400, description = "Missing offset, or more bytes than the upload length", body = ApiErrorBody),
+        (status = 404, description = "Upload not found", body = ApiErrorBody),
+        (status = 409, description = "Upload-Offset isn't the current offset, or the upload is complete and its file name is already taken in the bundle", body = ApiErrorBody),
+        (status = 412, description = "Unsupported tus version", body = ApiErrorBody),
+        (status = 415, description = "Wrong content type", body = ApiErrorBody),
+        (status = 423, description = "Another request is writing to the upload", body = ApiErrorBody),
+        (status = 500, description = "Failed storing the bytes", body = ApiErrorBody),
+    )
+)]
+pub async fn upload_append(
+    Path((bundle_id, upload_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, ApiErrorResponse> {
+    if headers.get(header::CONTENT_TYPE).is_none_or(|value| value != CONTENT_TYPE) {
+        return Err(ApiErrorResponse::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "invalid_content_type",
+            "Content-Type must be application/offset+octet-stream"
+        ));
+    }
+    let Some(Ok(request_offset)) = header_u64(&headers, &UPLOAD_OFFSET) else {
+        return Err(ApiErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_upload_offset",
+            "Upload-Offset must be the current offset in bytes"
+        ));
+    };
+
+    let upload = Upload::find(&bundle_id, &upload_id)?;
+    let _lock = upload.lock()?;
+    let mut info = upload.read_info().await?;
+    let mut offset = upload.offset(&info).await?;
+
+    if request_offset != offset {
+        return Err(ApiErrorResponse::new(
+            StatusCode::CONFLICT,
+            "offset_mismatch",
+            "Upload-Offset isn't the current offset of the upload"
+        ).with_details(serde_json::json!({ "offset": offset })));
+    }
+
+    if !info.completed {
+        let mut file = fs::OpenOptions::new().append(true).open(upload.part_path()).await.map_err(storage_error)?;
+        let mut stream = body.into_data_stream();
+
+        // Every chunk is on disk before the next one is read, an interrupted request keeps them all.
+        let mut result = Ok(());
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    tracing::warn!("Upload {} interrupted at {} bytes: {}", upload.id, offset, e);
+                    result = Err(ApiErrorResponse::new(
+                        StatusCode::BAD_REQUEST,
+                        "upload_interrupted",
+                        "The request body ended early, resume from the current offset"
+                    ).with_cause(&e.to_string()));
+                    break;
+                }
+            };
+
+            if offset + chunk.len() as u64 > info.length {
+                result = Err(ApiErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    "upload_length_exceeded",
+                    "More bytes were sent than the length of the upload"
+                ));
+                break;
+            }
+
+            file.write_all(&chunk).await.map_err(storage_error)?;
+            offset += chunk.len() as u64;
+        }
+        file.flush().await.map_err(storage_error)?;
+        drop(file);
+        result?;
+
+        if offset == info.length {
+            upload.complete(&mut info).await?;
+        }
+    }
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    response.headers_mut().insert(UPLOAD_OFFSET.clone(), HeaderValue::from(offset));
+    Ok(response)
+}
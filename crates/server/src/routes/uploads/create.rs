@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use lima_domain::models::asset::sanitize_asset_path;
+use lima_domain::models::bundle::BUNDLE_META_FILE;
+use tokio::fs;
+use uuid::Uuid;
+
+use crate::state::AppState;
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use crate::routes::bundle_create::{find_bundle_folder, is_ignored_upload};
+use super::{LIMA_BUNDLE_ID, UPLOAD_DEFER_LENGTH, UPLOAD_LENGTH, UPLOAD_METADATA, Upload, UploadInfo, duplicate_file, header_u64, storage_error};
+
+#[utoipa::path(
+    post,
+    path = "/api/uploads",
+    params(
+        ("Tus-Resumable" = String, Header, description = "`1.0.0`"),
+        ("Upload-Length" = u64, Header, description = "Size of the file in bytes"),
+        ("Upload-Metadata" = String, Header, description = "tus metadata. `filename` is required, it may be a path like `stls/base.stl`. `bundle_id` adds the file to an existing bundle, a new one is created otherwise"),
+    ),
+    responses(
+        (status = 201, description = "Upload created, send the file with PATCH to its location", headers(
+            ("Location" = String, description = "`/api/uploads/{bundle_id}/{upload_id}`"),
+            ("Lima-Bundle-Id" = String, description = "The bundle to import once its uploads are done"),
+        )),
+        (status = 400, description = "Missing length, invalid metadata or file name, or an ignored file", body = ApiErrorBody),
+        (status = 404, description = "Bundle not found", body = ApiErrorBody),
+        (status = 409, description = "A file of that name is already in the bundle", body = ApiErrorBody),
+        (status = 412, description = "Unsupported tus version", body = ApiErrorBody),
+        (status = 500, description = "Failed creating files or folders", body = ApiErrorBody),
+    )
+)]
+pub async fn create_upload(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Response, ApiErrorResponse> {
+    if headers.contains_key(&UPLOAD_DEFER_LENGTH) {
+        return Err(ApiErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "defer_length_unsupported",
+            "The file size must be known, Upload-Defer-Length isn't supported"
+        ));
+    }
+    let length = match header_u64(&headers, &UPLOAD_LENGTH) {
+        Some(Ok(length)) => length,
+        _ => return Err(ApiErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_upload_length",
+            "Upload-Length must be the size of the file in bytes"
+        )),
+    };
+
+    let raw_metadata = headers.get(&UPLOAD_METADATA)
+        .map(|value| value.to_str().map(str::to_string))
+        .transpose()
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+    let metadata = parse_metadata(&raw_metadata).ok_or_else(|| {
+        ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_upload_metadata", "Upload-Metadata isn't valid tus metadata")
+    })?;
+
+    let raw_name = metadata.get("filename").cloned().unwrap_or_default();
+    let file_name = sanitize_asset_path(&raw_name)
+        // The bundle's own file.
+        .filter(|name| name != BUNDLE_META_FILE)
+        .ok_or_else(|| {
+            ApiErrorResponse::new(StatusCode::BAD_REQUEST, "invalid_file_name", "filename metadata missing or not a valid path")
+                .with_cause(&raw_name)
+        })?;
+
+    // Same rules as the multipart upload and the library scan.
+    if is_ignored_upload(&state.ignore, &file_name) {
+        return Err(ApiErrorResponse::new(StatusCode::BAD_REQUEST, "ignored_file", "The file matches the ignore rules")
+            .with_cause(&file_name));
+    }
+
+    let bundle_id = match metadata.get("bundle_id") {
+        Some(bundle_id) => {
+            let bundle_folder = find_bundle_folder(bundle_id).await?;
+            // Checked again on completion, another upload may take the name meanwhile.
+            if fs::try_exists(bundle_folder.join(&file_name)).await.map_err(storage_error)? {
+                return Err(duplicate_file(&file_name));
+            }
+            bundle_id.clone()
+        }
+        None => Uuid::new_v4().to_string(),
+    };
+
+    let upload = Upload::new(&bundle_id, &Uuid::new_v4().to_string());
+    fs::create_dir_all(&upload.bundle_dir).await.map_err(storage_error)?;
+    fs::File::create(upload.part_path()).await.map_err(storage_error)?;
+
+    let mut info = UploadInfo {
+        file_name,
+        length,
+        metadata: raw_metadata,
+        completed: false,
+    };
+    upload.write_info(&info).await?;
+
+    // Nothing will be PATCHed to an empty file. Its Location isn't sent, so nothing is kept on failure.
+    if length == 0 && let Err(e) = upload.complete(&mut info).await {
+        let _ = fs::remove_file(upload.part_path()).await;
+        let _ = fs::remove_file(upload.info_path()).await;
+        return Err(e);
+    }
+
+    let location = format!("/api/uploads/{}/{}", bundle_id, upload.id);
+    let mut response = StatusCode::CREATED.into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::LOCATION, HeaderValue::from_str(&location).map_err(|_| storage_error(std::io::Error::other("invalid location")))?);
+    response_headers.insert(LIMA_BUNDLE_ID.clone(), HeaderValue::from_str(&bundle_id).map_err(|_| storage_error(std::io::Error::other("invalid bundle id")))?);
+
+    Ok(response)
+}
+
+// "key base64value,other base64value". Values may be left out.
+fn parse_metadata(raw: &str) -> Option<HashMap<String, String>> {
+    let mut metadata = HashMap::new();
+
+    for pair in raw.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let mut parts = pair.split(' ');
+        let key = parts.next()?;
+        let value = match parts.next() {
+            Some(encoded) => String::from_utf8(BASE64.decode(encoded).ok()?).ok()?,
+            None => String::new(),
+        };
+        if parts.next().is_some() || metadata.insert(key.to_string(), value).is_some() {
+            return None;
+        }
+    }
+
+    Some(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_pairs_are_decoded() {
+        let metadata = parse_metadata("filename c3Rscy9iYXNlLnN0bA==, bundle_id YWJj").unwrap();
+        assert_eq!(metadata["filename"], "stls/base.stl");
+        assert_eq!(metadata["bundle_id"], "abc");
+    }
+
+    #[test]
+    fn empty_metadata_and_values() {
+        assert!(parse_metadata("").unwrap().is_empty());
+        assert!(parse_metadata(" , ").unwrap().is_empty());
+
+        let metadata = parse_metadata("is_confidential,filename ").unwrap();
+        assert_eq!(metadata["is_confidential"], "");
+        assert_eq!(metadata["filename"], "");
+    }
+
+    #[test]
+    fn duplicate_keys_are_refused() {
+        assert_eq!(parse_metadata("filename YQ==,filename Yg=="), None);
+        assert_eq!(parse_metadata("flag,flag"), None);
+    }
+
+    #[test]
+    fn invalid_values_are_refused() {
+        // Not base64, base64 of invalid UTF-8, and a third part.
+        assert_eq!(parse_metadata("filename a.stl"), None);
+        assert_eq!(parse_metadata("filename /w=="), None);
+        assert_eq!(parse_metadata("filename YQ== YQ=="), None);
+    }
+}
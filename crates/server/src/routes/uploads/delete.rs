@@ -0,0 +1,40 @@
+use axum::{extract::Path, http::StatusCode};
+use tokio::fs;
+
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::{Upload, storage_error};
+
+#[utoipa::path(
+    delete,
+    path = "/api/uploads/{bundle_id}/{upload_id}",
+    params(
+        ("bundle_id" = String, Path, description = "The bundle the upload belongs to"),
+        ("upload_id" = String, Path, description = "The ID of the upload to stop"),
+        ("Tus-Resumable" = String, Header, description = "`1.0.0`"),
+    ),
+    responses(
+        (status = 204, description = "Upload removed. A file it completed stays in the bundle, delete the bundle to drop it"),
+        (status = 404, description = "Upload not found", body = ApiErrorBody),
+        (status = 412, description = "Unsupported tus version", body = ApiErrorBody),
+        (status = 423, description = "Another request is writing to the upload", body = ApiErrorBody),
+        (status = 500, description = "Failed removing the upload", body = ApiErrorBody),
+    )
+)]
+pub async fn upload_delete(
+    Path((bundle_id, upload_id)): Path<(String, String)>,
+) -> Result<StatusCode, ApiErrorResponse> {
+    let upload = Upload::find(&bundle_id, &upload_id)?;
+    let _lock = upload.lock()?;
+    let info = upload.read_info().await?;
+
+    if !info.completed {
+        match fs::remove_file(upload.part_path()).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(storage_error(e)),
+        }
+    }
+    fs::remove_file(upload.info_path()).await.map_err(storage_error)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use uuid::Uuid;
+
+use crate::models::http_error::ApiErrorResponse;
+use crate::routes::bundle_create::{add_to_bundle_meta, read_file_meta};
+
+pub mod options;
+pub mod create;
+pub mod offset;
+pub mod append;
+pub mod delete;
+
+// tus 1.0 (https://tus.io/protocols/resumable-upload), core plus the creation and termination
+// extensions. An upload is one file of a bundle: the bytes go to `.upload-<id>` in the bundle folder
+// and become `<file name>` in it, listed in meta.json, once the last one arrives. Partial uploads
+// sit at the top of the bundle so the bundle GC sees them being written to.
+
+pub const TUS_VERSION: &str = "1.0.0";
+pub const TUS_EXTENSIONS: &str = "creation,termination";
+
+pub static TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+pub static TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+pub static TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+pub static UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+pub static UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+pub static UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+pub static UPLOAD_DEFER_LENGTH: HeaderName = HeaderName::from_static("upload-defer-length");
+/// Sent back on creation, the ID to import once every upload of the bundle is done.
+pub static LIMA_BUNDLE_ID: HeaderName = HeaderName::from_static("lima-bundle-id");
+
+const PART_PREFIX: &str = ".upload-";
+
+// PATCH and DELETE of the same upload at the same time would mix up the file.
+static ACTIVE_UPLOADS: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Kept next to the partial file. Stays after completion so a client that lost the last
+/// response can still ask for the offset.
+#[derive(Serialize, Deserialize)]
+struct UploadInfo {
+    file_name: String,
+    length: u64,
+    /// `Upload-Metadata` as the client sent it, returned on HEAD.
+    metadata: String,
+    completed: bool,
+}
+
+/// Rejects requests for another protocol version and adds `Tus-Resumable` to every response.
+/// OPTIONS is how clients find the version, it's exempt both ways.
+pub async fn tus_resumable(request: Request, next: Next) -> Response {
+    if request.method() == Method::OPTIONS {
+        return next.run(request).await;
+    }
+
+    let supported = request.headers().get(&TUS_RESUMABLE).is_some_and(|version| version == TUS_VERSION);
+    let mut response = if supported {
+        next.run(request).await
+    } else {
+        let mut response = ApiErrorResponse::new(
+            StatusCode::PRECONDITION_FAILED,
+            "unsupported_tus_version",
+            "Tus-Resumable header missing or of an unsupported version",
+        ).into_response();
+        response.headers_mut().insert(TUS_VERSION_HEADER.clone(), HeaderValue::from_static(TUS_VERSION));
+        response
+    };
+
+    response.headers_mut().insert(TUS_RESUMABLE.clone(), HeaderValue::from_static(TUS_VERSION));
+    response
+}
+
+struct Upload {
+    bundle_dir: PathBuf,
+    id: String,
+}
+
+impl Upload {
+    fn new(bundle_id: &str, upload_id: &str) -> Self {
+        Self {
+            bundle_dir: ["data", "state", "bundles", bundle_id].iter().collect(),
+            id: upload_id.to_string(),
+        }
+    }
+
+    /// The IDs end up in paths, anything but ours is unknown.
+    fn find(bundle_id: &str, upload_id: &str) -> Result<Self, ApiErrorResponse> {
+        if Uuid::parse_str(bundle_id).is_err() || Uuid::parse_str(upload_id).is_err() {
+            return Err(upload_not_found());
+        }
+
+        Ok(Self::new(bundle_id, upload_id))
+    }
+
+    fn part_path(&self) -> PathBuf {
+        self.bundle_dir.join(format!("{}{}", PART_PREFIX, self.id))
+    }
+
+    fn info_path(&self) -> PathBuf {
+        self.bundle_dir.join(format!("{}{}.json", PART_PREFIX, self.id))
+    }
+
+    async fn read_info(&self) -> Result<UploadInfo, ApiErrorResponse> {
+        let json = match fs::read(self.info_path()).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(upload_not_found()),
+            Err(e) => return Err(storage_error(e)),
+        };
+
+        serde_json::from_slice(&json).map_err(|e| {
+            ApiErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, "upload_info_invalid", "Failed reading the upload state")
+                .with_cause(&e.to_string())
+        })
+    }
+
+    async fn write_info(&self, info: &UploadInfo) -> Result<(), ApiErrorResponse> {
+        let json = serde_json::to_vec(info).map_err(std::io::Error::other).map_err(storage_error)?;
+        fs::write(self.info_path(), json).await.map_err(storage_error)
+    }
+
+    /// Bytes received so far.
+    async fn offset(&self, info: &UploadInfo) -> Result<u64, ApiErrorResponse> {
+        if info.completed {
+            return Ok(info.length);
+        }
+
+        match fs::metadata(self.part_path()).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(upload_not_found()),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    /// Moves the received file to its name in the bundle and lists it in meta.json. A file of that
+    /// name already in the bundle stays, the upload is kept as it is and can be terminated. The
+    /// part file goes last, until then a failed completion is tried again by the next PATCH.
+    async fn complete(&self, info: &mut UploadInfo) -> Result<(), ApiErrorResponse> {
+        let destination = self.bundle_dir.join(&info.file_name);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).await.map_err(storage_error)?;
+        }
+        // A rename would replace the existing file, the link fails instead.
+        match fs::hard_link(self.part_path(), &destination).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return Err(duplicate_file(&info.file_name)),
+            Err(e) => return Err(storage_error(e)),
+        }
+
+        if let Err(e) = self.add_to_meta(&info.file_name).await {
+            // Left there, the link would make the retry a duplicate.
+            if let Err(e) = fs::remove_file(&destination).await {
+                tracing::warn!("Failed removing {} after a failed upload: {}", destination.display(), e);
+            }
+            return Err(e);
+        }
+
+        info.completed = true;
+        self.write_info(info).await?;
+
+        if let Err(e) = fs::remove_file(self.part_path()).await {
+            tracing::warn!("Failed removing the part file of upload {}: {}", self.id, e);
+        }
+        Ok(())
+    }
+
+    async fn add_to_meta(&self, file_name: &str) -> Result<(), ApiErrorResponse> {
+        let file_meta = read_file_meta(&self.bundle_dir, file_name).await.map_err(storage_error)?;
+        add_to_bundle_meta(&self.bundle_dir, vec![file_meta]).await.map_err(|e| {
+            tracing::error!("Failed adding {} to the meta file of {}: {}", file_name, self.bundle_dir.display(), e);
+            ApiErrorResponse::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "meta_creation_failed",
+                "Failed updating the bundle meta file"
+            ).with_cause(&e.to_string())
+        })
+    }
+
+    fn lock(&self) -> Result<UploadLock, ApiErrorResponse> {
+        let mut active = ACTIVE_UPLOADS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !active.insert(self.id.clone()) {
+            return Err(ApiErrorResponse::new(
+                StatusCode::LOCKED,
+                "upload_locked",
+                "Another request is writing to this upload",
+            ));
+        }
+
+        Ok(UploadLock { id: self.id.clone() })
+    }
+}
+
+struct UploadLock {
+    id: String,
+}
+
+impl Drop for UploadLock {
+    fn drop(&mut self) {
+        ACTIVE_UPLOADS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&self.id);
+    }
+}
+
+/// `None` when the header is missing.
+fn header_u64(headers: &HeaderMap, name: &HeaderName) -> Option<Result<u64, ()>> {
+    headers.get(name).map(|value| {
+        value.to_str().ok()
+            .filter(|value| value.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|value| value.parse().ok())
+            .ok_or(())
+    })
+}
+
+fn duplicate_file(file_name: &str) -> ApiErrorResponse {
+    ApiErrorResponse::new(StatusCode::CONFLICT, "duplicate_file", "A file of that name is already in the bundle")
+        .with_cause(file_name)
+}
+
+fn upload_not_found() -> ApiErrorResponse {
+    ApiErrorResponse::new(StatusCode::NOT_FOUND, "upload_not_found", "Upload not found")
+}
+
+fn storage_error(e: std::io::Error) -> ApiErrorResponse {
+    tracing::error!("Upload storage failed: {}", e);
+    ApiErrorResponse::new(StatusCode::INTERNAL_SERVER_ERROR, "upload_storage_failed", "Failed storing the upload")
+        .with_cause(&e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn length(value: &str) -> Option<Result<u64, ()>> {
+        let mut headers = HeaderMap::new();
+        headers.insert(UPLOAD_LENGTH.clone(), HeaderValue::from_str(value).unwrap());
+        header_u64(&headers, &UPLOAD_LENGTH)
+    }
+
+    #[test]
+    fn lengths_are_parsed() {
+        assert_eq!(length("0"), Some(Ok(0)));
+        assert_eq!(length("1048576"), Some(Ok(1_048_576)));
+        assert_eq!(length("18446744073709551615"), Some(Ok(u64::MAX)));
+        assert_eq!(header_u64(&HeaderMap::new(), &UPLOAD_LENGTH), None);
+    }
+
+    #[test]
+    fn non_digit_lengths_are_refused() {
+        for value in ["", "abc", "-1", "+1", " 1", "1 ", "1.0", "0x10", "1e3"] {
+            assert_eq!(length(value), Some(Err(())), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn overflowing_lengths_are_refused() {
+        assert_eq!(length("18446744073709551616"), Some(Err(())));
+        assert_eq!(length(&"9".repeat(40)), Some(Err(())));
+    }
+
+    #[tokio::test]
+    async fn failed_completion_is_tried_again() {
+        let bundle_dir = std::env::temp_dir().join(format!("lima-upload-complete-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&bundle_dir);
+        let upload = Upload { bundle_dir: bundle_dir.clone(), id: Uuid::new_v4().to_string() };
+        std::fs::create_dir_all(&bundle_dir).unwrap();
+        std::fs::write(upload.part_path(), "solid lid").unwrap();
+        let mut info = UploadInfo { file_name: "stls/lid.stl".to_string(), length: 9, metadata: String::new(), completed: false };
+        upload.write_info(&info).await.unwrap();
+
+        // meta.json can't be read while it's a folder.
+        let meta_path = bundle_dir.join(lima_domain::models::bundle::BUNDLE_META_FILE);
+        std::fs::create_dir(&meta_path).unwrap();
+        let failed = upload.complete(&mut info).await;
+        let linked = bundle_dir.join("stls/lid.stl").exists();
+        let offset = upload.offset(&upload.read_info().await.unwrap()).await.ok();
+
+        std::fs::remove_dir(&meta_path).unwrap();
+        let retried = upload.complete(&mut info).await;
+        let part_left = upload.part_path().exists();
+        let completed = upload.read_info().await.unwrap().completed;
+        let stored = std::fs::read_to_string(bundle_dir.join("stls/lid.stl")).ok();
+        let _ = std::fs::remove_dir_all(&bundle_dir);
+
+        assert!(failed.is_err());
+        assert!(!linked, "the file only goes in the bundle with its meta entry");
+        assert_eq!(offset, Some(9));
+        assert!(retried.is_ok());
+        assert!(!part_left);
+        assert!(completed);
+        assert_eq!(stored.as_deref(), Some("solid lid"));
+    }
+}
@@ -0,0 +1,47 @@
+use axum::{
+    extract::Path,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+
+use crate::models::http_error::{ApiErrorResponse, ApiErrorBody};
+use super::{UPLOAD_LENGTH, UPLOAD_METADATA, UPLOAD_OFFSET, Upload};
+
+#[utoipa::path(
+    head,
+    path = "/api/uploads/{bundle_id}/{upload_id}",
+    params(
+        ("bundle_id" = String, Path, description = "The bundle the upload belongs to"),
+        ("upload_id" = String, Path, description = "The ID of the upload"),
+        ("Tus-Resumable" = String, Header, description = "`1.0.0`"),
+    ),
+    responses(
+        (status = 200, description = "Where to resume the upload", headers(
+            ("Upload-Offset" = u64, description = "Bytes received so far"),
+            ("Upload-Length" = u64, description = "Size of the file"),
+            ("Upload-Metadata" = String, description = "Metadata given on creation"),
+        )),
+        (status = 404, description = "Upload not found", body = ApiErrorBody),
+        (status = 412, description = "Unsupported tus version", body = ApiErrorBody),
+    )
+)]
+pub async fn upload_offset(
+    Path((bundle_id, upload_id)): Path<(String, String)>,
+) -> Result<Response, ApiErrorResponse> {
+    let upload = Upload::find(&bundle_id, &upload_id)?;
+    let info = upload.read_info().await?;
+    let offset = upload.offset(&info).await?;
+
+    let mut response = StatusCode::OK.into_response();
+    let headers = response.headers_mut();
+    headers.insert(UPLOAD_OFFSET.clone(), HeaderValue::from(offset));
+    headers.insert(UPLOAD_LENGTH.clone(), HeaderValue::from(info.length));
+    if let Ok(metadata) = HeaderValue::from_str(&info.metadata)
+        && !info.metadata.is_empty()
+    {
+        headers.insert(UPLOAD_METADATA.clone(), metadata);
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+
+    Ok(response)
+}
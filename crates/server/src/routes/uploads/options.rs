@@ -0,0 +1,24 @@
+use axum::{
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+
+use super::{TUS_EXTENSION, TUS_EXTENSIONS, TUS_VERSION, TUS_VERSION_HEADER};
+
+#[utoipa::path(
+    options,
+    path = "/api/uploads",
+    responses(
+        (status = 204, description = "tus versions and extensions supported", headers(
+            ("Tus-Version" = String, description = "Always `1.0.0`"),
+            ("Tus-Extension" = String, description = "`creation,termination`"),
+        )),
+    )
+)]
+pub async fn upload_options() -> Response {
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    let headers = response.headers_mut();
+    headers.insert(TUS_VERSION_HEADER.clone(), HeaderValue::from_static(TUS_VERSION));
+    headers.insert(TUS_EXTENSION.clone(), HeaderValue::from_static(TUS_EXTENSIONS));
+    response
+}
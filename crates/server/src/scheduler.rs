@@ -0,0 +1,104 @@
+use std::env;
+use std::time::Duration;
+
+use lima_domain::models::sync::{SyncMode, SyncOptions, SyncReason};
+use lima_indexer::sync::{DEFAULT_BUNDLE_TTL, SyncQueue};
+
+/// Intervals for the periodic jobs. `None` disables a job.
+#[derive(Debug, Clone)]
+pub struct ScheduleConfig {
+    pub incremental_sync: Option<Duration>,
+    pub full_sync: Option<Duration>,
+    pub bundle_gc: Option<Duration>,
+    pub bundle_ttl: Duration,
+    pub verify: Option<Duration>,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            // The watcher catches most changes, this is the safety net for network shares where it can't.
+            incremental_sync: Some(Duration::from_secs(60 * 60)),
+            full_sync: Some(Duration::from_secs(24 * 60 * 60)),
+            bundle_gc: Some(Duration::from_secs(60 * 60)),
+            bundle_ttl: DEFAULT_BUNDLE_TTL,
+            verify: Some(Duration::from_secs(7 * 24 * 60 * 60)),
+        }
+    }
+}
+
+impl ScheduleConfig {
+    /// Every value is in seconds, `0` disables the job.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            incremental_sync: interval_from_env("LIMA_SYNC_INTERVAL_SECS", defaults.incremental_sync),
+            full_sync: interval_from_env("LIMA_FULL_SYNC_INTERVAL_SECS", defaults.full_sync),
+            bundle_gc: interval_from_env("LIMA_BUNDLE_GC_INTERVAL_SECS", defaults.bundle_gc),
+            bundle_ttl: secs_from_env("LIMA_BUNDLE_TTL_SECS").map(Duration::from_secs).unwrap_or(defaults.bundle_ttl),
+            verify: interval_from_env("LIMA_VERIFY_INTERVAL_SECS", defaults.verify),
+        }
+    }
+}
+
+fn secs_from_env(name: &str) -> Option<u64> {
+    let value = env::var(name).ok()?;
+
+    match value.parse() {
+        Ok(secs) => Some(secs),
+        Err(_) => {
+            tracing::warn!("Ignoring {}={}, expected a number of seconds", name, value);
+            None
+        }
+    }
+}
+
+fn interval_from_env(name: &str, default: Option<Duration>) -> Option<Duration> {
+    match secs_from_env(name) {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => default,
+    }
+}
+
+/// Queues the periodic jobs with reason `scheduled`. The worker runs them like any other run, so they
+/// are logged in `sync_runs` and a job that is still waiting from the previous tick is not queued twice.
+pub fn start_scheduler(queue: SyncQueue, config: ScheduleConfig) {
+    let bundle_gc_options = SyncOptions {
+        bundle_ttl_secs: Some(config.bundle_ttl.as_secs()),
+        ..SyncOptions::default()
+    };
+
+    let jobs = [
+        (SyncMode::Incremental, config.incremental_sync, SyncOptions::default()),
+        (SyncMode::Full, config.full_sync, SyncOptions::default()),
+        (SyncMode::BundleGc, config.bundle_gc, bundle_gc_options),
+        (SyncMode::Verify, config.verify, SyncOptions::default()),
+    ];
+
+    for (mode, every, options) in jobs {
+        let Some(every) = every else {
+            tracing::info!("Scheduled {} runs are disabled", mode.as_str());
+            continue;
+        };
+
+        tracing::info!("Scheduling {} runs every {}s", mode.as_str(), every.as_secs());
+        tokio::spawn(schedule(queue.clone(), mode, every, options));
+    }
+}
+
+async fn schedule(queue: SyncQueue, mode: SyncMode, every: Duration, options: SyncOptions) {
+    // First run after one interval, startup already has enough going on.
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+    // A suspended machine shouldn't fire all the missed ticks at once when it wakes up.
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        ticker.tick().await;
+
+        if let Err(e) = queue.enqueue(mode, SyncReason::Scheduled, &options).await {
+            tracing::error!("Failed to queue scheduled {} run: {}", mode.as_str(), e);
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use lima_db::Db;
+use lima_indexer::ignore_rules::IgnoreRules;
+use lima_indexer::sync::SyncQueue;
+use lima_indexer::thumbnails::ThumbnailStore;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Arc<Db>,
+    pub sync: SyncQueue,
+    pub ignore: IgnoreRules,
+    pub thumbnails: ThumbnailStore,
 }
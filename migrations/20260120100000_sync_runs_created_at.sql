@@ -0,0 +1,7 @@
+-- queued runs have no started_at yet, so we need our own column for ordering and cursors.
+ALTER TABLE sync_runs ADD COLUMN created_at TEXT NOT NULL DEFAULT '';   -- RFC3339
+
+UPDATE sync_runs SET created_at = COALESCE(started_at, '') WHERE created_at = '';
+
+CREATE INDEX IF NOT EXISTS idx_sync_runs_created_id ON sync_runs(created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_sync_runs_status ON sync_runs(`status`);
@@ -0,0 +1,47 @@
+-- Scheduled maintenance (bundle GC, hash verification) is recorded as sync runs too.
+-- SQLite can't alter a CHECK constraint so the table is rebuilt. sync_events goes first so the
+-- cascade on sync_runs doesn't wipe the event log.
+
+CREATE TABLE sync_events_backup AS SELECT id, run_id, created_at, `level`, kind, data_json FROM sync_events;
+DROP TABLE sync_events;
+
+CREATE TABLE sync_runs_new (
+  id TEXT PRIMARY KEY,                      -- UUID
+  mode TEXT NOT NULL CHECK (mode IN ('full','incremental','bundle_gc','verify')),
+  reason TEXT NOT NULL CHECK (reason IN ('manual','scheduled','watcher')),
+  `status` TEXT NOT NULL CHECK (`status` IN ('queued','running','complete','failed','cancelled')),
+  options_json TEXT NOT NULL DEFAULT '{}',
+  stats_json TEXT NOT NULL DEFAULT '{}',
+  started_at TEXT NULL,                    -- RFC3339
+  finished_at TEXT NULL,                   -- RFC3339
+  error TEXT NOT NULL DEFAULT '',
+  created_at TEXT NOT NULL DEFAULT ''      -- RFC3339
+);
+
+INSERT INTO sync_runs_new (id, mode, reason, `status`, options_json, stats_json, started_at, finished_at, error, created_at)
+SELECT id, mode, reason, `status`, options_json, stats_json, started_at, finished_at, error, created_at FROM sync_runs;
+
+DROP TABLE sync_runs;
+ALTER TABLE sync_runs_new RENAME TO sync_runs;
+
+CREATE INDEX IF NOT EXISTS idx_sync_runs_started_at ON sync_runs(started_at);
+CREATE INDEX IF NOT EXISTS idx_sync_runs_created_id ON sync_runs(created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_sync_runs_status ON sync_runs(`status`);
+
+CREATE TABLE IF NOT EXISTS sync_events (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  run_id TEXT NOT NULL,                     -- FK -> sync_runs(id)
+  created_at TEXT NOT NULL,                 -- RFC3339
+  `level` TEXT NOT NULL CHECK (`level` IN ('trace','debug','info','warn','error')),
+  kind TEXT NOT NULL,                       -- e.g., project_upsert, hash_failed, etc.
+  data_json TEXT NOT NULL DEFAULT '{}',
+  FOREIGN KEY(run_id) REFERENCES sync_runs(id) ON DELETE CASCADE
+);
+
+INSERT INTO sync_events (id, run_id, created_at, `level`, kind, data_json)
+SELECT id, run_id, created_at, `level`, kind, data_json FROM sync_events_backup;
+
+DROP TABLE sync_events_backup;
+
+CREATE INDEX IF NOT EXISTS idx_sync_events_run_id ON sync_events(run_id);
+CREATE INDEX IF NOT EXISTS idx_sync_events_ts ON sync_events(created_at);
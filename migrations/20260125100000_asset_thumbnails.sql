@@ -0,0 +1,3 @@
+-- Hash of the file the current thumbnails were generated from. NULL until they exist, a value that
+-- differs from file_hash means they are stale.
+ALTER TABLE assets ADD COLUMN thumbnail_hash TEXT NULL;
@@ -0,0 +1,4 @@
+-- Metadata read from inside the file (3MF for now), as JSON. metadata_hash is the file_hash it was
+-- read from, same idea as thumbnail_hash.
+ALTER TABLE assets ADD COLUMN metadata_json TEXT NULL;
+ALTER TABLE assets ADD COLUMN metadata_hash TEXT NULL;
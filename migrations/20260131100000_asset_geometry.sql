@@ -0,0 +1,20 @@
+-- Measurements of model assets, one row per asset. file_hash is the version of the file they were
+-- computed from, a row whose hash differs from assets.file_hash is stale.
+CREATE TABLE IF NOT EXISTS asset_geometry (
+  asset_id TEXT PRIMARY KEY,                -- FK -> assets(id)
+  file_hash TEXT NOT NULL,
+  unit TEXT NULL,                           -- as declared by the file; lengths below are in mm
+  min_x REAL NOT NULL,
+  min_y REAL NOT NULL,
+  min_z REAL NOT NULL,
+  max_x REAL NOT NULL,
+  max_y REAL NOT NULL,
+  max_z REAL NOT NULL,
+  triangle_count INTEGER NOT NULL,
+  vertex_count INTEGER NOT NULL,
+  surface_area REAL NOT NULL,               -- mm²
+  volume REAL NOT NULL,                     -- mm³
+  watertight INTEGER NOT NULL,              -- 0/1
+  computed_at TEXT NOT NULL,                -- RFC3339
+  FOREIGN KEY(asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- Printer profiles, used to find models that fit a build volume.
+CREATE TABLE IF NOT EXISTS printers (
+  id TEXT PRIMARY KEY,                      -- UUID
+  `name` TEXT NOT NULL UNIQUE,
+  build_x REAL NOT NULL,                    -- mm
+  build_y REAL NOT NULL,                    -- mm
+  build_z REAL NOT NULL,                    -- mm
+  created_at TEXT NOT NULL,                 -- RFC3339
+  updated_at TEXT NOT NULL                  -- RFC3339
+);
@@ -0,0 +1,36 @@
+-- More asset kinds: cad_source, document, video and archive. SQLite can't alter a CHECK constraint
+-- so the table is rebuilt. asset_geometry is kept aside first, the cascade on assets would wipe it.
+-- Rows keep their kind here, the next sync reclassifies them from their extension.
+
+CREATE TABLE asset_geometry_backup AS SELECT * FROM asset_geometry;
+
+CREATE TABLE assets_new (
+  id TEXT PRIMARY KEY,                      -- UUID
+  project_id TEXT NOT NULL,
+  file_path TEXT NOT NULL,                  -- relative to project folder
+  kind TEXT NOT NULL CHECK (kind IN ('model','cad_source','gcode','image','document','video','archive','other')),
+  size_bytes INTEGER NOT NULL,
+  mtime TEXT NOT NULL,                      -- RFC3339 (from fs metadata)
+  mime TEXT NOT NULL DEFAULT '',
+  file_hash TEXT NULL,                      -- hex; null until computed
+  created_at TEXT NOT NULL,                 -- RFC3339
+  updated_at TEXT NOT NULL,                 -- RFC3339
+  thumbnail_hash TEXT NULL,
+  metadata_json TEXT NULL,
+  metadata_hash TEXT NULL,
+  UNIQUE(project_id, file_path),
+  FOREIGN KEY(project_id) REFERENCES projects(id) ON DELETE CASCADE
+);
+
+INSERT INTO assets_new (id, project_id, file_path, kind, size_bytes, mtime, mime, file_hash, created_at, updated_at, thumbnail_hash, metadata_json, metadata_hash)
+SELECT id, project_id, file_path, kind, size_bytes, mtime, mime, file_hash, created_at, updated_at, thumbnail_hash, metadata_json, metadata_hash
+FROM assets;
+
+DROP TABLE assets;
+ALTER TABLE assets_new RENAME TO assets;
+
+CREATE INDEX IF NOT EXISTS idx_assets_project_kind ON assets(project_id, kind);
+
+DELETE FROM asset_geometry;
+INSERT INTO asset_geometry SELECT * FROM asset_geometry_backup;
+DROP TABLE asset_geometry_backup;